@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+
+use crate::mempool_client::MempoolTx;
+
+// An address on the watch list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedAddress {
+    pub address: String,
+    #[serde(default)]
+    pub label: String,
+}
+
+impl WatchedAddress {
+    /// The label, or a shortened address when no label was given
+    pub fn display_name(&self) -> String {
+        if !self.label.is_empty() {
+            self.label.clone()
+        } else {
+            shorten_address(&self.address)
+        }
+    }
+}
+
+// Latest balance of a watched address
+#[derive(Debug, Clone, Default)]
+pub struct AddressBalance {
+    // Satoshis
+    pub confirmed: i64,
+    pub unconfirmed: i64,
+    pub tx_count: u32,
+    pub last_error: Option<String>,
+}
+
+/// Remembers the transactions seen per address to detect new ones
+pub struct AddressWatcher {
+    seen: HashMap<String, HashSet<String>>,
+}
+
+impl AddressWatcher {
+    pub fn new() -> Self {
+        AddressWatcher { seen: HashMap::new() }
+    }
+
+    /// Record fetched transactions of `address` and return those not seen before.
+    /// The first fetch of an address only remembers its history.
+    pub fn new_transactions<'a>(&mut self, address: &str, txs: &'a [MempoolTx]) -> Vec<&'a MempoolTx> {
+        let txids: Vec<&str> = txs.iter().map(|tx| tx.txid.as_str()).collect();
+        let new_txids = self.new_txids(address, &txids);
+        txs.iter()
+            .filter(|tx| new_txids.contains(&tx.txid.as_str()))
+            .collect()
+    }
+
+    /// Like `new_transactions`, for backends that only report txids
+    pub fn new_txids<'a>(&mut self, address: &str, txids: &[&'a str]) -> Vec<&'a str> {
+        let first_fetch = !self.seen.contains_key(address);
+        let seen = self.seen.entry(address.to_string()).or_default();
+
+        let new_txids: Vec<&str> = txids.iter()
+            .copied()
+            .filter(|txid| seen.insert(txid.to_string()))
+            .collect();
+
+        if first_fetch {
+            Vec::new()
+        } else {
+            new_txids
+        }
+    }
+
+    /// Forget addresses that are no longer watched
+    pub fn retain(&mut self, watched: &[WatchedAddress]) {
+        self.seen.retain(|address, _| watched.iter().any(|w| &w.address == address));
+    }
+}
+
+// Shorten an address for display, e.g. bc1qxy2k…0wlh
+pub fn shorten_address(address: &str) -> String {
+    if address.len() <= 16 || !address.is_ascii() {
+        address.to_string()
+    } else {
+        format!("{}…{}", &address[..8], &address[address.len() - 4..])
+    }
+}
+
+// Format satoshis as BTC with all eight decimals
+pub fn format_btc(sats: i64) -> String {
+    let sign = if sats < 0 { "-" } else { "" };
+    let sats = sats.unsigned_abs();
+    format!("{}{}.{:08} BTC", sign, sats / 100_000_000, sats % 100_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+    #[test]
+    fn first_poll_only_remembers_history() {
+        let mut watcher = AddressWatcher::new();
+
+        assert!(watcher.new_txids(ADDRESS, &["a", "b"]).is_empty());
+        // Nothing changed since
+        assert!(watcher.new_txids(ADDRESS, &["a", "b"]).is_empty());
+    }
+
+    #[test]
+    fn later_polls_report_only_unseen_txids() {
+        let mut watcher = AddressWatcher::new();
+        watcher.new_txids(ADDRESS, &["a", "b"]);
+
+        assert_eq!(watcher.new_txids(ADDRESS, &["c", "a", "b"]), vec!["c"]);
+        assert_eq!(watcher.new_txids(ADDRESS, &["d", "c", "e", "a"]), vec!["d", "e"]);
+        assert!(watcher.new_txids(ADDRESS, &["e"]).is_empty());
+    }
+
+    #[test]
+    fn an_empty_first_poll_still_seeds_the_address() {
+        let mut watcher = AddressWatcher::new();
+
+        assert!(watcher.new_txids(ADDRESS, &[]).is_empty());
+        assert_eq!(watcher.new_txids(ADDRESS, &["a"]), vec!["a"]);
+    }
+
+    #[test]
+    fn tracks_addresses_separately() {
+        let mut watcher = AddressWatcher::new();
+        watcher.new_txids(ADDRESS, &["a"]);
+
+        // The same txid on a newly watched address is part of its history
+        assert!(watcher.new_txids("1BoatSLRHtKNngkdXEeobR76b53LETtpyT", &["a"]).is_empty());
+        assert_eq!(watcher.new_txids(ADDRESS, &["b"]), vec!["b"]);
+    }
+
+    #[test]
+    fn forgotten_addresses_are_seeded_again() {
+        let mut watcher = AddressWatcher::new();
+        watcher.new_txids(ADDRESS, &["a"]);
+
+        watcher.retain(&[]);
+        assert!(watcher.new_txids(ADDRESS, &["a", "b"]).is_empty());
+    }
+
+    #[test]
+    fn formats_addresses_and_amounts() {
+        assert_eq!(shorten_address(ADDRESS), "bc1qxy2k…0wlh");
+        assert_eq!(format_btc(-150_000), "-0.00150000 BTC");
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::VecDeque;
+use serde::{Serialize, Deserialize};
+
+use crate::mempool_client::MempoolFeeEstimate;
+use crate::price_provider::QuoteCurrency;
+
+// Condition a price alert watches for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceAlertKind {
+    // Price rises to or above the level
+    Above { level: f64 },
+    // Price falls to or below the level
+    Below { level: f64 },
+    // Price moves by at least `percent` (up or down) within the window
+    PercentMove { percent: f64, window_minutes: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceAlert {
+    pub kind: PriceAlertKind,
+    pub enabled: bool,
+}
+
+impl PriceAlert {
+    pub fn describe(&self, currency: QuoteCurrency) -> String {
+        match self.kind {
+            PriceAlertKind::Above { level } => format!("Price above {}", currency.format_price(level)),
+            PriceAlertKind::Below { level } => format!("Price below {}", currency.format_price(level)),
+            PriceAlertKind::PercentMove { percent, window_minutes } => {
+                format!("Price moves {:.1}% within {} min", percent, window_minutes)
+            },
+        }
+    }
+}
+
+// A triggered alert, shown as a notification and an in-app banner
+#[derive(Debug, Clone)]
+pub struct AlertEvent {
+    pub title: String,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+/// Evaluates price alerts against incoming prices. Once an alert fires it is
+/// disarmed until the price moves back past the level by the hysteresis margin,
+/// so it doesn't re-fire on every tick around the level.
+pub struct PriceAlertEvaluator {
+    // Armed flag for each alert, remembered by the alert's definition
+    armed: Vec<(PriceAlert, bool)>,
+    // Recent (timestamp, price) samples for percent-move windows
+    samples: VecDeque<(i64, f64)>,
+}
+
+impl PriceAlertEvaluator {
+    pub fn new() -> Self {
+        PriceAlertEvaluator {
+            armed: Vec::new(),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Evaluate all enabled alerts for a new price and return the ones that fired
+    pub fn evaluate(
+        &mut self,
+        alerts: &[PriceAlert],
+        timestamp: i64,
+        price: f64,
+        hysteresis_percent: f64,
+        currency: QuoteCurrency,
+    ) -> Vec<AlertEvent> {
+        self.record_sample(alerts, timestamp, price);
+
+        let hysteresis = hysteresis_percent / 100.0;
+        let mut previous = std::mem::take(&mut self.armed);
+        let mut events = Vec::new();
+
+        for alert in alerts {
+            // Carry over the armed state of alerts that are unchanged
+            let remembered = previous.iter()
+                .position(|(known, _)| known == alert)
+                .map(|index| previous.remove(index).1);
+
+            let armed = match alert.kind {
+                PriceAlertKind::Above { level } => {
+                    // New alerts only fire on an actual crossing
+                    let armed = remembered.unwrap_or(price < level);
+                    if armed && price >= level {
+                        false
+                    } else {
+                        armed || price < level * (1.0 - hysteresis)
+                    }
+                },
+                PriceAlertKind::Below { level } => {
+                    let armed = remembered.unwrap_or(price > level);
+                    if armed && price <= level {
+                        false
+                    } else {
+                        armed || price > level * (1.0 + hysteresis)
+                    }
+                },
+                PriceAlertKind::PercentMove { percent, window_minutes } => {
+                    let armed = remembered.unwrap_or(true);
+                    let change = self.percent_change(timestamp, price, window_minutes);
+                    if armed && change.abs() >= percent {
+                        false
+                    } else {
+                        armed || change.abs() < (percent - hysteresis_percent).max(0.0)
+                    }
+                },
+            };
+
+            let fired = alert.enabled && remembered.unwrap_or(false) && !armed;
+            if fired {
+                events.push(AlertEvent {
+                    title: alert.describe(currency),
+                    message: self.fired_message(alert, timestamp, price, currency),
+                    timestamp,
+                });
+            }
+
+            self.armed.push((alert.clone(), armed));
+        }
+
+        events
+    }
+
+    // Keep samples for the longest percent-move window
+    fn record_sample(&mut self, alerts: &[PriceAlert], timestamp: i64, price: f64) {
+        let longest_window = alerts.iter()
+            .filter_map(|alert| match alert.kind {
+                PriceAlertKind::PercentMove { window_minutes, .. } => Some(window_minutes as i64 * 60),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        self.samples.push_back((timestamp, price));
+        while let Some(&(oldest, _)) = self.samples.front() {
+            if timestamp - oldest > longest_window {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Percent change from the oldest sample inside the window to `price`
+    fn percent_change(&self, timestamp: i64, price: f64, window_minutes: u32) -> f64 {
+        let window_start = timestamp - window_minutes as i64 * 60;
+        self.samples.iter()
+            .find(|(sample_time, _)| *sample_time >= window_start)
+            .filter(|(_, start_price)| *start_price > 0.0)
+            .map(|(_, start_price)| (price - start_price) / start_price * 100.0)
+            .unwrap_or(0.0)
+    }
+
+    fn fired_message(&self, alert: &PriceAlert, timestamp: i64, price: f64, currency: QuoteCurrency) -> String {
+        match alert.kind {
+            PriceAlertKind::PercentMove { window_minutes, .. } => format!(
+                "BTC moved {:+.2}% in the last {} min, now {}",
+                self.percent_change(timestamp, price, window_minutes),
+                window_minutes,
+                currency.format_price(price)
+            ),
+            _ => format!("BTC is now {}", currency.format_price(price)),
+        }
+    }
+}
+
+// Fee estimate a fee alert watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeTarget {
+    Fastest,
+    HalfHour,
+    Hour,
+    Economy,
+}
+
+impl FeeTarget {
+    pub const ALL: [FeeTarget; 4] = [FeeTarget::Fastest, FeeTarget::HalfHour, FeeTarget::Hour, FeeTarget::Economy];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeeTarget::Fastest => "Fastest",
+            FeeTarget::HalfHour => "30m",
+            FeeTarget::Hour => "1h",
+            FeeTarget::Economy => "Economy",
+        }
+    }
+
+    // Pick this target's rate from a set of fee estimates
+    pub fn rate(&self, fees: &MempoolFeeEstimate) -> u32 {
+        match self {
+            FeeTarget::Fastest => fees.fastest_fee,
+            FeeTarget::HalfHour => fees.half_hour_fee,
+            FeeTarget::Hour => fees.hour_fee,
+            FeeTarget::Economy => fees.economy_fee,
+        }
+    }
+}
+
+// Fires once a fee estimate has stayed at or below `max_fee` for `min_minutes`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeAlert {
+    pub target: FeeTarget,
+    pub max_fee: u32,
+    pub min_minutes: u32,
+    pub enabled: bool,
+}
+
+impl FeeAlert {
+    pub fn describe(&self) -> String {
+        format!("{} fee ≤ {} sat/vB for {} min", self.target.name(), self.max_fee, self.min_minutes)
+    }
+}
+
+// Low-fee window of a single fee alert
+struct FeeWindow {
+    alert: FeeAlert,
+    // When the fee first dropped to or below the alert's limit
+    below_since: Option<i64>,
+    fired: bool,
+}
+
+/// Evaluates fee alerts against polled fee estimates. An alert fires once per
+/// low-fee window and re-arms when the fee rises above its limit again.
+pub struct FeeAlertEvaluator {
+    windows: Vec<FeeWindow>,
+}
+
+impl FeeAlertEvaluator {
+    pub fn new() -> Self {
+        FeeAlertEvaluator { windows: Vec::new() }
+    }
+
+    /// Evaluate all enabled fee alerts for new estimates and return the ones that fired
+    pub fn evaluate(&mut self, alerts: &[FeeAlert], timestamp: i64, fees: &MempoolFeeEstimate) -> Vec<AlertEvent> {
+        let mut previous = std::mem::take(&mut self.windows);
+        let mut events = Vec::new();
+
+        for alert in alerts {
+            // Carry over the window of alerts that are unchanged
+            let mut window = match previous.iter().position(|window| &window.alert == alert) {
+                Some(index) => previous.remove(index),
+                None => FeeWindow { alert: alert.clone(), below_since: None, fired: false },
+            };
+
+            let rate = alert.target.rate(fees);
+            if rate <= alert.max_fee {
+                let since = *window.below_since.get_or_insert(timestamp);
+                if alert.enabled && !window.fired && timestamp - since >= alert.min_minutes as i64 * 60 {
+                    window.fired = true;
+                    events.push(AlertEvent {
+                        title: alert.describe(),
+                        message: format!(
+                            "{} fee has been at or below {} sat/vB for {} min (now {} sat/vB)",
+                            alert.target.name(),
+                            alert.max_fee,
+                            (timestamp - since) / 60,
+                            rate
+                        ),
+                        timestamp,
+                    });
+                }
+            } else {
+                window.below_since = None;
+                window.fired = false;
+            }
+
+            self.windows.push(window);
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HYSTERESIS: f64 = 1.0;
+
+    fn alert(kind: PriceAlertKind) -> PriceAlert {
+        PriceAlert { kind, enabled: true }
+    }
+
+    // Feed prices one minute apart and return how many alerts fired on each
+    fn run(evaluator: &mut PriceAlertEvaluator, alerts: &[PriceAlert], prices: &[f64]) -> Vec<usize> {
+        prices.iter()
+            .enumerate()
+            .map(|(i, &price)| evaluator.evaluate(alerts, i as i64 * 60, price, HYSTERESIS, QuoteCurrency::Usd).len())
+            .collect()
+    }
+
+    #[test]
+    fn fires_when_crossing_above() {
+        let alerts = [alert(PriceAlertKind::Above { level: 100.0 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        assert_eq!(run(&mut evaluator, &alerts, &[98.0, 99.5, 100.0]), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn fires_when_crossing_below() {
+        let alerts = [alert(PriceAlertKind::Below { level: 100.0 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        let events = evaluator.evaluate(&alerts, 0, 101.0, HYSTERESIS, QuoteCurrency::Usd);
+        assert!(events.is_empty());
+        let events = evaluator.evaluate(&alerts, 60, 99.0, HYSTERESIS, QuoteCurrency::Usd);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Price below $100.00");
+        assert_eq!(events[0].message, "BTC is now $99.00");
+    }
+
+    #[test]
+    fn does_not_refire_past_the_level() {
+        let alerts = [alert(PriceAlertKind::Above { level: 100.0 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        assert_eq!(run(&mut evaluator, &alerts, &[99.0, 101.0, 102.0, 100.5, 105.0]), vec![0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rearms_only_after_moving_back_by_the_hysteresis() {
+        let alerts = [alert(PriceAlertKind::Above { level: 100.0 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        // Dipping to 99.5 is within 1% of the level, so going back above doesn't fire
+        assert_eq!(run(&mut evaluator, &alerts, &[99.0, 101.0, 99.5, 100.5]), vec![0, 1, 0, 0]);
+        // Falling below 99 re-arms it
+        assert_eq!(run(&mut evaluator, &alerts, &[98.9, 100.2]), vec![0, 1]);
+    }
+
+    #[test]
+    fn rearms_below_alerts_above_the_level() {
+        let alerts = [alert(PriceAlertKind::Below { level: 100.0 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        assert_eq!(run(&mut evaluator, &alerts, &[102.0, 99.0, 100.8, 99.0, 101.1, 99.9]), vec![0, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn first_sample_does_not_fire() {
+        let alerts = [
+            alert(PriceAlertKind::Above { level: 100.0 }),
+            alert(PriceAlertKind::Below { level: 200.0 }),
+            alert(PriceAlertKind::PercentMove { percent: 5.0, window_minutes: 60 }),
+        ];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        // Already past both levels, but no crossing was seen
+        assert_eq!(run(&mut evaluator, &alerts, &[150.0, 151.0]), vec![0, 0]);
+    }
+
+    #[test]
+    fn new_alert_waits_for_a_crossing() {
+        let mut evaluator = PriceAlertEvaluator::new();
+        run(&mut evaluator, &[], &[150.0]);
+
+        // Added while the price is already above the level
+        let alerts = [alert(PriceAlertKind::Above { level: 100.0 })];
+        assert_eq!(run(&mut evaluator, &alerts, &[150.0, 160.0]), vec![0, 0]);
+    }
+
+    #[test]
+    fn fires_on_percent_move_within_window() {
+        let alerts = [alert(PriceAlertKind::PercentMove { percent: 5.0, window_minutes: 10 })];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        assert_eq!(run(&mut evaluator, &alerts, &[100.0, 102.0, 105.5, 106.0]), vec![0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn disabled_alerts_stay_quiet() {
+        let alerts = [PriceAlert { kind: PriceAlertKind::Above { level: 100.0 }, enabled: false }];
+        let mut evaluator = PriceAlertEvaluator::new();
+
+        assert_eq!(run(&mut evaluator, &alerts, &[99.0, 101.0]), vec![0, 0]);
+    }
+
+    fn fee_alert(max_fee: u32, min_minutes: u32) -> FeeAlert {
+        FeeAlert { target: FeeTarget::HalfHour, max_fee, min_minutes, enabled: true }
+    }
+
+    // Feed half-hour fees one minute apart and return how many alerts fired on each
+    fn run_fees(evaluator: &mut FeeAlertEvaluator, alerts: &[FeeAlert], start_minute: i64, rates: &[u32]) -> Vec<usize> {
+        rates.iter()
+            .enumerate()
+            .map(|(i, &rate)| {
+                let fees = MempoolFeeEstimate { fastest_fee: rate + 5, half_hour_fee: rate, hour_fee: rate, economy_fee: 1 };
+                evaluator.evaluate(alerts, (start_minute + i as i64) * 60, &fees).len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fee_alert_waits_for_the_minimum_duration() {
+        let alerts = [fee_alert(10, 3)];
+        let mut evaluator = FeeAlertEvaluator::new();
+
+        // Low for minutes 1-3 is only two minutes, the fourth low sample makes three
+        assert_eq!(run_fees(&mut evaluator, &alerts, 0, &[12, 10, 8, 9, 7, 6]), vec![0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn fee_alert_fires_at_once_without_a_minimum_duration() {
+        let alerts = [fee_alert(10, 0)];
+        let mut evaluator = FeeAlertEvaluator::new();
+
+        assert_eq!(run_fees(&mut evaluator, &alerts, 0, &[10, 9]), vec![1, 0]);
+    }
+
+    #[test]
+    fn fee_alert_window_restarts_when_fees_rise_early() {
+        let alerts = [fee_alert(10, 2)];
+        let mut evaluator = FeeAlertEvaluator::new();
+
+        // The spike at minute 2 resets the window before it reaches two minutes
+        assert_eq!(run_fees(&mut evaluator, &alerts, 0, &[5, 5, 11, 5, 5, 5]), vec![0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn fee_alert_rearms_after_fees_rise() {
+        let alerts = [fee_alert(10, 1)];
+        let mut evaluator = FeeAlertEvaluator::new();
+
+        assert_eq!(run_fees(&mut evaluator, &alerts, 0, &[5, 5, 5]), vec![0, 1, 0]);
+        assert_eq!(run_fees(&mut evaluator, &alerts, 3, &[20, 5, 5]), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn fee_alert_message_reports_the_window() {
+        let alerts = [fee_alert(10, 2)];
+        let mut evaluator = FeeAlertEvaluator::new();
+        let fees = MempoolFeeEstimate { fastest_fee: 12, half_hour_fee: 8, hour_fee: 6, economy_fee: 2 };
+
+        assert!(evaluator.evaluate(&alerts, 0, &fees).is_empty());
+        let events = evaluator.evaluate(&alerts, 150, &fees);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "30m fee ≤ 10 sat/vB for 2 min");
+        assert_eq!(events[0].message, "30m fee has been at or below 10 sat/vB for 2 min (now 8 sat/vB)");
+    }
+
+    #[test]
+    fn disabled_fee_alerts_stay_quiet() {
+        let alerts = [FeeAlert { enabled: false, ..fee_alert(10, 0) }];
+        let mut evaluator = FeeAlertEvaluator::new();
+
+        assert_eq!(run_fees(&mut evaluator, &alerts, 0, &[5, 5, 5]), vec![0, 0, 0]);
+    }
+}
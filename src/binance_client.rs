@@ -0,0 +1,196 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize};
+
+use crate::price_provider::{ChartTimeframe, PriceCandle, PriceProvider, PriceSource, QuoteCurrency, parse_price_field};
+use crate::proxy::{HttpClient, ProxySource};
+
+// Default Binance API URL
+pub const DEFAULT_BINANCE_API_URL: &str = "https://api.binance.com/api/v3";
+
+#[derive(Debug, Deserialize)]
+pub struct BinanceTicker {
+    pub price: String,
+}
+
+// Binance API client for handling all API interactions
+pub struct BinanceClient {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl BinanceClient {
+    /// Create a new BinanceClient with the default Binance API URL
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_BINANCE_API_URL)
+    }
+
+    /// Create a new BinanceClient with a custom API URL (e.g. a local mock server)
+    pub fn with_url(base_url: &str) -> Self {
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)); // 10 second timeout
+        let client = HttpClient::build(ProxySource::Exchanges, base_url, builder);
+
+        BinanceClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+// Binance market symbol for BTC in the given currency
+fn symbol(currency: QuoteCurrency) -> Result<String> {
+    PriceSource::Binance.ensure_supports(currency)?;
+    match currency {
+        // Binance has no BTC/USD spot market, USDT is the closest equivalent
+        QuoteCurrency::Usd => Ok("BTCUSDT".to_string()),
+        _ => Ok(format!("BTC{}", currency.code())),
+    }
+}
+
+// Map a candle step in seconds to a Binance kline interval
+fn kline_interval(step: u32) -> Result<&'static str> {
+    match step {
+        3600 => Ok("1h"),
+        14400 => Ok("4h"),
+        86400 => Ok("1d"),
+        _ => Err(anyhow!("Binance does not support a {} second step", step)),
+    }
+}
+
+impl PriceProvider for BinanceClient {
+    fn name(&self) -> &'static str {
+        "Binance"
+    }
+
+    fn fetch_current_price(&self, currency: QuoteCurrency) -> Result<f64> {
+        let url = format!("{}/ticker/price?symbol={}", self.base_url, symbol(currency)?);
+
+        println!("Fetching current BTC price from: {}", url);
+
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch price: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+
+        let ticker: BinanceTicker = response.json()
+            .map_err(|e| anyhow!("Failed to parse price response: {}", e))?;
+
+        parse_price_field(&ticker.price, "price")
+    }
+
+    fn fetch_historical_prices(&self, timeframe: ChartTimeframe, currency: QuoteCurrency) -> Result<Vec<PriceCandle>> {
+        let (step, limit) = timeframe.api_params();
+
+        let url = format!("{}/klines?symbol={}&interval={}&limit={}",
+                          self.base_url, symbol(currency)?, kline_interval(step)?, limit);
+        println!("Fetching historical data from: {} ({})", url, timeframe.description());
+
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch historical data: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Historical API returned error status: {}", response.status()));
+        }
+
+        // Each row is [open time (ms), open, high, low, close, volume, close time, ...]
+        let rows: Vec<Vec<serde_json::Value>> = response.json()
+            .map_err(|e| anyhow!("Failed to parse historical data: {}", e))?;
+
+        let mut candles = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            if row.len() < 5 {
+                return Err(anyhow!("Unexpected Binance kline row: {:?}", row));
+            }
+
+            let open_time = row[0].as_i64()
+                .ok_or_else(|| anyhow!("Unexpected Binance kline open time: {}", row[0]))?;
+            let field = |index: usize, name: &str| -> Result<f64> {
+                let value = row[index].as_str()
+                    .ok_or_else(|| anyhow!("Unexpected Binance {} value: {}", name, row[index]))?;
+                parse_price_field(value, name)
+            };
+
+            candles.push(PriceCandle {
+                timestamp: open_time / 1000,
+                open: field(1, "open")?,
+                high: field(2, "high")?,
+                low: field(3, "low")?,
+                close: field(4, "close")?,
+            });
+        }
+
+        println!("Successfully parsed historical data for {} ({} candles)",
+                timeframe.description(), candles.len());
+
+        Ok(candles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn parses_ticker() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/ticker/price?symbol=BTCUSDT");
+            (200, r#"{"symbol": "BTCUSDT", "price": "64999.99000000"}"#.to_string())
+        });
+
+        let price = BinanceClient::with_url(&url).fetch_current_price(QuoteCurrency::Usd).unwrap();
+        assert_eq!(price, 64999.99);
+    }
+
+    #[test]
+    fn parses_klines() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/klines?symbol=BTCEUR&interval=4h&limit=42");
+            let body = r#"[
+                [1700006400000, "100.00", "110.00", "90.00", "105.00", "12.5", 1700020799999, "0", 10, "0", "0", "0"],
+                [1700020800000, "105.00", "108.00", "103.00", "107.50", "8.0", 1700035199999, "0", 8, "0", "0", "0"]
+            ]"#;
+            (200, body.to_string())
+        });
+
+        let candles = BinanceClient::with_url(&url)
+            .fetch_historical_prices(ChartTimeframe::Week, QuoteCurrency::Eur)
+            .unwrap();
+        assert_eq!(candles, vec![
+            PriceCandle { timestamp: 1_700_006_400, open: 100.0, high: 110.0, low: 90.0, close: 105.0 },
+            PriceCandle { timestamp: 1_700_020_800, open: 105.0, high: 108.0, low: 103.0, close: 107.5 },
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_klines() {
+        let url = test_support::serve_json(r#"[[1700006400000, 100.0, 110.0, 90.0, 105.0]]"#);
+
+        let result = BinanceClient::with_url(&url).fetch_historical_prices(ChartTimeframe::Month, QuoteCurrency::Usd);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_unlisted_markets_without_a_request() {
+        let url = test_support::serve(|request| panic!("unexpected request to {}", request.path));
+
+        for currency in [QuoteCurrency::Gbp, QuoteCurrency::Jpy] {
+            let error = BinanceClient::with_url(&url).fetch_current_price(currency).unwrap_err();
+            assert!(error.to_string().contains("Unsupported currency"), "{}", error);
+        }
+        let error = BinanceClient::with_url(&url).fetch_historical_prices(ChartTimeframe::Month, QuoteCurrency::Jpy).unwrap_err();
+        assert!(error.to_string().contains("Unsupported currency"), "{}", error);
+    }
+
+    #[test]
+    fn reports_error_status() {
+        let url = test_support::serve(|_| (400, r#"{"code": -1121, "msg": "Invalid symbol."}"#.to_string()));
+
+        let error = BinanceClient::with_url(&url).fetch_current_price(QuoteCurrency::Eur).unwrap_err();
+        assert!(error.to_string().contains("400"), "{}", error);
+    }
+}
@@ -0,0 +1,461 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::mempool_client::{MempoolBlockInfo, MempoolFeeEstimate, MempoolStats};
+use crate::proxy::{HttpClient, ProxySource};
+use crate::secret_store;
+
+// Default bitcoind RPC URL on mainnet
+pub const DEFAULT_BITCOIN_RPC_URL: &str = "http://127.0.0.1:8332";
+
+// Confirmation targets (in blocks) used for the four fee levels
+const FASTEST_TARGET: u16 = 1;
+const HALF_HOUR_TARGET: u16 = 3;
+const HOUR_TARGET: u16 = 6;
+const ECONOMY_TARGET: u16 = 144;
+
+// Keyring attributes the RPC password is stored under
+const PASSWORD_ATTRIBUTES: [(&str, &str); 1] = [("secret", "bitcoin-rpc-password")];
+
+// How the RPC client authenticates with bitcoind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcAuthMethod {
+    // The .cookie file bitcoind writes to its data directory
+    #[default]
+    Cookie,
+    // rpcuser/rpcpassword (or rpcauth) from bitcoin.conf
+    UserPassword,
+}
+
+impl RpcAuthMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RpcAuthMethod::Cookie => "Cookie file",
+            RpcAuthMethod::UserPassword => "Username & password",
+        }
+    }
+}
+
+// Credentials for a bitcoind RPC server
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcAuth {
+    Cookie(PathBuf),
+    UserPassword { user: String, password: String },
+}
+
+impl RpcAuth {
+    // Username and password for HTTP basic auth. The cookie is re-read on
+    // every call since bitcoind writes a new one each time it starts.
+    fn credentials(&self) -> Result<(String, String)> {
+        match self {
+            RpcAuth::Cookie(path) => {
+                let cookie = fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Failed to read RPC cookie {}: {}", path.display(), e))?;
+                let (user, password) = cookie.trim().split_once(':')
+                    .ok_or_else(|| anyhow!("Invalid RPC cookie file: {}", path.display()))?;
+                Ok((user.to_string(), password.to_string()))
+            },
+            RpcAuth::UserPassword { user, password } => Ok((user.clone(), password.clone())),
+        }
+    }
+}
+
+/// Location of the mainnet cookie file in bitcoind's default data directory
+pub fn default_cookie_path() -> PathBuf {
+    let mut path = if cfg!(any(target_os = "macos", target_os = "windows")) {
+        let mut dir = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+        dir.push("Bitcoin");
+        dir
+    } else {
+        let mut dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        dir.push(".bitcoin");
+        dir
+    };
+    path.push(".cookie");
+    path
+}
+
+/// RPC password saved in the OS keyring, or None when there is none
+pub fn load_password() -> Result<Option<String>> {
+    secret_store::load(&PASSWORD_ATTRIBUTES)
+}
+
+/// Save the RPC password in the OS keyring, replacing the previous one
+pub fn store_password(password: &str) -> Result<()> {
+    secret_store::store("BTC Ticker Bitcoin Core RPC password", &PASSWORD_ATTRIBUTES, password)
+}
+
+// JSON-RPC response envelope
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+// Subset of getblockchaininfo
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockchainInfo {
+    pub chain: String,
+    pub blocks: u32,
+    pub verificationprogress: f64,
+    pub initialblockdownload: bool,
+}
+
+// getblock at verbosity 1
+#[derive(Debug, Deserialize)]
+struct RpcBlock {
+    hash: String,
+    height: u32,
+    version: u32,
+    time: u32,
+    // Compact target as a hex string
+    bits: String,
+    nonce: u32,
+    difficulty: f64,
+    merkleroot: String,
+    #[serde(rename = "nTx")]
+    n_tx: u32,
+    size: u32,
+    weight: u32,
+    previousblockhash: Option<String>,
+}
+
+// estimatesmartfee
+#[derive(Debug, Deserialize)]
+struct RpcFeeEstimate {
+    // BTC per kvB, missing when the node has too little data
+    feerate: Option<f64>,
+}
+
+// getmempoolinfo
+#[derive(Debug, Deserialize)]
+struct RpcMempoolInfo {
+    size: u32,
+    bytes: u64,
+    // BTC, added in Bitcoin Core 23
+    #[serde(default)]
+    total_fee: f64,
+    // BTC per kvB
+    mempoolminfee: f64,
+}
+
+// Bitcoin Core JSON-RPC client for the network data normally fetched from mempool.space
+pub struct BitcoinRpcClient {
+    client: HttpClient,
+    url: String,
+    auth: RpcAuth,
+}
+
+impl BitcoinRpcClient {
+    /// Create a client for the RPC server at `url` (e.g. a local mock server)
+    pub fn with_url(url: &str, auth: RpcAuth) -> Self {
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)); // 10 second timeout
+        let client = HttpClient::build(ProxySource::BitcoinCore, url, builder);
+
+        BitcoinRpcClient {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            auth,
+        }
+    }
+
+    // Make a JSON-RPC call and deserialize its result
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        println!("Calling {} on: {}", method, self.url);
+
+        let (user, password) = self.auth.credentials()?;
+        let request = json!({
+            "jsonrpc": "1.0",
+            "id": "btc-ticker",
+            "method": method,
+            "params": params,
+        });
+
+        let response = self.client.post(&self.url)?
+            .basic_auth(user, Some(password))
+            .json(&request)
+            .send()
+            .map_err(|e| anyhow!("Failed to call {}: {}", method, e))?;
+
+        // bitcoind reports RPC errors with a 500 status and an error body
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(anyhow!("RPC server rejected the credentials ({})", status));
+        }
+
+        let body: RpcResponse<T> = response.json()
+            .map_err(|e| anyhow!("Failed to parse {} response ({}): {}", method, status, e))?;
+
+        if let Some(error) = body.error {
+            return Err(anyhow!("{} failed: {} (code {})", method, error.message, error.code));
+        }
+        body.result.ok_or_else(|| anyhow!("{} returned no result", method))
+    }
+
+    pub fn fetch_blockchain_info(&self) -> Result<BlockchainInfo> {
+        self.call("getblockchaininfo", json!([]))
+    }
+
+    fn fetch_block(&self, hash: &str) -> Result<MempoolBlockInfo> {
+        let block: RpcBlock = self.call("getblock", json!([hash, 1]))?;
+        let bits = u32::from_str_radix(&block.bits, 16)
+            .map_err(|e| anyhow!("Failed to parse block bits '{}': {}", block.bits, e))?;
+
+        Ok(MempoolBlockInfo {
+            id: block.hash,
+            height: block.height,
+            version: block.version,
+            timestamp: block.time,
+            bits,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            merkle_root: block.merkleroot,
+            tx_count: block.n_tx,
+            size: block.size,
+            weight: block.weight,
+            previousblockhash: block.previousblockhash,
+        })
+    }
+
+    pub fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        let hash: String = self.call("getbestblockhash", json!([]))?;
+        self.fetch_block(&hash)
+    }
+
+    /// Fetch up to 10 blocks ending at `start_height`, newest first
+    pub fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        let mut blocks = Vec::new();
+        for height in (start_height.saturating_sub(9)..=start_height).rev() {
+            let hash: String = self.call("getblockhash", json!([height]))?;
+            blocks.push(self.fetch_block(&hash)?);
+        }
+        Ok(blocks)
+    }
+
+    /// Fee levels from estimatesmartfee, in sat/vB. Targets the node can't
+    /// estimate fall back to the mempool's minimum fee.
+    pub fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
+        let mempool: RpcMempoolInfo = self.call("getmempoolinfo", json!([]))?;
+        let minimum_fee = btc_per_kvb_to_sat_per_vb(mempool.mempoolminfee);
+
+        let estimate = |target: u16| -> Result<u32> {
+            let estimate: RpcFeeEstimate = self.call("estimatesmartfee", json!([target]))?;
+            Ok(estimate.feerate
+                .map(btc_per_kvb_to_sat_per_vb)
+                .unwrap_or(minimum_fee)
+                .max(minimum_fee))
+        };
+
+        let economy_fee = estimate(ECONOMY_TARGET)?;
+        let hour_fee = estimate(HOUR_TARGET)?.max(economy_fee);
+        let half_hour_fee = estimate(HALF_HOUR_TARGET)?.max(hour_fee);
+        let fastest_fee = estimate(FASTEST_TARGET)?.max(half_hour_fee);
+
+        Ok(MempoolFeeEstimate {
+            fastest_fee,
+            half_hour_fee,
+            hour_fee,
+            economy_fee,
+        })
+    }
+
+    /// Mempool size and fees from getmempoolinfo. Bitcoin Core has no fee
+    /// histogram, so the histogram is left empty.
+    pub fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        let mempool: RpcMempoolInfo = self.call("getmempoolinfo", json!([]))?;
+
+        Ok(MempoolStats {
+            count: mempool.size,
+            vsize: mempool.bytes,
+            total_fee: (mempool.total_fee * 100_000_000.0).round() as u64,
+            fee_histogram: Vec::new(),
+        })
+    }
+}
+
+/// Convert a BTC/kvB fee rate to whole sat/vB, rounding up and never below 1
+pub fn btc_per_kvb_to_sat_per_vb(rate: f64) -> u32 {
+    // Round to whole sat/kvB first so float noise (0.00001 BTC is
+    // 1000.0000000000001 sat) doesn't round up a whole sat/vB
+    let sat_per_kvb = (rate * 100_000_000.0).round();
+    ((sat_per_kvb / 1000.0).ceil() as u32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Request};
+
+    fn user_password() -> RpcAuth {
+        RpcAuth::UserPassword { user: "alice".to_string(), password: "secret".to_string() }
+    }
+
+    // Mock bitcoind answering each RPC method with the result from `handler`
+    fn serve_rpc<F>(handler: F) -> String
+    where
+        F: Fn(&str, &Value) -> Value + Send + 'static,
+    {
+        test_support::serve(move |request: &Request| {
+            assert_eq!(request.method, "POST");
+            let body: Value = serde_json::from_str(&request.body).unwrap();
+            let result = handler(body["method"].as_str().unwrap(), &body["params"]);
+            (200, json!({ "result": result, "error": null, "id": "btc-ticker" }).to_string())
+        })
+    }
+
+    #[test]
+    fn call_sends_basic_auth() {
+        let url = test_support::serve(|request| {
+            // "alice:secret"
+            assert_eq!(request.header("Authorization"), Some("Basic YWxpY2U6c2VjcmV0"));
+            let body: Value = serde_json::from_str(&request.body).unwrap();
+            assert_eq!(body["method"], "getblockchaininfo");
+            let result = json!({
+                "chain": "main",
+                "blocks": 850000,
+                "verificationprogress": 0.9999,
+                "initialblockdownload": false,
+            });
+            (200, json!({ "result": result, "error": null }).to_string())
+        });
+
+        let info = BitcoinRpcClient::with_url(&url, user_password()).fetch_blockchain_info().unwrap();
+        assert_eq!(info.chain, "main");
+        assert_eq!(info.blocks, 850000);
+    }
+
+    #[test]
+    fn cookie_auth_reads_the_cookie_file() {
+        let path = std::env::temp_dir().join(format!("btc-ticker-test-cookie-{}", std::process::id()));
+        fs::write(&path, "__cookie__:abc123\n").unwrap();
+        let url = test_support::serve(|request| {
+            // "__cookie__:abc123"
+            assert_eq!(request.header("Authorization"), Some("Basic X19jb29raWVfXzphYmMxMjM="));
+            (200, json!({ "result": "00ff", "error": null }).to_string())
+        });
+
+        let client = BitcoinRpcClient::with_url(&url, RpcAuth::Cookie(path.clone()));
+        let hash: String = client.call("getbestblockhash", json!([])).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(hash, "00ff");
+    }
+
+    #[test]
+    fn missing_cookie_file_is_reported() {
+        let path = PathBuf::from("/nonexistent/btc-ticker/.cookie");
+        let client = BitcoinRpcClient::with_url("http://127.0.0.1:9", RpcAuth::Cookie(path));
+        let error = client.fetch_blockchain_info().unwrap_err().to_string();
+        assert!(error.contains("Failed to read RPC cookie"), "{}", error);
+    }
+
+    #[test]
+    fn rejected_credentials() {
+        let url = test_support::serve(|_| (401, String::new()));
+        let error = BitcoinRpcClient::with_url(&url, user_password())
+            .fetch_blockchain_info().unwrap_err().to_string();
+        assert!(error.contains("rejected the credentials"), "{}", error);
+    }
+
+    #[test]
+    fn rpc_errors_carry_method_message_and_code() {
+        let url = test_support::serve(|_| {
+            let error = json!({ "code": -8, "message": "Block height out of range" });
+            (500, json!({ "result": null, "error": error }).to_string())
+        });
+        let error = BitcoinRpcClient::with_url(&url, user_password())
+            .fetch_blocks(10).unwrap_err().to_string();
+        assert_eq!(error, "getblockhash failed: Block height out of range (code -8)");
+    }
+
+    #[test]
+    fn latest_block_parses_hex_bits() {
+        let url = serve_rpc(|method, params| match method {
+            "getbestblockhash" => json!("000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d"),
+            "getblock" => {
+                assert_eq!(params[1], 1);
+                json!({
+                    "hash": params[0],
+                    "height": 840000,
+                    "version": 0x2000_0000,
+                    "time": 1713571767,
+                    "bits": "17034219",
+                    "nonce": 3932395645u32,
+                    "difficulty": 86388558925171.02,
+                    "merkleroot": "031b417c3a1828ddf3d6527fc210daafcc9218e81f98257f88d4d43bd7a5894f",
+                    "nTx": 3050,
+                    "size": 2325617,
+                    "weight": 3993281,
+                    "previousblockhash": "0000000000000000000172014ba58d66455762add0512355ad651207918494ab",
+                })
+            },
+            other => panic!("unexpected call {}", other),
+        });
+
+        let block = BitcoinRpcClient::with_url(&url, user_password()).fetch_latest_block().unwrap();
+        assert_eq!(block.height, 840000);
+        assert_eq!(block.bits, 0x17034219);
+        assert_eq!(block.tx_count, 3050);
+        assert_eq!(block.id, "000000000000000000024bead8df69990852c202db0e0097c1a12ea637d7e96d");
+    }
+
+    #[test]
+    fn fee_estimates_fall_back_to_the_minimum_fee_and_stay_ordered() {
+        let url = serve_rpc(|method, params| match method {
+            // 1 sat/vB
+            "getmempoolinfo" => json!({ "size": 1, "bytes": 250, "mempoolminfee": 0.00001 }),
+            "estimatesmartfee" => match params[0].as_u64().unwrap() {
+                // Too little data for the economy target
+                144 => json!({ "errors": ["Insufficient data or no feerate found"] }),
+                // Below the 3 block estimate, so raised to it
+                6 => json!({ "feerate": 0.00002 }),
+                3 => json!({ "feerate": 0.0000501 }),
+                1 => json!({ "feerate": 0.00008 }),
+                target => panic!("unexpected target {}", target),
+            },
+            other => panic!("unexpected call {}", other),
+        });
+
+        let fees = BitcoinRpcClient::with_url(&url, user_password()).fetch_fee_estimates().unwrap();
+        assert_eq!(fees.economy_fee, 1);
+        assert_eq!(fees.hour_fee, 2);
+        // 5.01 sat/vB rounds up
+        assert_eq!(fees.half_hour_fee, 6);
+        assert_eq!(fees.fastest_fee, 8);
+    }
+
+    #[test]
+    fn mempool_stats_from_getmempoolinfo() {
+        let url = serve_rpc(|_, _| json!({
+            "size": 12000,
+            "bytes": 6500000,
+            "total_fee": 0.25,
+            "mempoolminfee": 0.00001,
+        }));
+
+        let stats = BitcoinRpcClient::with_url(&url, user_password()).fetch_mempool_stats().unwrap();
+        assert_eq!(stats.count, 12000);
+        assert_eq!(stats.vsize, 6500000);
+        assert_eq!(stats.total_fee, 25_000_000);
+        assert!(stats.fee_histogram.is_empty());
+    }
+
+    #[test]
+    fn fee_rate_conversion_rounds_up() {
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.00001), 1);
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.000015), 2);
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.0), 1);
+        assert_eq!(btc_per_kvb_to_sat_per_vb(0.0002), 20);
+    }
+}
@@ -2,6 +2,12 @@ use anyhow::{Result, anyhow};
 use serde::{Deserialize};
 use chrono::{Utc, TimeZone, DateTime, Local};
 
+use crate::price_provider::{ChartTimeframe, PriceCandle, PriceProvider, PriceSource, QuoteCurrency, parse_price_field};
+use crate::proxy::{HttpClient, ProxySource};
+
+// Default Bitstamp API URL
+pub const DEFAULT_BITSTAMP_API_URL: &str = "https://www.bitstamp.net/api/v2";
+
 // Bitstamp API response structures
 #[derive(Debug, Deserialize)]
 pub struct BitstampResponse {
@@ -27,59 +33,122 @@ pub struct BitstampHistoricalData {
     pub data: BitstampOHLCData,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ChartTimeframe {
-    Hours24,
-    Week,
-    Month,
-    Year,
-}
-
-impl ChartTimeframe {
-    pub fn description(&self) -> &'static str {
-        match self {
-            ChartTimeframe::Hours24 => "24 Hours (hourly)",
-            ChartTimeframe::Week => "1 Week (4-hour)",
-            ChartTimeframe::Month => "1 Month (daily)",
-            ChartTimeframe::Year => "1 Year (daily)",
-        }
-    }
-    
-    pub fn api_params(&self) -> (u32, u32) {
-        match self {
-            ChartTimeframe::Hours24 => (3600, 24),      // 1 hour steps, 24 candles
-            ChartTimeframe::Week => (14400, 42),        // 4 hour steps, 42 candles (1 week)
-            ChartTimeframe::Month => (86400, 30),       // 24 hour steps, 30 candles (1 month)
-            ChartTimeframe::Year => (86400, 365),       // 1 day steps, 365 candles (1 year)
-        }
-    }
-}
-
 // Bitstamp API client for handling all API interactions
 pub struct BitstampClient {
-    client: reqwest::blocking::Client,
+    client: HttpClient,
     base_url: String,
 }
 
 impl BitstampClient {
+    /// Create a new BitstampClient with the default Bitstamp API URL
     pub fn new() -> Self {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(10)) // 10 second timeout
-            .build()
-            .unwrap_or_else(|_| reqwest::blocking::Client::new());
+        Self::with_url(DEFAULT_BITSTAMP_API_URL)
+    }
+
+    /// Create a new BitstampClient with a custom API URL (e.g. a local mock server)
+    pub fn with_url(base_url: &str) -> Self {
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)); // 10 second timeout
+        let client = HttpClient::build(ProxySource::Exchanges, base_url, builder);
             
         BitstampClient {
             client,
-            base_url: "https://www.bitstamp.net/api/v2".to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
         }
     }
     
-    pub fn fetch_current_price(&self) -> Result<f64> {
-        let url = format!("{}/ticker/btcusd/", self.base_url);
+    // Fetch and parse an OHLC endpoint URL into normalized candles
+    fn fetch_ohlc(&self, url: &str) -> Result<Vec<PriceCandle>> {
+        let response = self.client.get(url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch historical data: {}", e))?;
+        
+        if !response.status().is_success() {
+            return Err(anyhow!("Historical API returned error status: {}", response.status()));
+        }
+    
+        let response_text = response.text()
+            .map_err(|e| anyhow!("Failed to get response text: {}", e))?;
+        
+        if response_text.len() > 200 {
+            println!("Response text sample: {}...", &response_text[..200]);
+        } else {
+            println!("Response text: {}", response_text);
+        }
+    
+        // Try to parse the JSON
+        let data = serde_json::from_str::<BitstampHistoricalData>(&response_text)
+            .map_err(|e| anyhow!("Failed to parse historical data: {}", e))?;
+        
+        println!("Successfully parsed {} candles", data.data.ohlc.len());
+    
+        // Print a sample of formatted timestamps if available
+        if !data.data.ohlc.is_empty() {
+            let sample_timestamp = &data.data.ohlc[0].timestamp;
+            let formatted = format_unix_timestamp(sample_timestamp);
+            println!("Sample timestamp: {} formatted as: {}", sample_timestamp, formatted);
+        }
+    
+        let mut candles = Vec::with_capacity(data.data.ohlc.len());
+        for point in data.data.ohlc.iter() {
+            let timestamp = point.timestamp.parse::<i64>()
+                .map_err(|e| anyhow!("Failed to parse candle timestamp: {}", e))?;
+
+            candles.push(PriceCandle {
+                timestamp,
+                open: parse_price_field(&point.open, "open")?,
+                high: parse_price_field(&point.high, "high")?,
+                low: parse_price_field(&point.low, "low")?,
+                close: parse_price_field(&point.close, "close")?,
+            });
+        }
+
+        Ok(candles)
+    }
+    
+    /// Fetch daily candles between `start` and `end` (Unix timestamps), paging
+    /// through the OHLC endpoint since each request returns at most 1000 candles
+    pub fn fetch_daily_range(&self, currency: QuoteCurrency, start: i64, end: i64) -> Result<Vec<PriceCandle>> {
+        const STEP: i64 = 86_400;
+        const PAGE_SIZE: i64 = 1000;
+        
+        let mut candles: Vec<PriceCandle> = Vec::new();
+        let mut page_start = start;
+        while page_start <= end {
+            let page_end = (page_start + (PAGE_SIZE - 1) * STEP).min(end);
+            let url = format!("{}/ohlc/{}/?step={}&limit={}&start={}&end={}",
+                              self.base_url, currency_pair(currency)?, STEP, PAGE_SIZE, page_start, page_end);
+            println!("Fetching daily candles from: {}", url);
+            
+            let page = self.fetch_ohlc(&url)?;
+            let Some(last) = page.last() else {
+                // No trading in this page, e.g. before the pair was listed
+                page_start = page_end + STEP;
+                continue;
+            };
+            
+            // Always move forward, even if the API ignored `start`
+            page_start = (last.timestamp + STEP).max(page_start + STEP);
+            candles.extend(page.into_iter().filter(|candle| candle.timestamp <= end));
+        }
+        
+        candles.sort_by_key(|candle| candle.timestamp);
+        candles.dedup_by_key(|candle| candle.timestamp);
+        Ok(candles)
+    }
+}
+
+impl PriceProvider for BitstampClient {
+    fn name(&self) -> &'static str {
+        "Bitstamp"
+    }
+
+    fn fetch_current_price(&self, currency: QuoteCurrency) -> Result<f64> {
+        let url = format!("{}/ticker/{}/", self.base_url, currency_pair(currency)?);
         
         println!("Fetching current BTC price from: {}", url);
         
-        let response = self.client.get(&url)
+        let response = self.client.get(&url)?
             .send()
             .map_err(|e| anyhow!("Failed to fetch price: {}", e))?;
             
@@ -97,47 +166,38 @@ impl BitstampClient {
         Ok(price)
     }
     
-    pub fn fetch_historical_prices(&self, timeframe: ChartTimeframe) -> Result<BitstampHistoricalData> {
+    fn fetch_historical_prices(&self, timeframe: ChartTimeframe, currency: QuoteCurrency) -> Result<Vec<PriceCandle>> {
         // Get the step (candle interval in seconds) and limit (number of candles) based on timeframe
         let (step, limit) = timeframe.api_params();
         
         // Construct the URL with the appropriate parameters
-        let url = format!("{}/ohlc/btcusd/?step={}&limit={}", self.base_url, step, limit);
+        let url = format!("{}/ohlc/{}/?step={}&limit={}", self.base_url, currency_pair(currency)?, step, limit);
         println!("Fetching historical data from: {} ({})", url, timeframe.description());
         
-        let response = self.client.get(&url)
-            .send()
-            .map_err(|e| anyhow!("Failed to fetch historical data: {}", e))?;
-            
-        if !response.status().is_success() {
-            return Err(anyhow!("Historical API returned error status: {}", response.status()));
-        }
+        let candles = self.fetch_ohlc(&url)?;
         
-        let response_text = response.text()
-            .map_err(|e| anyhow!("Failed to get response text: {}", e))?;
-            
-        if response_text.len() > 200 {
-            println!("Response text sample: {}...", &response_text[..200]);
-        } else {
-            println!("Response text: {}", response_text);
-        }
-        
-        // Try to parse the JSON
-        let data = serde_json::from_str::<BitstampHistoricalData>(&response_text)
-            .map_err(|e| anyhow!("Failed to parse historical data: {}", e))?;
-            
         println!("Successfully parsed historical data for {} ({} candles)", 
-                timeframe.description(), data.data.ohlc.len());
-                
-        // Print a sample of formatted timestamps if available
-        if !data.data.ohlc.is_empty() {
-            let sample_timestamp = &data.data.ohlc[0].timestamp;
-            let formatted = format_unix_timestamp(sample_timestamp);
-            println!("Sample timestamp: {} formatted as: {}", sample_timestamp, formatted);
-        }
+                timeframe.description(), candles.len());
         
-        Ok(data)
+        Ok(candles)
     }
+    
+    fn fetch_candles_since(&self, timeframe: ChartTimeframe, currency: QuoteCurrency, start: i64) -> Result<Vec<PriceCandle>> {
+        let (step, _) = timeframe.api_params();
+        
+        // Bitstamp returns up to 1000 candles beginning at `start`
+        let url = format!("{}/ohlc/{}/?step={}&limit=1000&start={}",
+                          self.base_url, currency_pair(currency)?, step, start);
+        println!("Fetching new candles from: {} ({})", url, timeframe.description());
+        
+        self.fetch_ohlc(&url)
+    }
+}
+
+// Bitstamp market symbol for BTC in the given currency (e.g. "btceur")
+fn currency_pair(currency: QuoteCurrency) -> Result<String> {
+    PriceSource::Bitstamp.ensure_supports(currency)?;
+    Ok(format!("btc{}", currency.code().to_lowercase()))
 }
 
 // Helper function to format Unix timestamp to date-time format (YYYY-MM-DD HH:MM)
@@ -150,3 +210,58 @@ pub fn format_unix_timestamp(timestamp_str: &str) -> String {
     }
     "Invalid timestamp".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn parses_ticker() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/ticker/btcusd/");
+            (200, r#"{"last": "65432.10", "high": "66000"}"#.to_string())
+        });
+
+        let price = BitstampClient::with_url(&url).fetch_current_price(QuoteCurrency::Usd).unwrap();
+        assert_eq!(price, 65432.10);
+    }
+
+    #[test]
+    fn parses_ohlc_candles() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/ohlc/btceur/?step=3600&limit=24");
+            let body = r#"{"data": {"pair": "BTC/EUR", "ohlc": [
+                {"timestamp": "1700000000", "open": "100.0", "high": "110.5", "low": "95.25", "close": "105.0", "volume": "1.5"},
+                {"timestamp": "1700003600", "open": "105.0", "high": "107.0", "low": "101.0", "close": "102.0", "volume": "2.0"}
+            ]}}"#;
+            (200, body.to_string())
+        });
+
+        let candles = BitstampClient::with_url(&url)
+            .fetch_historical_prices(ChartTimeframe::Hours24, QuoteCurrency::Eur)
+            .unwrap();
+        assert_eq!(candles, vec![
+            PriceCandle { timestamp: 1_700_000_000, open: 100.0, high: 110.5, low: 95.25, close: 105.0 },
+            PriceCandle { timestamp: 1_700_003_600, open: 105.0, high: 107.0, low: 101.0, close: 102.0 },
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_candles() {
+        let url = test_support::serve_json(r#"{"data": {"ohlc": [
+            {"timestamp": "soon", "open": "1", "high": "1", "low": "1", "close": "1"}
+        ]}}"#);
+
+        let result = BitstampClient::with_url(&url).fetch_historical_prices(ChartTimeframe::Hours24, QuoteCurrency::Usd);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reports_error_status() {
+        let url = test_support::serve(|_| (404, r#"{"message": "Not found"}"#.to_string()));
+
+        let error = BitstampClient::with_url(&url).fetch_current_price(QuoteCurrency::Usd).unwrap_err();
+        assert!(error.to_string().contains("404"), "{}", error);
+    }
+}
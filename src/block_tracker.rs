@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use crate::mempool_client::MempoolBlockInfo;
+
+// A block in the recent blocks timeline
+#[derive(Debug, Clone)]
+pub struct RecentBlock {
+    pub height: u32,
+    pub hash: String,
+    pub timestamp: u32,
+    pub tx_count: u32,
+    pub size: u32,
+    // Seconds since the previous block, if that block is known
+    pub since_previous: Option<i64>,
+}
+
+impl RecentBlock {
+    /// One-line summary for notifications, e.g. "3,215 txs, 1.54 MB, 9m 12s after the previous block"
+    pub fn summary(&self) -> String {
+        // Every block has a coinbase, so a zero count means only the header is known
+        let mut parts = Vec::new();
+        if self.tx_count > 0 {
+            parts.push(format!("{} txs, {}", format_count(self.tx_count), format_size(self.size)));
+        }
+        if let Some(interval) = self.since_previous {
+            parts.push(format!("{} after the previous block", format_interval(interval)));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Keeps the last few blocks and detects newly found ones, including several
+/// blocks found between two polls
+pub struct BlockTracker {
+    // Oldest first
+    blocks: VecDeque<RecentBlock>,
+    capacity: usize,
+}
+
+impl BlockTracker {
+    pub fn new(capacity: usize) -> Self {
+        BlockTracker {
+            blocks: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Height of the newest known block
+    pub fn tip_height(&self) -> Option<u32> {
+        self.blocks.back().map(|block| block.height)
+    }
+
+    /// Recent blocks, newest first
+    pub fn recent(&self) -> impl Iterator<Item = &RecentBlock> {
+        self.blocks.iter().rev()
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.trim();
+    }
+
+    /// Add fetched blocks (in any order) and return the ones above the previous
+    /// tip, oldest first. The first call only fills the timeline, so starting
+    /// the app doesn't report blocks that were found long ago.
+    pub fn record(&mut self, fetched: &[MempoolBlockInfo]) -> Vec<RecentBlock> {
+        let previous_tip = self.tip_height();
+        let mut fetched: Vec<&MempoolBlockInfo> = fetched.iter()
+            .filter(|block| match previous_tip {
+                Some(tip) => block.height > tip,
+                None => true,
+            })
+            .collect();
+        fetched.sort_by_key(|block| block.height);
+        fetched.dedup_by_key(|block| block.height);
+
+        let mut new_blocks = Vec::new();
+        for block in fetched {
+            // Only time blocks against their direct parent
+            let since_previous = self.blocks.back()
+                .filter(|previous| previous.height + 1 == block.height)
+                .map(|previous| block.timestamp as i64 - previous.timestamp as i64);
+
+            let recent = RecentBlock {
+                height: block.height,
+                hash: block.id.clone(),
+                timestamp: block.timestamp,
+                tx_count: block.tx_count,
+                size: block.size,
+                since_previous,
+            };
+            self.blocks.push_back(recent.clone());
+            new_blocks.push(recent);
+        }
+        self.trim();
+
+        if previous_tip.is_some() {
+            new_blocks
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.blocks.len() > self.capacity {
+            self.blocks.pop_front();
+        }
+    }
+}
+
+// Format a block interval, e.g. "9m 12s" or "1h 03m"
+pub fn format_interval(seconds: i64) -> String {
+    // Block timestamps may be out of order by up to two hours
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    if seconds >= 3600 {
+        format!("{}{}h {:02}m", sign, seconds / 3600, seconds % 3600 / 60)
+    } else {
+        format!("{}{}m {:02}s", sign, seconds / 60, seconds % 60)
+    }
+}
+
+// Format a block size in bytes as MB or kB
+pub fn format_size(bytes: u32) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.2} MB", bytes as f64 / 1_000_000.0)
+    } else {
+        format!("{:.1} kB", bytes as f64 / 1_000.0)
+    }
+}
+
+// Format a count with thousands separators, e.g. 3,215
+pub fn format_count(count: u32) -> String {
+    let digits = count.to_string();
+    let groups: Vec<&str> = digits.as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| std::str::from_utf8(group).unwrap_or_default())
+        .collect();
+    groups.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: u32, timestamp: u32) -> MempoolBlockInfo {
+        MempoolBlockInfo {
+            id: format!("hash{}", height),
+            height,
+            version: 0x2000_0000,
+            timestamp,
+            bits: 0x1702_5ced,
+            nonce: 0,
+            difficulty: 1.0,
+            merkle_root: String::new(),
+            tx_count: 3215,
+            size: 1_540_000,
+            weight: 3_993_000,
+            previousblockhash: None,
+        }
+    }
+
+    fn heights(blocks: &[RecentBlock]) -> Vec<u32> {
+        blocks.iter().map(|block| block.height).collect()
+    }
+
+    #[test]
+    fn first_fill_does_not_report_blocks() {
+        let mut tracker = BlockTracker::new(10);
+
+        assert!(tracker.record(&[block(100, 0), block(101, 600)]).is_empty());
+        assert_eq!(tracker.tip_height(), Some(101));
+        assert_eq!(tracker.recent().map(|block| block.height).collect::<Vec<_>>(), vec![101, 100]);
+    }
+
+    #[test]
+    fn reports_every_block_found_between_polls() {
+        let mut tracker = BlockTracker::new(10);
+        tracker.record(&[block(100, 0)]);
+
+        // Newest first, the way the API lists them, with the old tip included
+        let new_blocks = tracker.record(&[block(103, 1500), block(102, 1000), block(101, 600), block(100, 0)]);
+        assert_eq!(heights(&new_blocks), vec![101, 102, 103]);
+        assert_eq!(tracker.tip_height(), Some(103));
+    }
+
+    #[test]
+    fn times_blocks_against_their_parent() {
+        let mut tracker = BlockTracker::new(10);
+        tracker.record(&[block(100, 1000)]);
+
+        let new_blocks = tracker.record(&[block(101, 1552), block(103, 2000)]);
+        assert_eq!(new_blocks[0].since_previous, Some(552));
+        // 102 is missing, so 103 can't be timed
+        assert_eq!(new_blocks[1].since_previous, None);
+
+        // Timestamps may go backwards
+        let new_blocks = tracker.record(&[block(104, 1900)]);
+        assert_eq!(new_blocks[0].since_previous, Some(-100));
+        assert_eq!(format_interval(-100), "-1m 40s");
+    }
+
+    #[test]
+    fn ignores_blocks_at_or_below_the_tip() {
+        let mut tracker = BlockTracker::new(10);
+        tracker.record(&[block(100, 0), block(101, 600)]);
+
+        // Same height again (e.g. a reorg replacing the tip) and an older block
+        assert!(tracker.record(&[block(101, 700), block(99, 0)]).is_empty());
+        assert_eq!(tracker.recent().next().unwrap().timestamp, 600);
+        assert_eq!(tracker.recent().count(), 2);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_blocks() {
+        let mut tracker = BlockTracker::new(2);
+        tracker.record(&[block(100, 0), block(101, 600), block(102, 1200)]);
+        assert_eq!(tracker.recent().map(|block| block.height).collect::<Vec<_>>(), vec![102, 101]);
+
+        tracker.set_capacity(1);
+        assert_eq!(tracker.recent().map(|block| block.height).collect::<Vec<_>>(), vec![102]);
+    }
+
+    #[test]
+    fn summarizes_blocks() {
+        let mut tracker = BlockTracker::new(10);
+        tracker.record(&[block(100, 0)]);
+        let new_blocks = tracker.record(&[block(101, 552)]);
+
+        assert_eq!(new_blocks[0].summary(), "3,215 txs, 1.54 MB, 9m 12s after the previous block");
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+
+use crate::config::AppConfig;
+use crate::price_provider::{PriceCandle, PriceSource, QuoteCurrency};
+
+// Upper bound of candles kept per file (~27 years of daily candles)
+const MAX_STORED_CANDLES: usize = 10_000;
+
+/// On-disk OHLC cache with one JSON file per exchange, pair and candle step
+pub struct CandleStore {
+    dir: PathBuf,
+}
+
+impl CandleStore {
+    /// Open the store in the btc-ticker config directory
+    pub fn new() -> Self {
+        let mut dir = AppConfig::get_config_dir();
+        dir.push("candles");
+        Self::with_dir(dir)
+    }
+
+    /// Open a store in a custom directory
+    pub fn with_dir(dir: PathBuf) -> Self {
+        fs::create_dir_all(&dir).ok(); // Create directory if it doesn't exist
+        CandleStore { dir }
+    }
+
+    // File holding the candles for a source, pair and step, e.g. bitstamp_btcusd_3600.json
+    fn path(&self, source: PriceSource, currency: QuoteCurrency, step: u32) -> PathBuf {
+        let file_name = format!(
+            "{}_btc{}_{}.json",
+            source.name().to_lowercase(),
+            currency.code().to_lowercase(),
+            step
+        );
+        self.dir.join(file_name)
+    }
+
+    // File recording where the exchange's history for a pair and step begins
+    fn first_available_path(&self, source: PriceSource, currency: QuoteCurrency, step: u32) -> PathBuf {
+        self.path(source, currency, step).with_extension("first")
+    }
+
+    /// Timestamp of the exchange's first candle, once a fetch has reached back that far
+    pub fn first_available(&self, source: PriceSource, currency: QuoteCurrency, step: u32) -> Option<i64> {
+        fs::read_to_string(self.first_available_path(source, currency, step)).ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Remember that the exchange has no candles before `timestamp`
+    pub fn set_first_available(&self, source: PriceSource, currency: QuoteCurrency, step: u32, timestamp: i64) -> Result<()> {
+        fs::write(self.first_available_path(source, currency, step), timestamp.to_string())
+            .map_err(|e| anyhow!("Failed to write candle cache: {}", e))
+    }
+
+    /// Load stored candles (oldest first), or an empty list if nothing is cached
+    pub fn load(&self, source: PriceSource, currency: QuoteCurrency, step: u32) -> Vec<PriceCandle> {
+        let path = self.path(source, currency, step);
+        if !path.exists() {
+            return Vec::new();
+        }
+
+        match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(candles) => candles,
+                Err(e) => {
+                    eprintln!("Error parsing candle cache {}: {}", path.display(), e);
+                    Vec::new()
+                }
+            },
+            Err(e) => {
+                eprintln!("Error reading candle cache {}: {}", path.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Merge newly fetched candles into the store and return the full series.
+    /// Fetched candles replace stored ones with the same timestamp, since the
+    /// last stored candle may still have been in progress.
+    pub fn merge(&self, source: PriceSource, currency: QuoteCurrency, step: u32, fetched: &[PriceCandle]) -> Result<Vec<PriceCandle>> {
+        let mut candles = self.load(source, currency, step);
+
+        if let Some(first_new) = fetched.first() {
+            candles.retain(|candle| candle.timestamp < first_new.timestamp);
+        }
+        candles.extend_from_slice(fetched);
+        candles.sort_by_key(|candle| candle.timestamp);
+        candles.dedup_by_key(|candle| candle.timestamp);
+
+        if candles.len() > MAX_STORED_CANDLES {
+            let excess = candles.len() - MAX_STORED_CANDLES;
+            candles.drain(..excess);
+        }
+
+        let content = serde_json::to_string(&candles)
+            .map_err(|e| anyhow!("Failed to serialize candles: {}", e))?;
+        fs::write(self.path(source, currency, step), content)
+            .map_err(|e| anyhow!("Failed to write candle cache: {}", e))?;
+
+        Ok(candles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Store in a fresh directory under the system temp dir
+    fn temp_store(name: &str) -> CandleStore {
+        let dir = std::env::temp_dir().join(format!("btc-ticker-candles-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        CandleStore::with_dir(dir)
+    }
+
+    fn candle(timestamp: i64, close: f64) -> PriceCandle {
+        PriceCandle { timestamp, open: close, high: close, low: close, close }
+    }
+
+    fn timestamps(candles: &[PriceCandle]) -> Vec<i64> {
+        candles.iter().map(|candle| candle.timestamp).collect()
+    }
+
+    #[test]
+    fn persists_merged_candles() {
+        let store = temp_store("persist");
+        assert!(store.load(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600).is_empty());
+
+        let merged = store.merge(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600, &[candle(0, 1.0), candle(3600, 2.0)]).unwrap();
+        assert_eq!(merged, store.load(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600));
+        assert_eq!(timestamps(&merged), vec![0, 3600]);
+    }
+
+    #[test]
+    fn fetched_candles_replace_stored_ones() {
+        let store = temp_store("replace");
+        store.merge(PriceSource::Kraken, QuoteCurrency::Eur, 3600, &[candle(0, 1.0), candle(3600, 2.0), candle(7200, 3.0)]).unwrap();
+
+        // The stored 7200 candle was still in progress when it was saved
+        let merged = store.merge(PriceSource::Kraken, QuoteCurrency::Eur, 3600, &[candle(7200, 3.5), candle(10800, 4.0)]).unwrap();
+        assert_eq!(merged, vec![candle(0, 1.0), candle(3600, 2.0), candle(7200, 3.5), candle(10800, 4.0)]);
+    }
+
+    #[test]
+    fn sorts_and_dedupes_by_timestamp() {
+        let store = temp_store("dedupe");
+        store.merge(PriceSource::Coinbase, QuoteCurrency::Usd, 86400, &[candle(0, 1.0)]).unwrap();
+
+        let fetched = [candle(259_200, 4.0), candle(86_400, 2.0), candle(172_800, 3.0), candle(86_400, 2.0)];
+        let merged = store.merge(PriceSource::Coinbase, QuoteCurrency::Usd, 86400, &fetched).unwrap();
+        assert_eq!(timestamps(&merged), vec![0, 86_400, 172_800, 259_200]);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_candles() {
+        let store = temp_store("cap");
+        let fetched: Vec<PriceCandle> = (0..MAX_STORED_CANDLES as i64 + 5)
+            .map(|i| candle(i * 60, i as f64))
+            .collect();
+
+        let merged = store.merge(PriceSource::Binance, QuoteCurrency::Usd, 60, &fetched).unwrap();
+        assert_eq!(merged.len(), MAX_STORED_CANDLES);
+        assert_eq!(merged[0].timestamp, 5 * 60);
+    }
+
+    #[test]
+    fn keeps_pairs_and_steps_apart() {
+        let store = temp_store("files");
+        store.merge(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600, &[candle(0, 1.0)]).unwrap();
+
+        assert!(store.load(PriceSource::Bitstamp, QuoteCurrency::Eur, 3600).is_empty());
+        assert!(store.load(PriceSource::Bitstamp, QuoteCurrency::Usd, 86400).is_empty());
+        assert!(store.load(PriceSource::Kraken, QuoteCurrency::Usd, 3600).is_empty());
+    }
+
+    #[test]
+    fn ignores_a_corrupt_cache_file() {
+        let store = temp_store("corrupt");
+        fs::write(store.path(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600), "not json").unwrap();
+
+        assert!(store.load(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600).is_empty());
+        let merged = store.merge(PriceSource::Bitstamp, QuoteCurrency::Usd, 3600, &[candle(0, 1.0)]).unwrap();
+        assert_eq!(merged, vec![candle(0, 1.0)]);
+    }
+
+    #[test]
+    fn remembers_where_history_begins() {
+        let store = temp_store("first");
+        assert_eq!(store.first_available(PriceSource::Bitstamp, QuoteCurrency::Eur, 86400), None);
+
+        store.set_first_available(PriceSource::Bitstamp, QuoteCurrency::Eur, 86400, 1_313_625_600).unwrap();
+        assert_eq!(store.first_available(PriceSource::Bitstamp, QuoteCurrency::Eur, 86400), Some(1_313_625_600));
+        assert_eq!(store.first_available(PriceSource::Bitstamp, QuoteCurrency::Usd, 86400), None);
+        // The candles themselves are untouched
+        assert!(store.load(PriceSource::Bitstamp, QuoteCurrency::Eur, 86400).is_empty());
+    }
+}
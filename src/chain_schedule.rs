@@ -0,0 +1,163 @@
+// Difficulty epoch and halving schedule derived from the block height
+
+pub const BLOCKS_PER_EPOCH: u32 = 2016;
+pub const BLOCKS_PER_HALVING: u32 = 210_000;
+pub const TARGET_BLOCK_SECS: f64 = 600.0;
+
+// Block subsidy of the first halving era in satoshis
+const INITIAL_SUBSIDY_SATS: u64 = 50 * 100_000_000;
+
+// Progress through the current difficulty epoch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpochProgress {
+    pub blocks_into_epoch: u32,
+    pub remaining_blocks: u32,
+    pub progress_percent: f64,
+    pub next_retarget_height: u32,
+    // Estimated Unix timestamp of the retarget
+    pub estimated_retarget: i64,
+    // Expected difficulty change if blocks keep coming at `avg_block_secs` (percent)
+    pub estimated_change_percent: f64,
+}
+
+/// Progress through the difficulty epoch containing `height`, with the retarget
+/// estimated from `now` and the average block time of the epoch so far
+pub fn epoch_progress(height: u32, now: i64, avg_block_secs: f64) -> EpochProgress {
+    let blocks_into_epoch = height % BLOCKS_PER_EPOCH;
+    let remaining_blocks = BLOCKS_PER_EPOCH - blocks_into_epoch;
+    let avg_block_secs = if avg_block_secs > 0.0 { avg_block_secs } else { TARGET_BLOCK_SECS };
+
+    // The retarget is capped at a factor of four in either direction
+    let change_factor = (TARGET_BLOCK_SECS / avg_block_secs).clamp(0.25, 4.0);
+
+    EpochProgress {
+        blocks_into_epoch,
+        remaining_blocks,
+        progress_percent: blocks_into_epoch as f64 / BLOCKS_PER_EPOCH as f64 * 100.0,
+        next_retarget_height: height + remaining_blocks,
+        estimated_retarget: now + (remaining_blocks as f64 * avg_block_secs) as i64,
+        estimated_change_percent: (change_factor - 1.0) * 100.0,
+    }
+}
+
+// Countdown to the next subsidy halving
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HalvingCountdown {
+    pub next_halving_height: u32,
+    pub remaining_blocks: u32,
+    pub progress_percent: f64,
+    // Estimated Unix timestamp of the halving
+    pub estimated_date: i64,
+    pub current_subsidy_sats: u64,
+    pub next_subsidy_sats: u64,
+}
+
+/// Countdown to the halving after `height`, estimated at `avg_block_secs` per block
+pub fn halving_countdown(height: u32, now: i64, avg_block_secs: f64) -> HalvingCountdown {
+    let next_halving_height = (height / BLOCKS_PER_HALVING + 1) * BLOCKS_PER_HALVING;
+    let remaining_blocks = next_halving_height - height;
+    let avg_block_secs = if avg_block_secs > 0.0 { avg_block_secs } else { TARGET_BLOCK_SECS };
+
+    HalvingCountdown {
+        next_halving_height,
+        remaining_blocks,
+        progress_percent: (height % BLOCKS_PER_HALVING) as f64 / BLOCKS_PER_HALVING as f64 * 100.0,
+        estimated_date: now + (remaining_blocks as f64 * avg_block_secs) as i64,
+        current_subsidy_sats: block_subsidy_sats(height),
+        next_subsidy_sats: block_subsidy_sats(next_halving_height),
+    }
+}
+
+/// Block subsidy at `height` in satoshis
+pub fn block_subsidy_sats(height: u32) -> u64 {
+    let halvings = height / BLOCKS_PER_HALVING;
+    if halvings >= 64 {
+        0
+    } else {
+        INITIAL_SUBSIDY_SATS >> halvings
+    }
+}
+
+// Format a time span for countdowns, e.g. "412 days" or "5h 12m"
+pub fn format_countdown(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds >= 2 * 86_400 {
+        format!("{} days", seconds / 86_400)
+    } else {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn epoch_boundary_starts_a_new_epoch() {
+        let progress = epoch_progress(2016 * 420, NOW, TARGET_BLOCK_SECS);
+        assert_eq!(progress.blocks_into_epoch, 0);
+        assert_eq!(progress.remaining_blocks, 2016);
+        assert_eq!(progress.progress_percent, 0.0);
+        assert_eq!(progress.next_retarget_height, 2016 * 421);
+        assert_eq!(progress.estimated_retarget, NOW + 2016 * 600);
+        assert_eq!(progress.estimated_change_percent, 0.0);
+    }
+
+    #[test]
+    fn last_block_of_an_epoch() {
+        let progress = epoch_progress(2016 * 421 - 1, NOW, 540.0);
+        assert_eq!(progress.blocks_into_epoch, 2015);
+        assert_eq!(progress.remaining_blocks, 1);
+        assert_eq!(progress.next_retarget_height, 2016 * 421);
+        assert_eq!(progress.estimated_retarget, NOW + 540);
+        // Blocks 10% faster than target raise the difficulty by 1/0.9
+        assert!((progress.estimated_change_percent - 11.111).abs() < 0.001);
+    }
+
+    #[test]
+    fn difficulty_change_is_capped() {
+        assert_eq!(epoch_progress(100, NOW, 60.0).estimated_change_percent, 300.0);
+        assert_eq!(epoch_progress(100, NOW, 6000.0).estimated_change_percent, -75.0);
+        // Without a usable average the target block time is assumed
+        assert_eq!(epoch_progress(100, NOW, 0.0).estimated_retarget, NOW + 1916 * 600);
+    }
+
+    #[test]
+    fn one_block_before_a_halving() {
+        let countdown = halving_countdown(839_999, NOW, TARGET_BLOCK_SECS);
+        assert_eq!(countdown.next_halving_height, 840_000);
+        assert_eq!(countdown.remaining_blocks, 1);
+        assert_eq!(countdown.estimated_date, NOW + 600);
+        assert_eq!(countdown.current_subsidy_sats, 625_000_000);
+        assert_eq!(countdown.next_subsidy_sats, 312_500_000);
+        assert!((countdown.progress_percent - 99.9995).abs() < 0.0001);
+    }
+
+    #[test]
+    fn at_the_fourth_halving() {
+        let countdown = halving_countdown(840_000, NOW, TARGET_BLOCK_SECS);
+        assert_eq!(countdown.next_halving_height, 1_050_000);
+        assert_eq!(countdown.remaining_blocks, 210_000);
+        assert_eq!(countdown.progress_percent, 0.0);
+        assert_eq!(countdown.current_subsidy_sats, 312_500_000);
+        assert_eq!(countdown.next_subsidy_sats, 156_250_000);
+    }
+
+    #[test]
+    fn subsidy_runs_out() {
+        assert_eq!(block_subsidy_sats(0), 5_000_000_000);
+        assert_eq!(block_subsidy_sats(209_999), 5_000_000_000);
+        assert_eq!(block_subsidy_sats(210_000), 2_500_000_000);
+        assert_eq!(block_subsidy_sats(BLOCKS_PER_HALVING * 33), 0);
+        assert_eq!(block_subsidy_sats(BLOCKS_PER_HALVING * 64), 0);
+    }
+
+    #[test]
+    fn formats_countdowns() {
+        assert_eq!(format_countdown(412 * 86_400 + 5), "412 days");
+        assert_eq!(format_countdown(5 * 3600 + 12 * 60), "5h 12m");
+        assert_eq!(format_countdown(-30), "0h 00m");
+    }
+}
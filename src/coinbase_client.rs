@@ -0,0 +1,257 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize};
+use chrono::{Utc, TimeZone};
+
+use crate::price_provider::{ChartTimeframe, PriceCandle, PriceProvider, PriceSource, QuoteCurrency, parse_price_field};
+use crate::proxy::{HttpClient, ProxySource};
+
+// Default Coinbase Exchange API URL
+pub const DEFAULT_COINBASE_API_URL: &str = "https://api.exchange.coinbase.com";
+
+// Candle sizes (in seconds) the Coinbase candles endpoint accepts
+const SUPPORTED_GRANULARITIES: [u32; 6] = [60, 300, 900, 3600, 21600, 86400];
+
+// Coinbase returns at most 300 candles per request
+const MAX_CANDLES_PER_REQUEST: i64 = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct CoinbaseTicker {
+    pub price: String,
+}
+
+// Coinbase API client for handling all API interactions
+pub struct CoinbaseClient {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl CoinbaseClient {
+    /// Create a new CoinbaseClient with the default Coinbase Exchange API URL
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_COINBASE_API_URL)
+    }
+
+    /// Create a new CoinbaseClient with a custom API URL (e.g. a local mock server)
+    pub fn with_url(base_url: &str) -> Self {
+        // Coinbase rejects requests that don't send a User-Agent
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)) // 10 second timeout
+            .user_agent(concat!("btc-ticker/", env!("CARGO_PKG_VERSION")));
+        let client = HttpClient::build(ProxySource::Exchanges, base_url, builder);
+
+        CoinbaseClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // Fetch raw candles of the given granularity between start and end, paging as needed
+    fn fetch_candles(&self, currency: QuoteCurrency, granularity: u32, start: i64, end: i64) -> Result<Vec<PriceCandle>> {
+        let mut candles = Vec::new();
+        let page_span = granularity as i64 * MAX_CANDLES_PER_REQUEST;
+
+        let mut page_start = start;
+        while page_start < end {
+            let page_end = (page_start + page_span).min(end);
+            let url = format!(
+                "{}/products/BTC-{}/candles?granularity={}&start={}&end={}",
+                self.base_url,
+                currency.code(),
+                granularity,
+                format_iso8601(page_start),
+                format_iso8601(page_end)
+            );
+            println!("Fetching historical data from: {}", url);
+
+            let response = self.client.get(&url)?
+                .send()
+                .map_err(|e| anyhow!("Failed to fetch historical data: {}", e))?;
+
+            if !response.status().is_success() {
+                return Err(anyhow!("Historical API returned error status: {}", response.status()));
+            }
+
+            // Each row is [time, low, high, open, close, volume]
+            let rows: Vec<Vec<f64>> = response.json()
+                .map_err(|e| anyhow!("Failed to parse historical data: {}", e))?;
+
+            for row in rows {
+                if row.len() < 5 {
+                    return Err(anyhow!("Unexpected Coinbase candle row: {:?}", row));
+                }
+                candles.push(PriceCandle {
+                    timestamp: row[0] as i64,
+                    low: row[1],
+                    high: row[2],
+                    open: row[3],
+                    close: row[4],
+                });
+            }
+
+            page_start = page_end;
+        }
+
+        // Coinbase returns newest first and pages may overlap on their boundaries
+        candles.sort_by_key(|candle| candle.timestamp);
+        candles.dedup_by_key(|candle| candle.timestamp);
+
+        Ok(candles)
+    }
+}
+
+impl PriceProvider for CoinbaseClient {
+    fn name(&self) -> &'static str {
+        "Coinbase"
+    }
+
+    fn fetch_current_price(&self, currency: QuoteCurrency) -> Result<f64> {
+        PriceSource::Coinbase.ensure_supports(currency)?;
+        let url = format!("{}/products/BTC-{}/ticker", self.base_url, currency.code());
+
+        println!("Fetching current BTC price from: {}", url);
+
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch price: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+
+        let ticker: CoinbaseTicker = response.json()
+            .map_err(|e| anyhow!("Failed to parse price response: {}", e))?;
+
+        parse_price_field(&ticker.price, "price")
+    }
+
+    fn fetch_historical_prices(&self, timeframe: ChartTimeframe, currency: QuoteCurrency) -> Result<Vec<PriceCandle>> {
+        PriceSource::Coinbase.ensure_supports(currency)?;
+        let (step, limit) = timeframe.api_params();
+
+        // Coinbase has no 4-hour candles, so fall back to the largest supported
+        // granularity that evenly divides the step and aggregate afterwards
+        let granularity = SUPPORTED_GRANULARITIES.iter()
+            .rev()
+            .copied()
+            .find(|granularity| step % granularity == 0)
+            .ok_or_else(|| anyhow!("Coinbase does not support a {} second step", step))?;
+
+        let end = Utc::now().timestamp();
+        let start = end - step as i64 * limit as i64;
+        println!("Fetching historical data for {} from Coinbase", timeframe.description());
+
+        let raw = self.fetch_candles(currency, granularity, start, end)?;
+        let candles = if granularity == step {
+            raw
+        } else {
+            aggregate_candles(&raw, step)
+        };
+
+        // Only keep the most recent candles for the requested timeframe
+        let skip = candles.len().saturating_sub(limit as usize);
+        let candles: Vec<PriceCandle> = candles.into_iter().skip(skip).collect();
+
+        println!("Successfully parsed historical data for {} ({} candles)",
+                timeframe.description(), candles.len());
+
+        Ok(candles)
+    }
+}
+
+// Merge consecutive candles (oldest first) into buckets of `step` seconds
+fn aggregate_candles(candles: &[PriceCandle], step: u32) -> Vec<PriceCandle> {
+    let step = step as i64;
+    let mut aggregated: Vec<PriceCandle> = Vec::new();
+
+    for candle in candles {
+        let bucket = candle.timestamp - candle.timestamp.rem_euclid(step);
+        match aggregated.last_mut() {
+            Some(current) if current.timestamp == bucket => {
+                current.high = current.high.max(candle.high);
+                current.low = current.low.min(candle.low);
+                current.close = candle.close;
+            }
+            _ => aggregated.push(PriceCandle { timestamp: bucket, ..*candle }),
+        }
+    }
+
+    aggregated
+}
+
+// Coinbase expects ISO 8601 timestamps for the start/end parameters
+fn format_iso8601(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|datetime| datetime.to_rfc3339())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn parses_ticker() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.path, "/products/BTC-GBP/ticker");
+            assert!(request.header("User-Agent").is_some());
+            (200, r#"{"trade_id": 1, "price": "51234.56", "size": "0.1"}"#.to_string())
+        });
+
+        let price = CoinbaseClient::with_url(&url).fetch_current_price(QuoteCurrency::Gbp).unwrap();
+        assert_eq!(price, 51234.56);
+    }
+
+    #[test]
+    fn aggregates_hourly_candles_into_four_hours() {
+        // Two 4-hour buckets of hourly candles, newest first like Coinbase sends them.
+        // Rows are [time, low, high, open, close, volume].
+        let bucket = 1_700_006_400;
+        let url = test_support::serve(move |request| {
+            assert!(request.path.starts_with("/products/BTC-USD/candles?granularity=3600&"), "{}", request.path);
+            let rows: Vec<String> = (0..8).rev()
+                .map(|hour| {
+                    let open = 100.0 + hour as f64;
+                    format!("[{}, {}, {}, {}, {}, 1.0]", bucket + hour * 3600, open - 5.0, open + 10.0, open, open + 1.0)
+                })
+                .collect();
+            (200, format!("[{}]", rows.join(",")))
+        });
+
+        let candles = CoinbaseClient::with_url(&url)
+            .fetch_historical_prices(ChartTimeframe::Week, QuoteCurrency::Usd)
+            .unwrap();
+        assert_eq!(candles, vec![
+            PriceCandle { timestamp: bucket, open: 100.0, high: 113.0, low: 95.0, close: 104.0 },
+            PriceCandle { timestamp: bucket + 14_400, open: 104.0, high: 117.0, low: 99.0, close: 108.0 },
+        ]);
+    }
+
+    #[test]
+    fn rejects_short_candle_rows() {
+        let url = test_support::serve_json("[[1700000000, 1.0, 2.0]]");
+
+        let result = CoinbaseClient::with_url(&url).fetch_historical_prices(ChartTimeframe::Hours24, QuoteCurrency::Usd);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_unlisted_markets_without_a_request() {
+        let url = test_support::serve(|request| panic!("unexpected request to {}", request.path));
+
+        let error = CoinbaseClient::with_url(&url).fetch_current_price(QuoteCurrency::Jpy).unwrap_err();
+        assert!(error.to_string().contains("Unsupported currency"), "{}", error);
+        let error = CoinbaseClient::with_url(&url).fetch_historical_prices(ChartTimeframe::Week, QuoteCurrency::Jpy).unwrap_err();
+        assert!(error.to_string().contains("Unsupported currency"), "{}", error);
+    }
+
+    #[test]
+    fn reports_error_status() {
+        let url = test_support::serve(|_| (404, r#"{"message": "NotFound"}"#.to_string()));
+
+        let error = CoinbaseClient::with_url(&url).fetch_current_price(QuoteCurrency::Eur).unwrap_err();
+        assert!(error.to_string().contains("404"), "{}", error);
+    }
+}
@@ -1,16 +1,101 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
 use anyhow::{Result, anyhow};
 
+use crate::price_provider::{PriceSource, QuoteCurrency};
+use crate::alerts::{FeeAlert, PriceAlert};
+use crate::address_watch::WatchedAddress;
+use crate::watch_wallet::WatchedWallet;
+use crate::portfolio::CostBasisMethod;
+use crate::network_provider::NetworkBackend;
+use crate::bitcoin_rpc_client::{self, RpcAuthMethod, DEFAULT_BITCOIN_RPC_URL};
+use crate::electrum_client::DEFAULT_ELECTRUM_SERVER;
+use crate::mempool_auth::MempoolEndpointAccess;
+use crate::mempool_endpoints::{self, MempoolEndpoint};
+use crate::proxy::{ProxyRoute, ProxySource, DEFAULT_PROXY_HOST, DEFAULT_PROXY_PORT};
+
 // Default configuration values
 pub const DEFAULT_MEMPOOL_API_URL: &str = "https://mempool.space/api";
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)] // Fill in options missing from older config files
 pub struct AppConfig {
-    // Mempool configuration
+    // Mempool API instances in order of preference; the first healthy one is used
+    pub mempool_endpoints: Vec<MempoolEndpoint>,
+    // Single custom instance from older versions, moved into mempool_endpoints on load
+    #[serde(skip_serializing)]
     pub mempool_custom_url_enabled: bool,
+    #[serde(skip_serializing)]
     pub mempool_api_url: String,
+    // Receive mempool updates over the instance's WebSocket instead of polling
+    pub mempool_websocket_enabled: bool,
+    // Credentials, CA bundles and certificate pins per mempool API URL. The
+    // secrets themselves are stored in the OS keyring.
+    pub mempool_endpoint_access: Vec<MempoolEndpointAccess>,
+    
+    // Backend for block, fee and mempool data, and the Bitcoin Core RPC connection
+    pub network_backend: NetworkBackend,
+    pub bitcoin_rpc_url: String,
+    pub bitcoin_rpc_auth: RpcAuthMethod,
+    // Empty for the default data directory's .cookie
+    pub bitcoin_rpc_cookie_file: String,
+    pub bitcoin_rpc_user: String,
+    // Kept in the OS keyring; only read from config.json to move a password
+    // saved there by older versions
+    #[serde(skip_serializing)]
+    pub bitcoin_rpc_password: String,
+    
+    // Electrum server as tcp://host:port or ssl://host:port, and whether to
+    // accept self-signed certificates
+    pub electrum_server: String,
+    pub electrum_accept_invalid_certs: bool,
+    
+    // SOCKS5 proxy (e.g. Tor) for outgoing connections, routes for individual
+    // sources, and whether to refuse connections that would bypass a proxy
+    pub proxy_enabled: bool,
+    pub proxy_host: String,
+    pub proxy_port: u16,
+    pub proxy_tor_only: bool,
+    pub proxy_overrides: BTreeMap<ProxySource, ProxyRoute>,
+    
+    // Exchange used for price and chart data
+    pub price_source: PriceSource,
+    
+    // Fiat currency prices and charts are quoted in
+    pub quote_currency: QuoteCurrency,
+    
+    // Stream live trades over Bitstamp's WebSocket instead of polling
+    pub price_streaming_enabled: bool,
+    
+    // Median price aggregation across several exchanges
+    pub price_aggregation_enabled: bool,
+    pub aggregation_sources: Vec<PriceSource>,
+    pub max_price_deviation_percent: f64,
+    
+    // Price alerts and how far (in percent) the price must move back before an alert re-arms
+    pub price_alerts: Vec<PriceAlert>,
+    pub alert_hysteresis_percent: f64,
+    
+    // Alerts for low-fee broadcast windows
+    pub fee_alerts: Vec<FeeAlert>,
+    
+    // Notify when new blocks are found, and how many recent blocks to list
+    pub block_notifications_enabled: bool,
+    pub recent_blocks_count: usize,
+    
+    // Addresses whose balances and incoming payments are monitored
+    pub watched_addresses: Vec<WatchedAddress>,
+    
+    // Watch-only wallets given by their extended public keys
+    pub watched_wallets: Vec<WatchedWallet>,
+    
+    // Confirmation depth at which tracked transactions notify a second time
+    pub tx_confirmation_target: u32,
+    
+    // How sells are matched to buys in the portfolio
+    pub cost_basis_method: CostBasisMethod,
     
     // Can add more configuration options here in the future
 }
@@ -18,18 +103,55 @@ pub struct AppConfig {
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {
+            mempool_endpoints: Vec::new(),
             mempool_custom_url_enabled: false,
             mempool_api_url: DEFAULT_MEMPOOL_API_URL.to_string(),
+            mempool_websocket_enabled: false,
+            mempool_endpoint_access: Vec::new(),
+            network_backend: NetworkBackend::default(),
+            bitcoin_rpc_url: DEFAULT_BITCOIN_RPC_URL.to_string(),
+            bitcoin_rpc_auth: RpcAuthMethod::default(),
+            bitcoin_rpc_cookie_file: String::new(),
+            bitcoin_rpc_user: String::new(),
+            bitcoin_rpc_password: String::new(),
+            electrum_server: DEFAULT_ELECTRUM_SERVER.to_string(),
+            electrum_accept_invalid_certs: false,
+            proxy_enabled: false,
+            proxy_host: DEFAULT_PROXY_HOST.to_string(),
+            proxy_port: DEFAULT_PROXY_PORT,
+            proxy_tor_only: false,
+            proxy_overrides: BTreeMap::new(),
+            price_source: PriceSource::default(),
+            quote_currency: QuoteCurrency::default(),
+            price_streaming_enabled: false,
+            price_aggregation_enabled: false,
+            aggregation_sources: PriceSource::ALL.to_vec(),
+            max_price_deviation_percent: 1.0,
+            price_alerts: Vec::new(),
+            alert_hysteresis_percent: 0.5,
+            fee_alerts: Vec::new(),
+            block_notifications_enabled: false,
+            recent_blocks_count: 10,
+            watched_addresses: Vec::new(),
+            watched_wallets: Vec::new(),
+            tx_confirmation_target: 6,
+            cost_basis_method: CostBasisMethod::default(),
         }
     }
 }
 
 impl AppConfig {
-    // Get the config file path in the user's config directory
-    pub fn get_config_path() -> PathBuf {
+    // Get the btc-ticker directory in the user's config directory
+    pub fn get_config_dir() -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("btc-ticker");
         fs::create_dir_all(&path).ok(); // Create directory if it doesn't exist
+        path
+    }
+    
+    // Get the config file path in the user's config directory
+    pub fn get_config_path() -> PathBuf {
+        let mut path = Self::get_config_dir();
         path.push("config.json");
         path
     }
@@ -41,8 +163,12 @@ impl AppConfig {
         if config_path.exists() {
             match fs::read_to_string(&config_path) {
                 Ok(content) => {
-                    match serde_json::from_str(&content) {
-                        Ok(config) => return config,
+                    match serde_json::from_str::<AppConfig>(&content) {
+                        Ok(mut config) => {
+                            config.migrate_mempool_endpoints();
+                            config.load_rpc_password();
+                            return config;
+                        },
                         Err(e) => {
                             eprintln!("Error parsing config file: {}", e);
                             // Fall back to default config
@@ -58,11 +184,44 @@ impl AppConfig {
         
         // If we get here, either the file doesn't exist or there was an error
         // Create default config and save it
-        let default_config = AppConfig::default();
+        let mut default_config = AppConfig::default();
+        default_config.migrate_mempool_endpoints();
         default_config.save().ok(); // Ignore errors on first save
         default_config
     }
     
+    // Fill an empty endpoint list from the older single custom URL setting.
+    // A custom instance stays the only endpoint, so a self-hosted setup never
+    // falls back to mempool.space without the user adding it.
+    fn migrate_mempool_endpoints(&mut self) {
+        if !self.mempool_endpoints.is_empty() {
+            return;
+        }
+        if self.mempool_custom_url_enabled && !self.mempool_api_url.trim().is_empty() {
+            self.mempool_endpoints.push(MempoolEndpoint::new("Custom", &self.mempool_api_url));
+        } else {
+            self.mempool_endpoints = mempool_endpoints::default_endpoints();
+        }
+        self.mempool_custom_url_enabled = false;
+    }
+    
+    // Move an RPC password from config.json to the keyring, or read it from
+    // there when user/password auth is in use
+    fn load_rpc_password(&mut self) {
+        if !self.bitcoin_rpc_password.is_empty() {
+            match bitcoin_rpc_client::store_password(&self.bitcoin_rpc_password) {
+                // Rewrite the file without the password
+                Ok(()) => { self.save().ok(); },
+                Err(e) => eprintln!("Failed to move the RPC password to the keyring: {}", e),
+            }
+        } else if self.bitcoin_rpc_auth == RpcAuthMethod::UserPassword {
+            match bitcoin_rpc_client::load_password() {
+                Ok(password) => self.bitcoin_rpc_password = password.unwrap_or_default(),
+                Err(e) => eprintln!("Failed to load the RPC password from the keyring: {}", e),
+            }
+        }
+    }
+    
     // Save configuration to file
     pub fn save(&self) -> Result<()> {
         let config_path = Self::get_config_path();
@@ -75,3 +234,49 @@ impl AppConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated(json: &str) -> AppConfig {
+        let mut config: AppConfig = serde_json::from_str(json).unwrap();
+        config.migrate_mempool_endpoints();
+        config
+    }
+
+    #[test]
+    fn custom_url_migrates_without_the_public_default() {
+        let config = migrated(r#"{"mempool_custom_url_enabled": true, "mempool_api_url": "http://umbrel.local:3006/api"}"#);
+        assert_eq!(config.mempool_endpoints, [MempoolEndpoint::new("Custom", "http://umbrel.local:3006/api")]);
+        assert!(!config.mempool_custom_url_enabled);
+    }
+
+    #[test]
+    fn configs_without_a_custom_url_get_the_default() {
+        // A custom URL that was switched off is not migrated
+        let config = migrated(r#"{"mempool_custom_url_enabled": false, "mempool_api_url": "http://umbrel.local:3006/api"}"#);
+        assert_eq!(config.mempool_endpoints, mempool_endpoints::default_endpoints());
+
+        let config = migrated("{}");
+        assert_eq!(config.mempool_endpoints, mempool_endpoints::default_endpoints());
+    }
+
+    #[test]
+    fn configured_endpoints_are_left_alone() {
+        let config = migrated(r#"{
+            "mempool_endpoints": [{"name": "Home", "url": "http://10.0.0.5/api"}],
+            "mempool_custom_url_enabled": true,
+            "mempool_api_url": "https://mempool.space/api"
+        }"#);
+        assert_eq!(config.mempool_endpoints, [MempoolEndpoint { name: "Home".to_string(), url: "http://10.0.0.5/api".to_string() }]);
+    }
+
+    #[test]
+    fn rpc_password_is_not_saved() {
+        let config = AppConfig { bitcoin_rpc_password: "hunter2".to_string(), ..AppConfig::default() };
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(!json.contains("bitcoin_rpc_password"));
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Duration, Local};
+
+// Connectivity of a single data source at a point in time
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceStatus {
+    // Nothing has been fetched yet
+    Unknown,
+    Fresh,
+    // The last fetch succeeded, but longer ago than the source's refresh interval allows
+    Stale { since: DateTime<Local> },
+    // The most recent fetch failed
+    Failing { error: String, last_success: Option<DateTime<Local>> },
+}
+
+/// Tracks fetch results of one data source (e.g. price, blocks, fees)
+#[derive(Debug, Clone)]
+pub struct SourceHealth {
+    pub name: &'static str,
+    pub last_success: Option<DateTime<Local>>,
+    pub last_error: Option<String>,
+    // Data older than this is considered stale
+    stale_after: Duration,
+}
+
+impl SourceHealth {
+    pub fn new(name: &'static str, stale_after_secs: i64) -> Self {
+        SourceHealth {
+            name,
+            last_success: None,
+            last_error: None,
+            stale_after: Duration::seconds(stale_after_secs),
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.last_success = Some(Local::now());
+        self.last_error = None;
+    }
+
+    pub fn record_failure(&mut self, error: impl ToString) {
+        self.last_error = Some(error.to_string());
+    }
+
+    /// Status of the source as of `now`
+    pub fn status_at(&self, now: DateTime<Local>) -> SourceStatus {
+        if let Some(error) = &self.last_error {
+            return SourceStatus::Failing {
+                error: error.clone(),
+                last_success: self.last_success,
+            };
+        }
+
+        match self.last_success {
+            None => SourceStatus::Unknown,
+            Some(last_success) if now - last_success > self.stale_after => {
+                SourceStatus::Stale { since: last_success }
+            },
+            Some(_) => SourceStatus::Fresh,
+        }
+    }
+
+    pub fn status(&self) -> SourceStatus {
+        self.status_at(Local::now())
+    }
+
+    /// Short human readable description, e.g. "stale since 14:03"
+    pub fn describe_at(&self, now: DateTime<Local>) -> String {
+        match self.status_at(now) {
+            SourceStatus::Unknown => "waiting for data".to_string(),
+            SourceStatus::Fresh => "fresh".to_string(),
+            SourceStatus::Stale { since } => format!("stale since {}", format_since(since, now)),
+            SourceStatus::Failing { error, last_success: Some(since) } => {
+                format!("failing (data from {}): {}", format_since(since, now), error)
+            },
+            SourceStatus::Failing { error, last_success: None } => format!("failing: {}", error),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.describe_at(Local::now())
+    }
+}
+
+// Show just the time for today's timestamps, otherwise include the date
+fn format_since(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    if time.date_naive() == now.date_naive() {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 5, 1, hour, minute, second).unwrap()
+    }
+
+    fn succeeded_at(time: DateTime<Local>) -> SourceHealth {
+        let mut health = SourceHealth::new("Price", 120);
+        health.last_success = Some(time);
+        health
+    }
+
+    #[test]
+    fn unknown_until_the_first_fetch() {
+        let health = SourceHealth::new("Price", 120);
+
+        assert_eq!(health.status_at(at(14, 0, 0)), SourceStatus::Unknown);
+        assert_eq!(health.describe_at(at(14, 0, 0)), "waiting for data");
+    }
+
+    #[test]
+    fn fresh_up_to_the_stale_threshold() {
+        let health = succeeded_at(at(14, 0, 0));
+
+        assert_eq!(health.status_at(at(14, 0, 0)), SourceStatus::Fresh);
+        assert_eq!(health.status_at(at(14, 2, 0)), SourceStatus::Fresh);
+    }
+
+    #[test]
+    fn stale_once_past_the_threshold() {
+        let health = succeeded_at(at(14, 0, 0));
+
+        assert_eq!(health.status_at(at(14, 2, 1)), SourceStatus::Stale { since: at(14, 0, 0) });
+        assert_eq!(health.describe_at(at(14, 30, 0)), "stale since 14:00");
+        assert_eq!(
+            health.describe_at(Local.with_ymd_and_hms(2024, 5, 2, 9, 0, 0).unwrap()),
+            "stale since 2024-05-01 14:00"
+        );
+    }
+
+    #[test]
+    fn failing_regardless_of_age() {
+        let mut health = succeeded_at(at(14, 0, 0));
+        health.record_failure("connection refused");
+
+        // Even while the last good data would still count as fresh
+        assert_eq!(health.status_at(at(14, 0, 30)), SourceStatus::Failing {
+            error: "connection refused".to_string(),
+            last_success: Some(at(14, 0, 0)),
+        });
+        assert_eq!(health.describe_at(at(14, 5, 0)), "failing (data from 14:00): connection refused");
+
+        let mut health = SourceHealth::new("Price", 120);
+        health.record_failure("timed out");
+        assert_eq!(health.describe_at(at(14, 0, 0)), "failing: timed out");
+    }
+
+    #[test]
+    fn recovers_after_a_success() {
+        let mut health = succeeded_at(at(14, 0, 0));
+        health.record_failure("connection refused");
+        health.record_success();
+
+        assert_eq!(health.status(), SourceStatus::Fresh);
+    }
+}
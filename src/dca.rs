@@ -0,0 +1,317 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Months, Utc};
+
+use crate::bitstamp_client::BitstampClient;
+use crate::candle_store::CandleStore;
+use crate::price_provider::{PriceCandle, PriceSource, QuoteCurrency};
+
+// Daily candles, shared with the chart's daily timeframes in the candle cache
+const DAILY_STEP: u32 = 86_400;
+
+// How often a purchase is made
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcaFrequency {
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl DcaFrequency {
+    pub const ALL: [DcaFrequency; 4] = [
+        DcaFrequency::Daily,
+        DcaFrequency::Weekly,
+        DcaFrequency::Biweekly,
+        DcaFrequency::Monthly,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DcaFrequency::Daily => "Daily",
+            DcaFrequency::Weekly => "Weekly",
+            DcaFrequency::Biweekly => "Every 2 weeks",
+            DcaFrequency::Monthly => "Monthly",
+        }
+    }
+
+    // Date of the purchase after `date`
+    fn next(&self, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            DcaFrequency::Daily => date.checked_add_signed(Duration::days(1)),
+            DcaFrequency::Weekly => date.checked_add_signed(Duration::weeks(1)),
+            DcaFrequency::Biweekly => date.checked_add_signed(Duration::weeks(2)),
+            DcaFrequency::Monthly => date.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+// A recurring purchase schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DcaPlan {
+    // Fiat spent per purchase
+    pub amount: f64,
+    pub frequency: DcaFrequency,
+    // Unix timestamps of the first and last day of the schedule
+    pub start: i64,
+    pub end: i64,
+}
+
+// Portfolio state on one day of the simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DcaPoint {
+    pub timestamp: i64,
+    pub invested: f64,
+    pub value: f64,
+}
+
+// Outcome of replaying a plan against historical closes
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcaResult {
+    pub currency: QuoteCurrency,
+    pub purchases: usize,
+    pub total_invested: f64,
+    pub total_btc: f64,
+    pub average_price: f64,
+    pub current_value: f64,
+    pub profit: f64,
+    pub profit_percent: f64,
+    // Daily invested amount and market value, for the chart
+    pub series: Vec<DcaPoint>,
+}
+
+/// Replay `plan` buying at the daily close of each purchase date and value the
+/// result at `current_price` (the last close if no live price is known)
+pub fn simulate(plan: &DcaPlan, candles: &[PriceCandle], currency: QuoteCurrency, current_price: f64) -> Result<DcaResult> {
+    if plan.amount <= 0.0 {
+        return Err(anyhow!("The purchase amount must be positive"));
+    }
+    if plan.end < plan.start {
+        return Err(anyhow!("The end date is before the start date"));
+    }
+
+    let candles: Vec<&PriceCandle> = candles.iter()
+        .filter(|candle| candle.timestamp + DAILY_STEP as i64 > plan.start && candle.timestamp <= plan.end)
+        .collect();
+    if candles.is_empty() {
+        return Err(anyhow!("No price history in the selected range"));
+    }
+
+    let mut next_purchase = DateTime::from_timestamp(plan.start, 0);
+    let mut purchases = 0;
+    let mut total_invested = 0.0;
+    let mut total_btc = 0.0;
+    let mut series = Vec::with_capacity(candles.len());
+
+    for candle in &candles {
+        // Buy on every purchase date falling within this candle's day
+        while let Some(date) = next_purchase {
+            if date.timestamp() >= candle.timestamp + DAILY_STEP as i64 || date.timestamp() > plan.end {
+                break;
+            }
+            if candle.close > 0.0 {
+                total_invested += plan.amount;
+                total_btc += plan.amount / candle.close;
+                purchases += 1;
+            }
+            next_purchase = plan.frequency.next(date);
+        }
+
+        series.push(DcaPoint {
+            timestamp: candle.timestamp,
+            invested: total_invested,
+            value: total_btc * candle.close,
+        });
+    }
+
+    if purchases == 0 {
+        return Err(anyhow!("No purchases fall within the available price history"));
+    }
+
+    let price = if current_price > 0.0 {
+        current_price
+    } else {
+        candles.last().map(|candle| candle.close).unwrap_or(0.0)
+    };
+    let current_value = total_btc * price;
+    let profit = current_value - total_invested;
+
+    Ok(DcaResult {
+        currency,
+        purchases,
+        total_invested,
+        total_btc,
+        average_price: total_invested / total_btc,
+        current_value,
+        profit,
+        profit_percent: profit / total_invested * 100.0,
+        series,
+    })
+}
+
+/// Daily Bitstamp candles covering `start` to `end`, fetching only what the
+/// candle cache doesn't already hold
+pub fn load_daily_history(currency: QuoteCurrency, start: i64, end: i64) -> Result<Vec<PriceCandle>> {
+    let store = CandleStore::new();
+    let cached = store.load(PriceSource::Bitstamp, currency, DAILY_STEP);
+    let first_available = store.first_available(PriceSource::Bitstamp, currency, DAILY_STEP);
+    let day = DAILY_STEP as i64;
+    let now = Utc::now().timestamp();
+
+    let candles = match fetch_from(&cached, first_available, start, end, now) {
+        Some(from) => {
+            let from = from - from.rem_euclid(day);
+            let fetched = BitstampClient::new().fetch_daily_range(currency, from, now)?;
+            // Nothing came back for the first days, so the pair wasn't trading yet;
+            // remember that so later runs don't ask for those days again
+            if let Some(first) = fetched.first().filter(|first| first.timestamp > from) {
+                store.set_first_available(PriceSource::Bitstamp, currency, DAILY_STEP, first.timestamp)?;
+            }
+            store.merge(PriceSource::Bitstamp, currency, DAILY_STEP, &fetched)?
+        },
+        None => cached,
+    };
+
+    Ok(candles)
+}
+
+// Where to start fetching so the cache covers `start` to `end`, or None if it
+// already does. The cache only ever grows towards the present, so fetch either
+// from the requested start or from the last cached day up to now. Days before
+// the exchange's first candle (`first_available`) are never fetched.
+fn fetch_from(cached: &[PriceCandle], first_available: Option<i64>, start: i64, end: i64, now: i64) -> Option<i64> {
+    let start = first_available.map_or(start, |first| start.max(first));
+    match (cached.first(), cached.last()) {
+        (Some(first), _) if first.timestamp > start => Some(start),
+        (Some(_), Some(last)) if last.timestamp + DAILY_STEP as i64 <= end.min(now) => Some(last.timestamp),
+        (Some(_), _) => None,
+        (None, _) => Some(start),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01 00:00 UTC
+    const JAN_1: i64 = 1_704_067_200;
+    const DAY: i64 = DAILY_STEP as i64;
+
+    fn daily_candles(start: i64, closes: &[f64]) -> Vec<PriceCandle> {
+        closes.iter()
+            .enumerate()
+            .map(|(i, &close)| PriceCandle { timestamp: start + i as i64 * DAY, open: close, high: close, low: close, close })
+            .collect()
+    }
+
+    fn plan(frequency: DcaFrequency, start: i64, end: i64) -> DcaPlan {
+        DcaPlan { amount: 100.0, frequency, start, end }
+    }
+
+    #[test]
+    fn buys_weekly_at_the_daily_close() {
+        let closes: Vec<f64> = (1..=10).map(|i| i as f64 * 100.0).collect();
+        let candles = daily_candles(JAN_1, &closes);
+
+        let result = simulate(&plan(DcaFrequency::Weekly, JAN_1, JAN_1 + 9 * DAY), &candles, QuoteCurrency::Usd, 0.0).unwrap();
+        // Day 0 at 100 and day 7 at 800, valued at the last close of 1000
+        assert_eq!(result.purchases, 2);
+        assert_eq!(result.total_invested, 200.0);
+        assert_eq!(result.total_btc, 1.125);
+        assert_eq!(result.average_price, 200.0 / 1.125);
+        assert_eq!(result.current_value, 1125.0);
+        assert_eq!(result.profit, 925.0);
+        assert_eq!(result.profit_percent, 462.5);
+
+        assert_eq!(result.series.len(), 10);
+        assert_eq!(result.series[6], DcaPoint { timestamp: JAN_1 + 6 * DAY, invested: 100.0, value: 700.0 });
+        assert_eq!(result.series[7], DcaPoint { timestamp: JAN_1 + 7 * DAY, invested: 200.0, value: 900.0 });
+    }
+
+    #[test]
+    fn values_at_the_live_price() {
+        let candles = daily_candles(JAN_1, &[100.0, 100.0]);
+
+        let result = simulate(&plan(DcaFrequency::Daily, JAN_1, JAN_1 + DAY), &candles, QuoteCurrency::Eur, 150.0).unwrap();
+        assert_eq!(result.purchases, 2);
+        assert_eq!(result.current_value, 300.0);
+        assert_eq!(result.profit_percent, 50.0);
+        assert_eq!(result.currency, QuoteCurrency::Eur);
+    }
+
+    #[test]
+    fn buys_monthly_on_the_same_day() {
+        // January through March 2024
+        let candles = daily_candles(JAN_1, &[100.0; 91]);
+
+        let start = JAN_1 + 14 * DAY;
+        let result = simulate(&plan(DcaFrequency::Monthly, start, JAN_1 + 90 * DAY), &candles, QuoteCurrency::Usd, 0.0).unwrap();
+        // Jan 15, Feb 15 and Mar 15; candles before the start aren't charted
+        assert_eq!(result.purchases, 3);
+        assert_eq!(result.series.len(), 91 - 14);
+    }
+
+    #[test]
+    fn purchase_on_a_missing_day_uses_the_next_close() {
+        let mut candles = daily_candles(JAN_1, &[100.0, 100.0, 0.0, 200.0]);
+        candles.remove(2);
+
+        let result = simulate(&plan(DcaFrequency::Daily, JAN_1, JAN_1 + 3 * DAY), &candles, QuoteCurrency::Usd, 0.0).unwrap();
+        assert_eq!(result.purchases, 4);
+        assert_eq!(result.total_btc, 3.0);
+    }
+
+    #[test]
+    fn rejects_invalid_plans() {
+        let candles = daily_candles(JAN_1, &[100.0; 5]);
+
+        let zero_amount = DcaPlan { amount: 0.0, ..plan(DcaFrequency::Daily, JAN_1, JAN_1 + DAY) };
+        assert!(simulate(&zero_amount, &candles, QuoteCurrency::Usd, 0.0).is_err());
+        assert!(simulate(&plan(DcaFrequency::Daily, JAN_1 + DAY, JAN_1), &candles, QuoteCurrency::Usd, 0.0).is_err());
+        // Entirely after the available history
+        assert!(simulate(&plan(DcaFrequency::Daily, JAN_1 + 10 * DAY, JAN_1 + 20 * DAY), &candles, QuoteCurrency::Usd, 0.0).is_err());
+    }
+
+    #[test]
+    fn fetches_everything_without_a_cache() {
+        assert_eq!(fetch_from(&[], None, JAN_1, JAN_1 + 30 * DAY, JAN_1 + 60 * DAY), Some(JAN_1));
+    }
+
+    #[test]
+    fn fetches_from_the_start_when_the_cache_begins_later() {
+        let cached = daily_candles(JAN_1 + 10 * DAY, &[100.0; 50]);
+        assert_eq!(fetch_from(&cached, None, JAN_1, JAN_1 + 30 * DAY, JAN_1 + 60 * DAY), Some(JAN_1));
+    }
+
+    #[test]
+    fn fetches_only_the_missing_tail() {
+        let cached = daily_candles(JAN_1, &[100.0; 20]);
+        assert_eq!(fetch_from(&cached, None, JAN_1, JAN_1 + 30 * DAY, JAN_1 + 60 * DAY), Some(JAN_1 + 19 * DAY));
+    }
+
+    #[test]
+    fn uses_an_up_to_date_cache() {
+        let cached = daily_candles(JAN_1, &[100.0; 31]);
+        assert_eq!(fetch_from(&cached, None, JAN_1 + DAY, JAN_1 + 30 * DAY, JAN_1 + 60 * DAY), None);
+
+        // Today's candle is still in progress, so an end in the future doesn't refetch
+        let now = JAN_1 + 30 * DAY + 3600;
+        assert_eq!(fetch_from(&cached, None, JAN_1, now + 365 * DAY, now), None);
+    }
+
+    #[test]
+    fn does_not_refetch_before_the_first_candle() {
+        // The pair started trading on day 10 and the cache holds everything since
+        let cached = daily_candles(JAN_1 + 10 * DAY, &[100.0; 50]);
+        let now = JAN_1 + 59 * DAY + 3600;
+
+        assert_eq!(fetch_from(&cached, Some(JAN_1 + 10 * DAY), JAN_1, JAN_1 + 30 * DAY, now), None);
+        // Only the tail once new days are needed
+        let now = JAN_1 + 65 * DAY;
+        assert_eq!(fetch_from(&cached, Some(JAN_1 + 10 * DAY), JAN_1, now, now), Some(JAN_1 + 59 * DAY));
+    }
+
+    #[test]
+    fn fetches_from_the_first_candle_without_a_cache() {
+        assert_eq!(fetch_from(&[], Some(JAN_1 + 10 * DAY), JAN_1, JAN_1 + 30 * DAY, JAN_1 + 60 * DAY), Some(JAN_1 + 10 * DAY));
+    }
+}
@@ -0,0 +1,528 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use bitcoin::block::Header;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::DisplayHex;
+use bitcoin::{Address, Script, ScriptBuf, Transaction};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::bitcoin_rpc_client::btc_per_kvb_to_sat_per_vb;
+use crate::mempool_client::{MempoolBlockInfo, MempoolFeeEstimate, MempoolStats};
+use crate::proxy::ProxySource;
+use crate::reconnect::Reconnect;
+use crate::transport::{self, Stream, TlsOptions};
+
+// Default public Electrum server
+pub const DEFAULT_ELECTRUM_SERVER: &str = "ssl://electrum.blockstream.info:50002";
+
+// Protocol version we speak (server.version)
+const PROTOCOL_VERSION: &str = "1.4";
+
+// Requests fail if the server doesn't answer within this time
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long a subscription waits for a notification before checking whether to keep going
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Ping the server when a subscription has been quiet for this long
+const PING_INTERVAL: Duration = Duration::from_secs(60);
+
+// Confirmation targets (in blocks) used for the four fee levels
+const FASTEST_TARGET: u16 = 1;
+const HALF_HOUR_TARGET: u16 = 3;
+const HOUR_TARGET: u16 = 6;
+const ECONOMY_TARGET: u16 = 144;
+
+// Header notification from blockchain.headers.subscribe
+#[derive(Debug, Deserialize)]
+struct HeaderNotification {
+    height: u32,
+    hex: String,
+}
+
+// Result of blockchain.block.headers
+#[derive(Debug, Deserialize)]
+struct HeadersChunk {
+    count: u32,
+    hex: String,
+}
+
+// Result of blockchain.scripthash.get_balance (satoshis)
+#[derive(Debug, Deserialize)]
+pub struct ScripthashBalance {
+    pub confirmed: i64,
+    pub unconfirmed: i64,
+}
+
+// Entry of blockchain.scripthash.get_history
+#[derive(Debug, Deserialize)]
+pub struct ScripthashTx {
+    pub tx_hash: String,
+    // Block height, or 0 (-1 with unconfirmed inputs) while in the mempool
+    pub height: i64,
+    // Satoshis, only reported for unconfirmed transactions
+    #[serde(default)]
+    pub fee: Option<u64>,
+}
+
+// Where a transaction stands according to an Electrum server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectrumTxStatus {
+    pub confirmed_height: Option<u32>,
+    // Satoshis, only known while the transaction is unconfirmed
+    pub fee: Option<u64>,
+    pub vsize: u32,
+}
+
+#[derive(Debug)]
+pub enum HeaderEvent {
+    // Subscribed; carries the current tip height
+    Connected(u32),
+    NewTip(u32),
+    // No notification arrived within the read timeout
+    Idle,
+    Disconnected(String),
+}
+
+// A line-delimited JSON-RPC connection to an Electrum server
+struct ElectrumConnection {
+    stream: BufReader<Box<dyn Stream>>,
+    next_id: u64,
+    // Notifications received while waiting for a response
+    notifications: VecDeque<(String, Value)>,
+    // Start of a line cut off by a read timeout
+    partial: Vec<u8>,
+}
+
+impl ElectrumConnection {
+    fn connect(server: &str, accept_invalid_certs: bool, read_timeout: Duration) -> Result<Self> {
+        let (host, port, secure) = parse_server(server)?;
+        println!("Connecting to Electrum server at: {}", server);
+
+        let tls = TlsOptions { accept_invalid_certs, ..Default::default() };
+        let stream = transport::connect(ProxySource::Electrum, &host, port, secure, &tls, read_timeout)?;
+        let mut connection = ElectrumConnection {
+            stream: BufReader::new(stream),
+            next_id: 0,
+            notifications: VecDeque::new(),
+            partial: Vec::new(),
+        };
+
+        // Servers expect the version negotiation before anything else
+        connection.request("server.version", json!([format!("btc-ticker {}", env!("CARGO_PKG_VERSION")), PROTOCOL_VERSION]))?;
+        Ok(connection)
+    }
+
+    // Send a request and wait for its response
+    fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        let mut line = request.to_string();
+        line.push('\n');
+        self.stream.get_mut().write_all(line.as_bytes())
+            .map_err(|e| anyhow!("Failed to send {}: {}", method, e))?;
+
+        let started = Instant::now();
+        loop {
+            let message = match self.read_message() {
+                Ok(message) => message,
+                Err(e) if transport::is_timeout(&e) && started.elapsed() < REQUEST_TIMEOUT => continue,
+                Err(e) => return Err(anyhow!("Failed to read {} response: {}", method, e)),
+            };
+
+            // Notifications have a method and no id
+            if let Some(notification) = message.get("method").and_then(Value::as_str) {
+                let params = message.get("params").cloned().unwrap_or(Value::Null);
+                self.notifications.push_back((notification.to_string(), params));
+                continue;
+            }
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+
+            if let Some(error) = message.get("error").filter(|error| !error.is_null()) {
+                let text = error.get("message").and_then(Value::as_str).map(str::to_string)
+                    .unwrap_or_else(|| error.to_string());
+                return Err(anyhow!("{} failed: {}", method, text));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    // Next queued or incoming notification; times out like the underlying socket
+    fn next_notification(&mut self) -> Result<(String, Value)> {
+        if let Some(notification) = self.notifications.pop_front() {
+            return Ok(notification);
+        }
+        loop {
+            let message = self.read_message()?;
+            if let Some(method) = message.get("method").and_then(Value::as_str) {
+                let params = message.get("params").cloned().unwrap_or(Value::Null);
+                return Ok((method.to_string(), params));
+            }
+        }
+    }
+
+    // Read the next newline-terminated message, keeping partial lines across timeouts
+    fn read_message(&mut self) -> Result<Value> {
+        self.stream.read_until(b'\n', &mut self.partial)?;
+        if !self.partial.ends_with(b"\n") {
+            return Err(anyhow!("Server closed the connection"));
+        }
+
+        let line = std::mem::take(&mut self.partial);
+        serde_json::from_slice(&line)
+            .map_err(|e| anyhow!("Failed to parse Electrum message: {}", e))
+    }
+}
+
+// Electrum protocol client (electrs, Fulcrum, ElectrumX) for network and address data
+pub struct ElectrumClient {
+    server: String,
+    accept_invalid_certs: bool,
+    // Opened on first use and reused for later requests
+    connection: Mutex<Option<ElectrumConnection>>,
+}
+
+impl ElectrumClient {
+    /// Create a client for a server given as tcp://host:port or ssl://host:port
+    pub fn with_url(server: &str, accept_invalid_certs: bool) -> Self {
+        ElectrumClient {
+            server: server.trim().to_string(),
+            accept_invalid_certs,
+            connection: Mutex::new(None),
+        }
+    }
+
+    fn request<T: for<'de> Deserialize<'de>>(&self, method: &str, params: Value) -> Result<T> {
+        println!("Calling {} on: {}", method, self.server);
+
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(ElectrumConnection::connect(&self.server, self.accept_invalid_certs, READ_TIMEOUT)?);
+        }
+
+        let result = connection.as_mut().unwrap().request(method, params);
+        let value = match result {
+            Ok(value) => value,
+            Err(e) => {
+                // Start over with a fresh connection next time
+                *connection = None;
+                return Err(e);
+            }
+        };
+        serde_json::from_value(value)
+            .map_err(|e| anyhow!("Failed to parse {} result: {}", method, e))
+    }
+
+    pub fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        let tip: HeaderNotification = self.request("blockchain.headers.subscribe", json!([]))?;
+        header_to_block(&tip.hex, tip.height)
+    }
+
+    /// Fetch up to 10 block headers ending at `start_height`, newest first.
+    /// Headers carry no transaction count or size, so those are left at zero.
+    pub fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        let first_height = start_height.saturating_sub(9);
+        let chunk: HeadersChunk = self.request(
+            "blockchain.block.headers",
+            json!([first_height, start_height - first_height + 1]),
+        )?;
+
+        let mut blocks = Vec::with_capacity(chunk.count as usize);
+        // Each header is 80 bytes (160 hex characters)
+        for (index, header) in chunk.hex.as_bytes().chunks(160).enumerate().take(chunk.count as usize) {
+            let header = std::str::from_utf8(header)
+                .map_err(|e| anyhow!("Invalid header hex: {}", e))?;
+            blocks.push(header_to_block(header, first_height + index as u32)?);
+        }
+        blocks.reverse();
+        Ok(blocks)
+    }
+
+    /// Fee levels from blockchain.estimatefee, in sat/vB. Targets the server
+    /// can't estimate fall back to its relay fee.
+    pub fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
+        let relay_fee: f64 = self.request("blockchain.relayfee", json!([]))?;
+        let minimum_fee = btc_per_kvb_to_sat_per_vb(relay_fee);
+
+        let estimate = |target: u16| -> Result<u32> {
+            // -1 when the server has no estimate
+            let rate: f64 = self.request("blockchain.estimatefee", json!([target]))?;
+            if rate > 0.0 {
+                Ok(btc_per_kvb_to_sat_per_vb(rate).max(minimum_fee))
+            } else {
+                Ok(minimum_fee)
+            }
+        };
+
+        let economy_fee = estimate(ECONOMY_TARGET)?;
+        let hour_fee = estimate(HOUR_TARGET)?.max(economy_fee);
+        let half_hour_fee = estimate(HALF_HOUR_TARGET)?.max(hour_fee);
+        let fastest_fee = estimate(FASTEST_TARGET)?.max(half_hour_fee);
+
+        Ok(MempoolFeeEstimate {
+            fastest_fee,
+            half_hour_fee,
+            hour_fee,
+            economy_fee,
+        })
+    }
+
+    /// Mempool size from the server's fee histogram. The transaction count is
+    /// unknown and the total fee is estimated from the histogram's fee rates.
+    pub fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        let fee_histogram: Vec<(f64, f64)> = self.request("mempool.get_fee_histogram", json!([]))?;
+        let vsize: f64 = fee_histogram.iter().map(|&(_, vsize)| vsize).sum();
+        let total_fee: f64 = fee_histogram.iter().map(|&(fee_rate, vsize)| fee_rate * vsize).sum();
+
+        Ok(MempoolStats {
+            count: 0,
+            vsize: vsize as u64,
+            total_fee: total_fee as u64,
+            fee_histogram,
+        })
+    }
+
+    /// Confirmed and unconfirmed balance of an address, in satoshis
+    pub fn fetch_address_balance(&self, address: &str) -> Result<ScripthashBalance> {
+        self.request("blockchain.scripthash.get_balance", json!([script_scripthash(&address_script(address)?)]))
+    }
+
+    /// Transactions touching an address, oldest first with unconfirmed ones last
+    pub fn fetch_address_history(&self, address: &str) -> Result<Vec<ScripthashTx>> {
+        self.fetch_script_history(&address_script(address)?)
+    }
+
+    /// Transactions paying to or spending from an output script
+    pub fn fetch_script_history(&self, script: &Script) -> Result<Vec<ScripthashTx>> {
+        self.request("blockchain.scripthash.get_history", json!([script_scripthash(script)]))
+    }
+
+    pub fn fetch_transaction(&self, txid: &str) -> Result<Transaction> {
+        let hex: String = self.request("blockchain.transaction.get", json!([txid]))?;
+        deserialize_hex(&hex)
+            .map_err(|e| anyhow!("Failed to decode transaction {}: {}", txid, e))
+    }
+
+    /// Timestamp of the block at `height`
+    pub fn fetch_block_time(&self, height: u32) -> Result<u32> {
+        let hex: String = self.request("blockchain.block.header", json!([height]))?;
+        Ok(header_to_block(&hex, height)?.timestamp)
+    }
+
+    /// Whether a transaction has confirmed, and at which height. Electrum
+    /// servers can't look that up by txid, so it is found in the history of
+    /// one of the transaction's own output scripts.
+    pub fn fetch_tx_status(&self, txid: &str) -> Result<ElectrumTxStatus> {
+        let tx = self.fetch_transaction(txid)?;
+        let vsize = tx.vsize() as u32;
+
+        // OP_RETURN outputs can't be spent, so servers don't index them
+        for output in tx.output.iter().filter(|output| !output.script_pubkey.is_op_return()) {
+            let history = self.fetch_script_history(&output.script_pubkey)?;
+            if let Some(entry) = history.into_iter().find(|entry| entry.tx_hash == txid) {
+                return Ok(ElectrumTxStatus {
+                    confirmed_height: u32::try_from(entry.height).ok().filter(|&height| height > 0),
+                    fee: entry.fee,
+                    vsize,
+                });
+            }
+        }
+        Err(anyhow!("Transaction {} is not in the history of its outputs", txid))
+    }
+
+    /// Subscribe to new block headers, reconnecting with exponential backoff.
+    /// Every event is passed to `handler`; the subscription ends once it returns false.
+    pub fn subscribe_headers<F: FnMut(HeaderEvent) -> bool>(&self, handler: F) {
+        Reconnect::new("Electrum header subscription").run(
+            |handler| self.subscribe_once(handler),
+            |event| matches!(event, HeaderEvent::Connected(_)),
+            HeaderEvent::Disconnected,
+            handler,
+        );
+    }
+
+    // Subscribe on a dedicated connection and forward new tips until an error
+    // occurs or the handler stops us
+    fn subscribe_once(&self, handler: &mut dyn FnMut(HeaderEvent) -> bool) -> Result<()> {
+        let mut connection = ElectrumConnection::connect(&self.server, self.accept_invalid_certs, READ_TIMEOUT)?;
+        let tip: HeaderNotification = serde_json::from_value(connection.request("blockchain.headers.subscribe", json!([]))?)
+            .map_err(|e| anyhow!("Failed to parse header subscription: {}", e))?;
+        println!("Subscribed to Electrum headers at height {}", tip.height);
+
+        if !handler(HeaderEvent::Connected(tip.height)) {
+            return Ok(());
+        }
+
+        let mut last_message = Instant::now();
+        loop {
+            let event = match connection.next_notification() {
+                Ok((method, params)) if method == "blockchain.headers.subscribe" => {
+                    last_message = Instant::now();
+                    let headers: Vec<HeaderNotification> = serde_json::from_value(params)
+                        .map_err(|e| anyhow!("Failed to parse header notification: {}", e))?;
+                    match headers.into_iter().map(|header| header.height).max() {
+                        Some(height) => HeaderEvent::NewTip(height),
+                        None => continue,
+                    }
+                },
+                Ok(_) => continue,
+                Err(e) if transport::is_timeout(&e) => {
+                    // Keep the connection alive through idle periods
+                    if last_message.elapsed() > PING_INTERVAL {
+                        connection.request("server.ping", json!([]))?;
+                        last_message = Instant::now();
+                    }
+                    HeaderEvent::Idle
+                },
+                Err(e) => return Err(e),
+            };
+
+            if !handler(event) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Split tcp://host:port or ssl://host:port into host, port and whether to use TLS
+fn parse_server(server: &str) -> Result<(String, u16, bool)> {
+    let parsed = Url::parse(server)
+        .map_err(|e| anyhow!("Invalid Electrum server '{}' (expected tcp://host:port or ssl://host:port): {}", server, e))?;
+
+    let (secure, default_port) = match parsed.scheme() {
+        "tcp" => (false, 50001),
+        "ssl" | "tls" => (true, 50002),
+        scheme => return Err(anyhow!("Unsupported Electrum scheme: {} (use tcp or ssl)", scheme)),
+    };
+    let host = parsed.host_str()
+        .ok_or_else(|| anyhow!("Electrum server has no host: {}", server))?
+        .to_string();
+
+    Ok((host, parsed.port().unwrap_or(default_port), secure))
+}
+
+/// Output script paying to `address`
+pub fn address_script(address: &str) -> Result<ScriptBuf> {
+    let address = Address::from_str(address)
+        .map_err(|e| anyhow!("Invalid address {}: {}", address, e))?
+        .assume_checked();
+    Ok(address.script_pubkey())
+}
+
+// Electrum identifies addresses by the reversed SHA256 of their output script
+fn script_scripthash(script: &Script) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    hash.to_lower_hex_string()
+}
+
+// Decode a raw 80-byte header into block info
+fn header_to_block(hex: &str, height: u32) -> Result<MempoolBlockInfo> {
+    let header: Header = deserialize_hex(hex)
+        .map_err(|e| anyhow!("Failed to decode block header at {}: {}", height, e))?;
+
+    Ok(MempoolBlockInfo {
+        id: header.block_hash().to_string(),
+        height,
+        version: header.version.to_consensus() as u32,
+        timestamp: header.time,
+        bits: header.bits.to_consensus(),
+        nonce: header.nonce,
+        difficulty: header.difficulty_float(),
+        merkle_root: header.merkle_root.to_string(),
+        tx_count: 0,
+        size: 0,
+        weight: 0,
+        previousblockhash: Some(header.prev_blockhash.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::OutPoint;
+    use crate::test_support;
+
+    const ADDRESS: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+
+    #[test]
+    fn scripthash_matches_the_protocol_docs() {
+        let script = address_script("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(script_scripthash(&script), "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161");
+    }
+
+    // Server knowing one transaction, listed at `height` with `fee` in the
+    // history of ADDRESS
+    fn serve_tx(tx: &Transaction, height: i64, fee: Option<u64>) -> String {
+        let hex = serialize_hex(tx);
+        let txid = tx.compute_txid().to_string();
+        let scripthash = script_scripthash(&address_script(ADDRESS).unwrap());
+        test_support::serve_electrum(move |method, params| match method {
+            "blockchain.transaction.get" if params[0] == txid.as_str() => json!(hex),
+            "blockchain.scripthash.get_history" if params[0] == scripthash.as_str() => {
+                json!([{ "tx_hash": txid, "height": height, "fee": fee }])
+            },
+            "blockchain.scripthash.get_history" => json!([]),
+            other => panic!("unexpected call {} {}", other, params),
+        })
+    }
+
+    fn payment() -> Transaction {
+        let funding = OutPoint { txid: test_support::transaction(&[OutPoint::null()], &[]).compute_txid(), vout: 0 };
+        // The OP_RETURN output comes first and has to be skipped
+        let op_return = ScriptBuf::new_op_return([0u8; 4]);
+        test_support::transaction(&[funding], &[(op_return, 0), (address_script(ADDRESS).unwrap(), 50_000)])
+    }
+
+    #[test]
+    fn tx_status_of_a_confirmed_transaction() {
+        let tx = payment();
+        let client = ElectrumClient::with_url(&serve_tx(&tx, 840_000, None), false);
+
+        let status = client.fetch_tx_status(&tx.compute_txid().to_string()).unwrap();
+        assert_eq!(status.confirmed_height, Some(840_000));
+        assert_eq!(status.fee, None);
+        assert_eq!(status.vsize, tx.vsize() as u32);
+    }
+
+    #[test]
+    fn tx_status_of_a_mempool_transaction() {
+        let tx = payment();
+        // -1 means an unconfirmed parent
+        let client = ElectrumClient::with_url(&serve_tx(&tx, -1, Some(1410)), false);
+
+        let status = client.fetch_tx_status(&tx.compute_txid().to_string()).unwrap();
+        assert_eq!(status.confirmed_height, None);
+        assert_eq!(status.fee, Some(1410));
+    }
+
+    #[test]
+    fn tx_missing_from_its_output_histories() {
+        let tx = payment();
+        let hex = serialize_hex(&tx);
+        let server = test_support::serve_electrum(move |method, _| match method {
+            "blockchain.transaction.get" => json!(hex),
+            _ => json!([]),
+        });
+
+        let error = ElectrumClient::with_url(&server, false)
+            .fetch_tx_status(&tx.compute_txid().to_string()).unwrap_err().to_string();
+        assert!(error.contains("not in the history"), "{}", error);
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Result, anyhow};
+use bitcoin::bip32::{ChildNumber, Xpub};
+use bitcoin::secp256k1::{Secp256k1, VerifyOnly};
+use bitcoin::{base58, Address, CompressedPublicKey, KnownHrp, NetworkKind};
+
+// Version bytes of the extended public key formats we accept
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xB2, 0x1E];
+const YPUB_VERSION: [u8; 4] = [0x04, 0x9D, 0x7C, 0xB2];
+const ZPUB_VERSION: [u8; 4] = [0x04, 0xB2, 0x47, 0x46];
+const TPUB_VERSION: [u8; 4] = [0x04, 0x35, 0x87, 0xCF];
+const UPUB_VERSION: [u8; 4] = [0x04, 0x4A, 0x52, 0x62];
+const VPUB_VERSION: [u8; 4] = [0x04, 0x5F, 0x1C, 0xF6];
+
+// Address type implied by the extended key's prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    // xpub/tpub: BIP44 pay-to-pubkey-hash (1...)
+    Legacy,
+    // ypub/upub: BIP49 P2WPKH nested in P2SH (3...)
+    NestedSegwit,
+    // zpub/vpub: BIP84 native P2WPKH (bc1q...)
+    NativeSegwit,
+}
+
+impl ScriptKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptKind::Legacy => "Legacy (P2PKH)",
+            ScriptKind::NestedSegwit => "Nested SegWit (P2SH-P2WPKH)",
+            ScriptKind::NativeSegwit => "Native SegWit (P2WPKH)",
+        }
+    }
+}
+
+// External (receive) or internal (change) address chain of an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain {
+    Receive,
+    Change,
+}
+
+impl Chain {
+    fn index(&self) -> u32 {
+        match self {
+            Chain::Receive => 0,
+            Chain::Change => 1,
+        }
+    }
+}
+
+/// An account-level extended public key (e.g. m/84'/0'/0') and the address
+/// type it derives. Derivation is purely local and never touches the network.
+pub struct AccountKey {
+    xpub: Xpub,
+    kind: ScriptKind,
+    secp: Secp256k1<VerifyOnly>,
+}
+
+impl AccountKey {
+    /// Parse an xpub, ypub or zpub (or their testnet tpub/upub/vpub counterparts)
+    pub fn parse(encoded: &str) -> Result<Self> {
+        let mut data = base58::decode_check(encoded.trim())
+            .map_err(|e| anyhow!("Invalid extended public key: {}", e))?;
+        if data.len() != 78 {
+            return Err(anyhow!("Invalid extended public key length: {}", data.len()));
+        }
+
+        let version: [u8; 4] = [data[0], data[1], data[2], data[3]];
+        let (kind, xpub_version) = match version {
+            XPUB_VERSION => (ScriptKind::Legacy, XPUB_VERSION),
+            YPUB_VERSION => (ScriptKind::NestedSegwit, XPUB_VERSION),
+            ZPUB_VERSION => (ScriptKind::NativeSegwit, XPUB_VERSION),
+            TPUB_VERSION => (ScriptKind::Legacy, TPUB_VERSION),
+            UPUB_VERSION => (ScriptKind::NestedSegwit, TPUB_VERSION),
+            VPUB_VERSION => (ScriptKind::NativeSegwit, TPUB_VERSION),
+            _ => return Err(anyhow!("Unsupported extended key version: {:02x?}", version)),
+        };
+
+        // ypub/zpub only differ from xpub in their version bytes
+        data[..4].copy_from_slice(&xpub_version);
+        let xpub = Xpub::decode(&data)
+            .map_err(|e| anyhow!("Invalid extended public key: {}", e))?;
+
+        Ok(AccountKey {
+            xpub,
+            kind,
+            secp: Secp256k1::verification_only(),
+        })
+    }
+
+    pub fn kind(&self) -> ScriptKind {
+        self.kind
+    }
+
+    /// Address at `index` on the receive or change chain
+    pub fn address(&self, chain: Chain, index: u32) -> Result<String> {
+        let path = [
+            ChildNumber::from_normal_idx(chain.index()).map_err(|e| anyhow!("Invalid chain: {}", e))?,
+            ChildNumber::from_normal_idx(index).map_err(|e| anyhow!("Invalid address index: {}", e))?,
+        ];
+        let child = self.xpub.derive_pub(&self.secp, &path)
+            .map_err(|e| anyhow!("Failed to derive address {}: {}", index, e))?;
+        let public_key = CompressedPublicKey(child.public_key);
+
+        let address = match self.kind {
+            ScriptKind::Legacy => Address::p2pkh(public_key, self.xpub.network),
+            ScriptKind::NestedSegwit => Address::p2shwpkh(&public_key, self.xpub.network),
+            ScriptKind::NativeSegwit => {
+                let hrp = match self.xpub.network {
+                    NetworkKind::Main => KnownHrp::Mainnet,
+                    NetworkKind::Test => KnownHrp::Testnets,
+                };
+                Address::p2wpkh(&public_key, hrp)
+            },
+        };
+        Ok(address.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP32 test vector 1, chains m/0' and m/0'/1
+    const TV1_M_0H: &str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+    const TV1_M_0H_1: &str = "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ";
+
+    // Account keys of the "abandon ... about" mnemonic from BIP49 and BIP84
+    const BIP49_YPUB: &str = "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP";
+    const BIP84_ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    // Re-encode a key with other version bytes
+    fn with_version(encoded: &str, version: [u8; 4]) -> String {
+        let mut data = base58::decode_check(encoded).unwrap();
+        data[..4].copy_from_slice(&version);
+        base58::encode_check(&data)
+    }
+
+    #[test]
+    fn parses_bip32_test_vector() {
+        let key = AccountKey::parse(TV1_M_0H_1).unwrap();
+        assert_eq!(key.kind(), ScriptKind::Legacy);
+        assert_eq!(key.xpub.to_string(), TV1_M_0H_1);
+
+        // Public derivation from m/0' reproduces the vector's m/0'/1
+        let parent = AccountKey::parse(TV1_M_0H).unwrap();
+        let child = parent.xpub.derive_pub(&parent.secp, &[ChildNumber::from_normal_idx(1).unwrap()]).unwrap();
+        assert_eq!(child.to_string(), TV1_M_0H_1);
+    }
+
+    #[test]
+    fn derives_bip84_addresses() {
+        let key = AccountKey::parse(BIP84_ZPUB).unwrap();
+        assert_eq!(key.kind(), ScriptKind::NativeSegwit);
+        assert_eq!(key.address(Chain::Receive, 0).unwrap(), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(key.address(Chain::Receive, 1).unwrap(), "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+        assert_eq!(key.address(Chain::Change, 0).unwrap(), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+    }
+
+    #[test]
+    fn derives_bip49_addresses() {
+        let key = AccountKey::parse(BIP49_YPUB).unwrap();
+        assert_eq!(key.kind(), ScriptKind::NestedSegwit);
+        assert_eq!(key.address(Chain::Receive, 0).unwrap(), "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+    }
+
+    #[test]
+    fn testnet_keys_derive_testnet_addresses() {
+        let key = AccountKey::parse(&with_version(BIP84_ZPUB, VPUB_VERSION)).unwrap();
+        assert_eq!(key.kind(), ScriptKind::NativeSegwit);
+        assert!(key.address(Chain::Receive, 0).unwrap().starts_with("tb1q"));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        // xprv version bytes: a private key must never be accepted as a watch-only key
+        let error = AccountKey::parse(&with_version(BIP84_ZPUB, [0x04, 0x88, 0xAD, 0xE4])).err().unwrap();
+        assert!(error.to_string().contains("Unsupported extended key version"), "{}", error);
+
+        // Litecoin's Ltub
+        assert!(AccountKey::parse(&with_version(BIP84_ZPUB, [0x01, 0x9D, 0xA4, 0x62])).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut corrupted = BIP84_ZPUB.to_string();
+        corrupted.pop();
+        corrupted.push('t');
+        let error = AccountKey::parse(&corrupted).err().unwrap();
+        assert!(error.to_string().contains("checksum"), "{}", error);
+        assert!(AccountKey::parse("zpub").is_err());
+    }
+}
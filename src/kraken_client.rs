@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::price_provider::{ChartTimeframe, PriceCandle, PriceProvider, QuoteCurrency, parse_price_field};
+use crate::proxy::{HttpClient, ProxySource};
+
+// Default Kraken API URL
+pub const DEFAULT_KRAKEN_API_URL: &str = "https://api.kraken.com/0/public";
+
+// Kraken wraps every response in an error list plus an optional result
+#[derive(Debug, Deserialize)]
+pub struct KrakenResponse<T> {
+    pub error: Vec<String>,
+    pub result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KrakenTicker {
+    // Last trade closed: [price, lot volume]
+    pub c: Vec<String>,
+}
+
+// Kraken API client for handling all API interactions
+pub struct KrakenClient {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl KrakenClient {
+    /// Create a new KrakenClient with the default Kraken API URL
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_KRAKEN_API_URL)
+    }
+
+    /// Create a new KrakenClient with a custom API URL (e.g. a local mock server)
+    pub fn with_url(base_url: &str) -> Self {
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)); // 10 second timeout
+        let client = HttpClient::build(ProxySource::Exchanges, base_url, builder);
+
+        KrakenClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // Send a GET request and unwrap Kraken's error/result envelope
+    fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self.client.get(url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch from Kraken: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+
+        let body: KrakenResponse<T> = response.json()
+            .map_err(|e| anyhow!("Failed to parse Kraken response: {}", e))?;
+
+        if !body.error.is_empty() {
+            return Err(anyhow!("Kraken API error: {}", body.error.join(", ")));
+        }
+
+        body.result.ok_or_else(|| anyhow!("Kraken response did not contain a result"))
+    }
+}
+
+impl PriceProvider for KrakenClient {
+    fn name(&self) -> &'static str {
+        "Kraken"
+    }
+
+    fn fetch_current_price(&self, currency: QuoteCurrency) -> Result<f64> {
+        let url = format!("{}/Ticker?pair=XBT{}", self.base_url, currency.code());
+
+        println!("Fetching current BTC price from: {}", url);
+
+        let tickers: HashMap<String, KrakenTicker> = self.get(&url)?;
+
+        // The result is keyed by Kraken's internal pair name (e.g. XXBTZEUR)
+        let ticker = tickers.values().next()
+            .ok_or_else(|| anyhow!("Kraken ticker response was empty"))?;
+        let last = ticker.c.first()
+            .ok_or_else(|| anyhow!("Kraken ticker response had no last trade"))?;
+
+        parse_price_field(last, "price")
+    }
+
+    fn fetch_historical_prices(&self, timeframe: ChartTimeframe, currency: QuoteCurrency) -> Result<Vec<PriceCandle>> {
+        let (step, limit) = timeframe.api_params();
+
+        // Kraken takes the interval in minutes and always returns up to 720 candles
+        let url = format!("{}/OHLC?pair=XBT{}&interval={}", self.base_url, currency.code(), step / 60);
+        println!("Fetching historical data from: {} ({})", url, timeframe.description());
+
+        let result: HashMap<String, serde_json::Value> = self.get(&url)?;
+
+        // Besides the pair the result contains a "last" cursor, so pick the array entry
+        let rows = result.iter()
+            .find(|(key, _)| key.as_str() != "last")
+            .and_then(|(_, value)| value.as_array())
+            .ok_or_else(|| anyhow!("Kraken OHLC response did not contain any candles"))?;
+
+        // Each row is [time, open, high, low, close, vwap, volume, count]
+        let mut candles = Vec::with_capacity(rows.len());
+        for row in rows {
+            let fields = row.as_array()
+                .filter(|fields| fields.len() >= 5)
+                .ok_or_else(|| anyhow!("Unexpected Kraken OHLC row: {}", row))?;
+
+            let timestamp = fields[0].as_i64()
+                .ok_or_else(|| anyhow!("Unexpected Kraken candle timestamp: {}", fields[0]))?;
+            let field = |index: usize, name: &str| -> Result<f64> {
+                let value = fields[index].as_str()
+                    .ok_or_else(|| anyhow!("Unexpected Kraken {} value: {}", name, fields[index]))?;
+                parse_price_field(value, name)
+            };
+
+            candles.push(PriceCandle {
+                timestamp,
+                open: field(1, "open")?,
+                high: field(2, "high")?,
+                low: field(3, "low")?,
+                close: field(4, "close")?,
+            });
+        }
+
+        // Only keep the most recent candles for the requested timeframe
+        let skip = candles.len().saturating_sub(limit as usize);
+        let candles: Vec<PriceCandle> = candles.into_iter().skip(skip).collect();
+
+        println!("Successfully parsed historical data for {} ({} candles)",
+                timeframe.description(), candles.len());
+
+        Ok(candles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn parses_ticker() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/Ticker?pair=XBTEUR");
+            (200, r#"{"error": [], "result": {"XXBTZEUR": {"a": ["60001.0", "1", "1.000"], "c": ["60000.5", "0.01"]}}}"#.to_string())
+        });
+
+        let price = KrakenClient::with_url(&url).fetch_current_price(QuoteCurrency::Eur).unwrap();
+        assert_eq!(price, 60000.5);
+    }
+
+    #[test]
+    fn skips_last_cursor_in_ohlc() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.path, "/OHLC?pair=XBTUSD&interval=60");
+            // "last" sits next to the pair and is a number, not a list of candles
+            let body = r#"{"error": [], "result": {
+                "last": 1700003600,
+                "XXBTZUSD": [
+                    [1700000000, "100.0", "110.0", "90.0", "105.0", "101.0", "3.5", 42],
+                    [1700003600, "105.0", "106.0", "104.0", "104.5", "105.1", "1.0", 7]
+                ]
+            }}"#;
+            (200, body.to_string())
+        });
+
+        let candles = KrakenClient::with_url(&url)
+            .fetch_historical_prices(ChartTimeframe::Hours24, QuoteCurrency::Usd)
+            .unwrap();
+        assert_eq!(candles, vec![
+            PriceCandle { timestamp: 1_700_000_000, open: 100.0, high: 110.0, low: 90.0, close: 105.0 },
+            PriceCandle { timestamp: 1_700_003_600, open: 105.0, high: 106.0, low: 104.0, close: 104.5 },
+        ]);
+    }
+
+    #[test]
+    fn keeps_only_the_timeframe() {
+        // Kraken always sends up to 720 candles; a day of hourly candles keeps the last 24
+        let rows: Vec<String> = (0..30)
+            .map(|i| format!(r#"[{}, "1", "1", "1", "{}", "1", "1", 1]"#, 1_700_000_000 + i * 3600, i))
+            .collect();
+        let url = test_support::serve_json(&format!(r#"{{"error": [], "result": {{"XXBTZUSD": [{}], "last": 0}}}}"#, rows.join(",")));
+
+        let candles = KrakenClient::with_url(&url)
+            .fetch_historical_prices(ChartTimeframe::Hours24, QuoteCurrency::Usd)
+            .unwrap();
+        assert_eq!(candles.len(), 24);
+        assert_eq!(candles[0].close, 6.0);
+        assert_eq!(candles[23].close, 29.0);
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let url = test_support::serve_json(r#"{"error": ["EQuery:Unknown asset pair"]}"#);
+
+        let error = KrakenClient::with_url(&url).fetch_current_price(QuoteCurrency::Jpy).unwrap_err();
+        assert!(error.to_string().contains("EQuery:Unknown asset pair"), "{}", error);
+    }
+}
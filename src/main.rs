@@ -4,7 +4,7 @@ use std::time::Duration;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use anyhow::Result;
-use egui_plot::{Plot, BoxPlot, BoxElem, BoxSpread, Corner, Legend};
+use egui_plot::{Plot, Bar, BarChart, BoxPlot, BoxElem, BoxSpread, Corner, GridMark, Legend, Line};
 use chrono::{DateTime, Utc, TimeZone, Timelike, Local};
 
 use tray_icon::{
@@ -14,20 +14,74 @@ use tray_icon::{
 
 use eframe::egui;
 
+mod price_provider;
+mod price_aggregator;
 mod bitstamp_client;
+mod kraken_client;
+mod coinbase_client;
+mod binance_client;
 mod mempool_client;
+mod ws_client;
+mod price_stream;
+mod mempool_stream;
+mod candle_store;
+mod connectivity;
+mod block_tracker;
+mod chain_schedule;
+mod alerts;
+mod address_watch;
+mod tx_tracker;
+mod hd_wallet;
+mod watch_wallet;
+mod portfolio;
+mod dca;
+mod bitcoin_rpc_client;
+mod network_provider;
+mod transport;
+mod proxy;
+mod secret_store;
+mod mempool_auth;
+mod mempool_endpoints;
+mod electrum_client;
+mod reconnect;
+mod notifications;
 mod config;
+#[cfg(test)]
+mod test_support;
 
-use bitstamp_client::{BitstampClient, ChartTimeframe};
-use mempool_client::{MempoolClient};
+use price_provider::{ChartTimeframe, PriceCandle, PriceSource, QuoteCurrency};
+use price_aggregator::AggregatedPrice;
+use price_stream::{BitstampPriceStream, StreamEvent};
+use mempool_stream::{MempoolEvent, MempoolStream};
+use candle_store::CandleStore;
+use connectivity::{SourceHealth, SourceStatus};
+use block_tracker::BlockTracker;
+use address_watch::{AddressBalance, AddressWatcher, WatchedAddress};
+use tx_tracker::{TrackedTx, TrackedTxStore, TxProgress};
+use watch_wallet::{AddressIndex, WalletSummary, WatchedWallet};
+use portfolio::{CostBasisMethod, PortfolioStore, Trade, TradeSide};
+use dca::{DcaFrequency, DcaPlan, DcaResult};
+use bitcoin_rpc_client::{BitcoinRpcClient, RpcAuthMethod};
+use network_provider::{NetworkBackend, NetworkProvider};
+use electrum_client::{ElectrumClient, HeaderEvent};
+use proxy::{ProxyRoute, ProxySource};
+use mempool_auth::{MempoolAuthMethod, MempoolEndpointAccess};
+use mempool_endpoints::{EndpointPool, EndpointStatus, MempoolEndpoint, HEALTH_CHECK_INTERVAL};
+use alerts::{AlertEvent, FeeAlert, FeeAlertEvaluator, FeeTarget, PriceAlert, PriceAlertEvaluator, PriceAlertKind};
+use mempool_client::{
+    HashrateTimeframe, MempoolBlockInfo, MempoolClient, MempoolDifficultyAdjustment,
+    MempoolHashrateHistory, MempoolProjectedBlock, MempoolStats, MempoolTxStatus,
+};
 use config::{AppConfig, DEFAULT_MEMPOOL_API_URL};
 
-// For debugging
-fn print_historical_data(data: &bitstamp_client::BitstampHistoricalData) {
-    for (i, point) in data.data.ohlc.iter().enumerate().take(5) {
-        println!("Point {}: timestamp={}, close={}", i, point.timestamp, point.close);
-    }
-}
+// Number of fired alerts kept in the alert log
+const MAX_ALERT_LOG: usize = 100;
+
+// Number of projected mempool blocks shown in the network panel
+const PROJECTED_BLOCK_TILES: usize = 6;
+
+// Hashrate history is daily, so refetching it more often than hourly is pointless
+const HASHRATE_REFRESH_SECS: i64 = 3600;
 
 // Shared state between the tray icon and the egui app
 struct BitcoinState {
@@ -39,20 +93,78 @@ struct BitcoinState {
     historical_data: Vec<(TimeInfo, CandleData)>,
     chart_timeframe: ChartTimeframe,
     timeframe_changed: bool,
+    // Chart shown in the main window
+    chart_view: ChartView,
+    hashrate_timeframe: HashrateTimeframe,
+    hashrate_history: Option<MempoolHashrateHistory>,
+    hashrate_fetched_at: i64,
+    // Set when new hashrate data should reset the chart view
+    hashrate_changed: bool,
+    // Per-exchange breakdown when the price is aggregated across sources
+    price_aggregate: Option<AggregatedPrice>,
+    // Set while live trades are arriving over the WebSocket stream
+    price_stream_connected: bool,
     block_height: u32,
     block_time: String,
+    // Last few blocks, used to detect newly found blocks
+    block_tracker: BlockTracker,
+    difficulty_adjustment: Option<MempoolDifficultyAdjustment>,
     fastest_fee: u32,
     half_hour_fee: u32,
     hour_fee: u32,
     economy_fee: u32,
-    minimum_fee: u32,
+    // Blocks the current mempool would fill next
+    projected_blocks: Vec<MempoolProjectedBlock>,
+    // Mempool size and fee histogram
+    mempool_stats: Option<MempoolStats>,
     mempool_updating: bool,
     mempool_last_updated: String,
-    mempool_api_url: String,
-    mempool_custom_url_enabled: bool,
+    // Health of the mempool endpoints, and the URL of the one that served the current data
+    endpoint_pool: EndpointPool,
+    mempool_served_by: Option<String>,
+    // Connectivity of each data source
+    price_health: SourceHealth,
+    history_health: SourceHealth,
+    block_health: SourceHealth,
+    fee_health: SourceHealth,
+    price_alert_evaluator: PriceAlertEvaluator,
+    fee_alert_evaluator: FeeAlertEvaluator,
+    // Fired alerts shown as banners until dismissed
+    alert_banners: Vec<AlertEvent>,
+    // Recently fired alerts, newest last
+    alert_log: Vec<AlertEvent>,
+    // Balances of the watched addresses, by address
+    address_balances: std::collections::HashMap<String, AddressBalance>,
+    address_watcher: AddressWatcher,
+    // Transactions whose confirmations are tracked, persisted in the config directory
+    tracked_txs: Vec<TrackedTx>,
+    tx_progress: std::collections::HashMap<String, TxProgress>,
+    // Scan results of the watch-only wallets, by xpub
+    wallet_summaries: std::collections::HashMap<String, Result<WalletSummary, String>>,
+    // Recorded buys and sells
+    trades: Vec<Trade>,
+    // Latest DCA simulation and whether one is running
+    dca_result: Option<Result<DcaResult, String>>,
+    dca_running: bool,
+    // Result of the last Bitcoin Core connection test
+    node_status: Option<Result<String, String>>,
+    // Whether an Electrum header subscription is pushing new blocks
+    electrum_subscribed: bool,
+    // Set while the mempool WebSocket is pushing blocks, fees and projected blocks
+    mempool_stream_connected: bool,
+    // Outcome of saving mempool credentials: Ok with a note, or the error
+    mempool_access_status: Option<Result<String, String>>,
     config: AppConfig,
 }
 
+// Charts that can be shown in the main window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartView {
+    Price,
+    Hashrate,
+    FeeHistogram,
+}
+
 // Structure to hold candlestick data
 #[derive(Debug, Clone, Copy)]
 struct CandleData {
@@ -79,8 +191,11 @@ impl BitcoinState {
     fn new() -> Self {
         // Load configuration from file
         let config = AppConfig::load();
+        // Nothing may connect before the proxy settings are in place
+        proxy::configure(&config);
+        mempool_auth::configure(&config.mempool_endpoint_access);
         
-        BitcoinState {
+        let mut state = BitcoinState {
             price: 0.0,
             last_updated: "Never".to_string(),
             updating: false,
@@ -88,46 +203,241 @@ impl BitcoinState {
             historical_data: Vec::new(),
             chart_timeframe: ChartTimeframe::Hours24,
             timeframe_changed: false,
+            chart_view: ChartView::Price,
+            hashrate_timeframe: HashrateTimeframe::Year,
+            hashrate_history: None,
+            hashrate_fetched_at: 0,
+            hashrate_changed: false,
+            price_aggregate: None,
+            price_stream_connected: false,
             block_height: 0,
             block_time: "Unknown".to_string(),
+            block_tracker: BlockTracker::new(config.recent_blocks_count),
+            difficulty_adjustment: None,
             fastest_fee: 0,
             half_hour_fee: 0,
             hour_fee: 0,
             economy_fee: 0,
-            minimum_fee: 0,
+            projected_blocks: Vec::new(),
+            mempool_stats: None,
             mempool_updating: false,
             mempool_last_updated: "Never".to_string(),
-            mempool_api_url: config.mempool_api_url.clone(),
-            mempool_custom_url_enabled: config.mempool_custom_url_enabled,
+            endpoint_pool: EndpointPool::new(),
+            mempool_served_by: None,
+            // Allow roughly three missed refreshes before data counts as stale
+            price_health: SourceHealth::new("Price", 180),
+            history_health: SourceHealth::new("Chart", 300),
+            block_health: SourceHealth::new("Blocks", 360),
+            fee_health: SourceHealth::new("Fees", 360),
+            price_alert_evaluator: PriceAlertEvaluator::new(),
+            fee_alert_evaluator: FeeAlertEvaluator::new(),
+            alert_banners: Vec::new(),
+            alert_log: Vec::new(),
+            address_balances: std::collections::HashMap::new(),
+            address_watcher: AddressWatcher::new(),
+            tracked_txs: TrackedTxStore::new().load(),
+            tx_progress: std::collections::HashMap::new(),
+            wallet_summaries: std::collections::HashMap::new(),
+            trades: PortfolioStore::new().load(),
+            dca_result: None,
+            dca_running: false,
+            node_status: None,
+            electrum_subscribed: false,
+            mempool_stream_connected: false,
+            mempool_access_status: None,
             config,
+        };
+        
+        // Serve the chart from the candle cache right away, even when offline
+        state.load_cached_history();
+        if let Some((_, latest_candle)) = state.historical_data.last() {
+            state.price = latest_candle.close;
+            state.last_updated = format!("{} (cached)", latest_candle_time(&state.historical_data));
         }
+        state
     }
     
-    // Set a custom mempool API URL
-    fn set_mempool_api_url(&mut self, url: &str) {
-        self.mempool_api_url = url.to_string();
-        self.config.mempool_api_url = url.to_string();
-        if let Err(e) = self.config.save() {
-            eprintln!("Failed to save config: {}", e);
+    // Replace the mempool endpoints, in order of preference
+    fn set_mempool_endpoints(&mut self, endpoints: Vec<MempoolEndpoint>) {
+        self.config.mempool_endpoints = endpoints;
+        self.save_config();
+    }
+    
+    // Get the current mempool API URL to use: the first endpoint that isn't failing
+    fn get_active_mempool_url(&self) -> &str {
+        self.endpoint_pool.preferred(&self.config.mempool_endpoints)
+            .map(|endpoint| endpoint.url.as_str())
+            .unwrap_or(DEFAULT_MEMPOOL_API_URL)
+    }
+    
+    // Name of the endpoint at `url`, or the URL itself once it was removed
+    fn mempool_endpoint_name(&self, url: &str) -> String {
+        self.config.mempool_endpoints.iter()
+            .find(|endpoint| endpoint.url == url)
+            .map(|endpoint| endpoint.name.clone())
+            .unwrap_or_else(|| url.to_string())
+    }
+    
+    // Switch the backend for block, fee and mempool data
+    fn set_network_backend(&mut self, backend: NetworkBackend) {
+        self.config.network_backend = backend;
+        // mempool.space-only data would otherwise go stale
+        if backend != NetworkBackend::Mempool {
+            self.difficulty_adjustment = None;
+            self.projected_blocks.clear();
+            self.mempool_served_by = None;
+        }
+        self.save_config();
+    }
+    
+    // Save the proxy settings and route new connections by them
+    fn apply_proxy_settings(&mut self) {
+        proxy::configure(&self.config);
+        self.save_config();
+    }
+    
+    // Replace the credentials and TLS settings saved for one mempool API URL
+    fn set_mempool_endpoint_access(&mut self, access: MempoolEndpointAccess) {
+        self.config.mempool_endpoint_access.retain(|existing| existing.url != access.url);
+        if !access.is_empty() {
+            self.config.mempool_endpoint_access.push(access);
         }
+        self.save_config();
     }
     
-    // Enable or disable custom mempool URL
-    fn set_custom_mempool_enabled(&mut self, enabled: bool) {
-        self.mempool_custom_url_enabled = enabled;
-        self.config.mempool_custom_url_enabled = enabled;
+    // Persist the current configuration to disk
+    fn save_config(&self) {
         if let Err(e) = self.config.save() {
             eprintln!("Failed to save config: {}", e);
         }
     }
     
-    // Get the current mempool API URL to use
-    fn get_active_mempool_url(&self) -> &str {
-        if self.mempool_custom_url_enabled {
-            &self.mempool_api_url
-        } else {
-            DEFAULT_MEMPOOL_API_URL
+    // Replace the chart history with the cached candles for the current
+    // source, currency and timeframe
+    fn load_cached_history(&mut self) {
+        let (step, limit) = self.chart_timeframe.api_params();
+        let cached = CandleStore::new().load(self.config.price_source, self.config.quote_currency, step);
+        let skip = cached.len().saturating_sub(limit as usize);
+        self.historical_data = candles_to_history(&cached[skip..]);
+        self.new_price_fetched = true;
+    }
+    
+    // Switch the chart timeframe, showing cached candles until fresh ones arrive
+    fn set_chart_timeframe(&mut self, timeframe: ChartTimeframe) {
+        self.chart_timeframe = timeframe;
+        self.timeframe_changed = true;
+        self.load_cached_history();
+    }
+    
+    // Persist the tracked transactions
+    fn save_tracked_txs(&self) {
+        if let Err(e) = TrackedTxStore::new().save(&self.tracked_txs) {
+            eprintln!("Failed to save tracked transactions: {}", e);
+        }
+    }
+    
+    // Start tracking a transaction; returns false if it is already tracked
+    fn track_tx(&mut self, txid: &str) -> bool {
+        if self.tracked_txs.iter().any(|tx| tx.txid == txid) {
+            return false;
         }
+        self.tracked_txs.push(TrackedTx::new(txid));
+        self.save_tracked_txs();
+        true
+    }
+    
+    fn untrack_tx(&mut self, txid: &str) {
+        self.tracked_txs.retain(|tx| tx.txid != txid);
+        self.tx_progress.remove(txid);
+        self.save_tracked_txs();
+    }
+    
+    // Persist the recorded trades
+    fn save_trades(&self) {
+        if let Err(e) = PortfolioStore::new().save(&self.trades) {
+            eprintln!("Failed to save portfolio: {}", e);
+        }
+    }
+    
+    fn add_trade(&mut self, trade: Trade) {
+        self.trades.push(trade);
+        self.trades.sort_by_key(|trade| trade.timestamp);
+        self.save_trades();
+    }
+    
+    fn remove_trade(&mut self, index: usize) {
+        if index < self.trades.len() {
+            self.trades.remove(index);
+            self.save_trades();
+        }
+    }
+    
+    // Add imported trades, skipping ones already recorded; returns how many were added
+    fn import_trades(&mut self, trades: Vec<Trade>) -> usize {
+        let mut added = 0;
+        for trade in trades {
+            if !self.trades.iter().any(|existing| existing.same_fill(&trade)) {
+                self.trades.push(trade);
+                added += 1;
+            }
+        }
+        if added > 0 {
+            self.trades.sort_by_key(|trade| trade.timestamp);
+            self.save_trades();
+        }
+        added
+    }
+    
+    fn set_cost_basis_method(&mut self, method: CostBasisMethod) {
+        self.config.cost_basis_method = method;
+        self.save_config();
+    }
+    
+    // Switch the hashrate chart period; the old data is dropped until the new period is fetched
+    fn set_hashrate_timeframe(&mut self, timeframe: HashrateTimeframe) {
+        self.hashrate_timeframe = timeframe;
+        self.hashrate_history = None;
+        self.hashrate_fetched_at = 0;
+    }
+    
+    // Whether the hashrate chart is shown and its data is missing or outdated
+    fn wants_hashrate_refresh(&self) -> bool {
+        self.chart_view == ChartView::Hashrate
+            && Utc::now().timestamp() - self.hashrate_fetched_at > HASHRATE_REFRESH_SECS
+    }
+    
+    // Switch the exchange used for price and chart data
+    fn set_price_source(&mut self, source: PriceSource) {
+        self.config.price_source = source;
+        // Candles from another exchange shouldn't be mixed with the current ones
+        self.load_cached_history();
+        self.timeframe_changed = true;
+        self.save_config();
+    }
+    
+    // The mempool WebSocket only replaces polling for the mempool API backend
+    fn wants_mempool_stream(&self) -> bool {
+        self.config.mempool_websocket_enabled
+            && self.config.network_backend == NetworkBackend::Mempool
+    }
+    
+    // Live trade streaming is only available for a single Bitstamp source
+    fn wants_price_stream(&self) -> bool {
+        self.config.price_streaming_enabled
+            && self.config.price_source == PriceSource::Bitstamp
+            && self.config.price_source.supports(self.config.quote_currency)
+            && !self.config.price_aggregation_enabled
+    }
+    
+    // Switch the fiat currency prices and charts are quoted in
+    fn set_quote_currency(&mut self, currency: QuoteCurrency) {
+        self.config.quote_currency = currency;
+        // Prices in the old currency are meaningless now
+        self.price = 0.0;
+        self.price_aggregate = None;
+        self.load_cached_history();
+        self.timeframe_changed = true;
+        self.save_config();
     }
 }
 
@@ -136,7 +446,59 @@ struct BitcoinApp {
     price_history: Vec<(TimeInfo, CandleData)>,
     // UI state
     show_settings: bool,
-    mempool_url_input: String,
+    show_price_source: bool,
+    show_price_details: bool,
+    show_price_alerts: bool,
+    show_alert_log: bool,
+    show_dca: bool,
+    show_watched_addresses: bool,
+    show_transactions: bool,
+    show_wallets: bool,
+    show_portfolio: bool,
+    show_proxy: bool,
+    // Mempool endpoints being edited, applied together, and the one being added
+    mempool_endpoints_input: Vec<MempoolEndpoint>,
+    new_endpoint_name: String,
+    new_endpoint_url: String,
+    // Credentials and TLS settings being edited for the endpoint at `mempool_access_url`.
+    // The password or token and the headers are only kept until saved.
+    mempool_access_url: String,
+    mempool_access_input: MempoolEndpointAccess,
+    mempool_secret_input: String,
+    mempool_headers_input: String,
+    // Wallet being added to the watch-only wallets
+    new_wallet: WatchedWallet,
+    new_wallet_error: Option<String>,
+    // Trade being entered in the portfolio
+    new_trade: Trade,
+    new_trade_date: String,
+    csv_path_input: String,
+    portfolio_message: Option<String>,
+    // DCA simulator inputs
+    dca_amount: f64,
+    dca_frequency: DcaFrequency,
+    dca_start: String,
+    dca_end: String,
+    // Bitcoin Core RPC settings being edited
+    rpc_url_input: String,
+    rpc_cookie_input: String,
+    rpc_user_input: String,
+    rpc_password_input: String,
+    electrum_server_input: String,
+    // Proxy settings being edited, applied together
+    proxy_enabled_input: bool,
+    proxy_host_input: String,
+    proxy_port_input: u16,
+    proxy_tor_only_input: bool,
+    proxy_overrides_input: std::collections::BTreeMap<ProxySource, ProxyRoute>,
+    // Txid being added to the transaction tracker
+    txid_input: String,
+    // Address being added to the watch list
+    new_address_input: String,
+    new_address_label: String,
+    // Alerts being composed in the settings windows
+    new_price_alert: PriceAlertKind,
+    new_fee_alert: FeeAlert,
 }
 
 impl BitcoinApp {
@@ -146,49 +508,100 @@ impl BitcoinApp {
             state.historical_data.clone()
         };
         
-        let mempool_url = {
+        let (mempool_endpoints, rpc_url, rpc_cookie, rpc_user, rpc_password, electrum_server) = {
+            let state = state.lock().unwrap();
+            (
+                state.config.mempool_endpoints.clone(),
+                state.config.bitcoin_rpc_url.clone(),
+                state.config.bitcoin_rpc_cookie_file.clone(),
+                state.config.bitcoin_rpc_user.clone(),
+                state.config.bitcoin_rpc_password.clone(),
+                state.config.electrum_server.clone(),
+            )
+        };
+        let (proxy_enabled, proxy_host, proxy_port, proxy_tor_only, proxy_overrides) = {
             let state = state.lock().unwrap();
-            state.mempool_api_url.clone()
+            (
+                state.config.proxy_enabled,
+                state.config.proxy_host.clone(),
+                state.config.proxy_port,
+                state.config.proxy_tor_only,
+                state.config.proxy_overrides.clone(),
+            )
         };
         
         BitcoinApp {
             state,
             price_history,
             show_settings: false,
-            mempool_url_input: mempool_url,
+            show_price_source: false,
+            show_price_details: false,
+            show_price_alerts: false,
+            show_alert_log: false,
+            show_dca: false,
+            show_watched_addresses: false,
+            show_transactions: false,
+            show_wallets: false,
+            show_portfolio: false,
+            show_proxy: false,
+            mempool_endpoints_input: mempool_endpoints,
+            new_endpoint_name: String::new(),
+            new_endpoint_url: String::new(),
+            mempool_access_url: String::new(),
+            mempool_access_input: MempoolEndpointAccess::default(),
+            mempool_secret_input: String::new(),
+            mempool_headers_input: String::new(),
+            new_wallet: WatchedWallet {
+                xpub: String::new(),
+                label: String::new(),
+                gap_limit: 20,
+            },
+            new_wallet_error: None,
+            new_trade: Trade {
+                side: TradeSide::Buy,
+                amount: 0.0,
+                price: 0.0,
+                fee: 0.0,
+                timestamp: 0,
+                currency: QuoteCurrency::default(),
+            },
+            new_trade_date: Local::now().format("%Y-%m-%d").to_string(),
+            csv_path_input: String::new(),
+            portfolio_message: None,
+            dca_amount: 100.0,
+            dca_frequency: DcaFrequency::Weekly,
+            dca_start: (Local::now() - chrono::Duration::days(4 * 365)).format("%Y-%m-%d").to_string(),
+            dca_end: Local::now().format("%Y-%m-%d").to_string(),
+            rpc_url_input: rpc_url,
+            rpc_cookie_input: rpc_cookie,
+            rpc_user_input: rpc_user,
+            rpc_password_input: rpc_password,
+            electrum_server_input: electrum_server,
+            proxy_enabled_input: proxy_enabled,
+            proxy_host_input: proxy_host,
+            proxy_port_input: proxy_port,
+            proxy_tor_only_input: proxy_tor_only,
+            proxy_overrides_input: proxy_overrides,
+            txid_input: String::new(),
+            new_address_input: String::new(),
+            new_address_label: String::new(),
+            new_price_alert: PriceAlertKind::Above { level: 0.0 },
+            new_fee_alert: FeeAlert {
+                target: FeeTarget::Hour,
+                max_fee: 5,
+                min_minutes: 30,
+                enabled: true,
+            },
         }
     }
 
     fn update_price_history(&mut self) {
-        let mut state = self.state.lock().unwrap();  
-        // First check if we need to load initial historical data
-        if self.price_history.is_empty() && !state.historical_data.is_empty() {
-            self.price_history = state.historical_data.clone();
-        } else if !state.historical_data.is_empty() {
-            self.price_history = state.historical_data.clone();
-        }
+        let mut state = self.state.lock().unwrap();
         
-        if state.new_price_fetched {
-            let candle = CandleData {
-                open: state.price,
-                high: state.price,
-                low: state.price,
-                close: state.price,
-            };
-            
-            let now = chrono::Utc::now();
-            let timestamp = now.timestamp();
-            let time_info = TimeInfo {
-                raw_timestamp: timestamp,
-                formatted_time: state.last_updated.clone(),
-                rfc3339: now.to_rfc3339(),
-            };
-            self.price_history.push((time_info, candle));
-            
-            // Keep only last 100 entries
-            if self.price_history.len() > 100 {
-                self.price_history.remove(0);
-            }
+        // New prices are folded into the current candle of the shared history,
+        // so the chart only needs a fresh copy when something changed
+        if state.new_price_fetched || self.price_history.is_empty() || state.historical_data.is_empty() {
+            self.price_history = state.historical_data.clone();
             
             // Reset the flag
             state.new_price_fetched = false;
@@ -215,7 +628,39 @@ impl eframe::App for BitcoinApp {
                         self.show_settings = !self.show_settings;
                         ui.close_menu();
                     }
+                    if ui.button("Price Source").clicked() {
+                        self.show_price_source = !self.show_price_source;
+                        ui.close_menu();
+                    }
+                    if ui.button("Price Alerts").clicked() {
+                        self.show_price_alerts = !self.show_price_alerts;
+                        ui.close_menu();
+                    }
+                    if ui.button("Watched Addresses").clicked() {
+                        self.show_watched_addresses = !self.show_watched_addresses;
+                        ui.close_menu();
+                    }
+                    if ui.button("Watch-only Wallets").clicked() {
+                        self.show_wallets = !self.show_wallets;
+                        ui.close_menu();
+                    }
+                    if ui.button("Proxy").clicked() {
+                        self.show_proxy = !self.show_proxy;
+                        ui.close_menu();
+                    }
                 });
+                if ui.button("Portfolio").clicked() {
+                    self.show_portfolio = !self.show_portfolio;
+                }
+                if ui.button("DCA").clicked() {
+                    self.show_dca = !self.show_dca;
+                }
+                if ui.button("Transactions").clicked() {
+                    self.show_transactions = !self.show_transactions;
+                }
+                if ui.button("Alert Log").clicked() {
+                    self.show_alert_log = !self.show_alert_log;
+                }
             });
         });
         
@@ -227,48 +672,422 @@ impl eframe::App for BitcoinApp {
                 .show(ctx, |ui| {
                     let mut state = self.state.lock().unwrap();
                     
-                    // Custom URL toggle
-                    let mut custom_enabled = state.mempool_custom_url_enabled;
-                    ui.checkbox(&mut custom_enabled, "Use custom mempool instance");
-                    if custom_enabled != state.mempool_custom_url_enabled {
-                        state.set_custom_mempool_enabled(custom_enabled);
+                    // Backend for block, fee and mempool data
+                    ui.horizontal(|ui| {
+                        ui.label("Network data from:");
+                        let mut backend = state.config.network_backend;
+                        for option in NetworkBackend::ALL {
+                            ui.selectable_value(&mut backend, option, option.name());
+                        }
+                        if backend != state.config.network_backend {
+                            state.set_network_backend(backend);
+                            let state_clone = self.state.clone();
+                            std::thread::spawn(move || {
+                                refresh_mempool_data(state_clone);
+                            });
+                        }
+                    });
+                    
+                    if state.config.network_backend == NetworkBackend::BitcoinCore {
+                        egui::Grid::new("bitcoin_rpc_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("RPC URL:");
+                            ui.text_edit_singleline(&mut self.rpc_url_input);
+                            ui.end_row();
+                            
+                            ui.label("Authentication:");
+                            ui.horizontal(|ui| {
+                                let mut auth = state.config.bitcoin_rpc_auth;
+                                ui.selectable_value(&mut auth, RpcAuthMethod::Cookie, RpcAuthMethod::Cookie.name());
+                                ui.selectable_value(&mut auth, RpcAuthMethod::UserPassword, RpcAuthMethod::UserPassword.name());
+                                if auth != state.config.bitcoin_rpc_auth {
+                                    state.config.bitcoin_rpc_auth = auth;
+                                    state.save_config();
+                                    // Only read the keyring once the password is needed
+                                    if auth == RpcAuthMethod::UserPassword && state.config.bitcoin_rpc_password.is_empty() {
+                                        match bitcoin_rpc_client::load_password() {
+                                            Ok(password) => {
+                                                state.config.bitcoin_rpc_password = password.unwrap_or_default();
+                                                self.rpc_password_input = state.config.bitcoin_rpc_password.clone();
+                                            },
+                                            Err(e) => state.node_status = Some(Err(format!("Failed to load the RPC password: {}", e))),
+                                        }
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                            
+                            match state.config.bitcoin_rpc_auth {
+                                RpcAuthMethod::Cookie => {
+                                    ui.label("Cookie file:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.rpc_cookie_input)
+                                        .hint_text(bitcoin_rpc_client::default_cookie_path().display().to_string()));
+                                    ui.end_row();
+                                },
+                                RpcAuthMethod::UserPassword => {
+                                    ui.label("Username:");
+                                    ui.text_edit_singleline(&mut self.rpc_user_input);
+                                    ui.end_row();
+                                    ui.label("Password:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.rpc_password_input).password(true));
+                                    ui.end_row();
+                                },
+                            }
+                        });
+                        
+                        ui.horizontal(|ui| {
+                            if ui.button("Apply").clicked() {
+                                state.config.bitcoin_rpc_url = self.rpc_url_input.trim().to_string();
+                                state.config.bitcoin_rpc_cookie_file = self.rpc_cookie_input.trim().to_string();
+                                state.config.bitcoin_rpc_user = self.rpc_user_input.trim().to_string();
+                                if self.rpc_password_input != state.config.bitcoin_rpc_password {
+                                    match bitcoin_rpc_client::store_password(&self.rpc_password_input) {
+                                        Ok(()) => state.config.bitcoin_rpc_password = self.rpc_password_input.clone(),
+                                        Err(e) => state.node_status = Some(Err(format!("Failed to store the RPC password: {}", e))),
+                                    }
+                                }
+                                state.save_config();
+                                
+                                let state_clone = self.state.clone();
+                                std::thread::spawn(move || {
+                                    refresh_mempool_data(state_clone);
+                                });
+                            }
+                            if ui.button("Test Connection").clicked() {
+                                state.node_status = None;
+                                let state_clone = self.state.clone();
+                                std::thread::spawn(move || {
+                                    check_bitcoin_node(state_clone);
+                                });
+                            }
+                        });
+                        match &state.node_status {
+                            Some(Ok(status)) => {
+                                ui.colored_label(egui::Color32::from_rgb(0, 150, 0), status);
+                            },
+                            Some(Err(error)) => {
+                                ui.colored_label(egui::Color32::from_rgb(200, 0, 0), error);
+                            },
+                            None => {},
+                        }
+                    }
+                    
+                    if state.config.network_backend == NetworkBackend::Electrum {
+                        ui.horizontal(|ui| {
+                            ui.label("Server:");
+                            ui.add(egui::TextEdit::singleline(&mut self.electrum_server_input)
+                                .hint_text("ssl://host:50002 or tcp://host:50001"));
+                        });
+                        let mut accept_invalid = state.config.electrum_accept_invalid_certs;
+                        if ui.checkbox(&mut accept_invalid, "Accept self-signed certificates").changed() {
+                            state.config.electrum_accept_invalid_certs = accept_invalid;
+                            state.save_config();
+                        }
+                        if ui.button("Apply").clicked() {
+                            // The header subscription reconnects once it notices the change
+                            state.config.electrum_server = self.electrum_server_input.trim().to_string();
+                            state.save_config();
+                        }
+                        if state.electrum_subscribed {
+                            ui.colored_label(egui::Color32::from_rgb(0, 150, 0), "Subscribed to new blocks");
+                        } else {
+                            ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "Not connected, polling every 2 minutes");
+                        }
+                    }
+                    
+                    match state.config.network_backend {
+                        NetworkBackend::Mempool => {
+                            let mut websocket_enabled = state.config.mempool_websocket_enabled;
+                            if ui.checkbox(&mut websocket_enabled, "Stream updates over WebSocket").changed() {
+                                state.config.mempool_websocket_enabled = websocket_enabled;
+                                state.save_config();
+                            }
+                            if websocket_enabled {
+                                if state.mempool_stream_connected {
+                                    ui.colored_label(egui::Color32::from_rgb(0, 150, 0), "Streaming blocks, fees and projected blocks");
+                                } else {
+                                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "Not connected, polling every 2 minutes");
+                                }
+                            }
+                        },
+                        NetworkBackend::BitcoinCore => {
+                            ui.weak("Projected blocks, difficulty estimates and the fee histogram need the mempool API. Addresses, wallets and transactions are still looked up there.");
+                        },
+                        NetworkBackend::Electrum => {
+                            ui.weak("Projected blocks and difficulty estimates need the mempool API. Wallets and transactions are still looked up there.");
+                        },
+                    }
+                    ui.separator();
+                    
+                    // Mempool API endpoints, tried in order
+                    ui.label("Mempool API endpoints, in order of preference:");
+                    let endpoint_count = self.mempool_endpoints_input.len();
+                    let mut move_up = None;
+                    let mut remove = None;
+                    egui::Grid::new("mempool_endpoints_grid").show(ui, |ui| {
+                        for (index, endpoint) in self.mempool_endpoints_input.iter_mut().enumerate() {
+                            ui.add(egui::TextEdit::singleline(&mut endpoint.name).desired_width(100.0));
+                            ui.add(egui::TextEdit::singleline(&mut endpoint.url).desired_width(220.0));
+                            endpoint_status_label(ui, state.endpoint_pool.status(&endpoint.url));
+                            if ui.add_enabled(index > 0, egui::Button::new("Move up").small()).clicked() {
+                                move_up = Some(index);
+                            }
+                            // At least one endpoint is always needed
+                            if ui.add_enabled(endpoint_count > 1, egui::Button::new("Remove").small()).clicked() {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(index) = move_up {
+                        self.mempool_endpoints_input.swap(index - 1, index);
+                    }
+                    if let Some(index) = remove {
+                        self.mempool_endpoints_input.remove(index);
                     }
                     
-                    // Custom URL input field
                     ui.horizontal(|ui| {
-                        ui.label("Mempool API URL:");
-                        ui.add_enabled(custom_enabled, egui::TextEdit::singleline(&mut self.mempool_url_input));
+                        ui.add(egui::TextEdit::singleline(&mut self.new_endpoint_name)
+                            .hint_text("Name")
+                            .desired_width(100.0));
+                        ui.add(egui::TextEdit::singleline(&mut self.new_endpoint_url)
+                            .hint_text("https://host/api")
+                            .desired_width(220.0));
+                        if ui.add_enabled(!self.new_endpoint_url.trim().is_empty(), egui::Button::new("Add")).clicked() {
+                            self.mempool_endpoints_input.push(MempoolEndpoint::new(&self.new_endpoint_name, &self.new_endpoint_url));
+                            self.new_endpoint_name.clear();
+                            self.new_endpoint_url.clear();
+                        }
                     });
                     
-                    // Apply button
-                    if ui.add_enabled(custom_enabled, egui::Button::new("Apply")).clicked() {
-                        if !self.mempool_url_input.is_empty() {
-                            state.set_mempool_api_url(&self.mempool_url_input);
+                    ui.horizontal(|ui| {
+                        let mut applied = None;
+                        if ui.button("Apply").clicked() {
+                            let endpoints: Vec<MempoolEndpoint> = self.mempool_endpoints_input.iter()
+                                .filter(|endpoint| !endpoint.url.trim().is_empty())
+                                .map(|endpoint| MempoolEndpoint::new(&endpoint.name, &endpoint.url))
+                                .collect();
+                            if !endpoints.is_empty() {
+                                applied = Some(endpoints);
+                            }
+                        }
+                        if ui.button("Reset to Default").clicked() {
+                            applied = Some(mempool_endpoints::default_endpoints());
+                        }
+                        
+                        if let Some(endpoints) = applied {
+                            self.mempool_endpoints_input = endpoints.clone();
+                            state.set_mempool_endpoints(endpoints);
                             
-                            // Refresh mempool data with new URL
+                            // Check the new endpoints, then refresh from the first healthy one
                             let state_clone = self.state.clone();
                             std::thread::spawn(move || {
+                                check_mempool_endpoints(&state_clone);
                                 refresh_mempool_data(state_clone);
                             });
                         }
-                    }
+                    });
                     
-                    // Reset to default button
-                    if ui.button("Reset to Default").clicked() {
-                        state.set_custom_mempool_enabled(false);
-                        self.mempool_url_input = DEFAULT_MEMPOOL_API_URL.to_string();
+                    // Credentials and TLS settings, per endpoint
+                    if !state.config.mempool_endpoints.iter().any(|endpoint| endpoint.url == self.mempool_access_url) {
+                        self.mempool_access_url = state.get_active_mempool_url().to_string();
+                    }
+                    if self.mempool_access_input.url != self.mempool_access_url {
+                        self.mempool_access_input = state.config.mempool_endpoint_access.iter()
+                            .find(|access| access.url == self.mempool_access_url)
+                            .cloned()
+                            .unwrap_or_else(|| MempoolEndpointAccess::new(&self.mempool_access_url));
+                        self.mempool_secret_input.clear();
+                        self.mempool_headers_input.clear();
+                        state.mempool_access_status = None;
+                    }
+                    ui.collapsing("Authentication & TLS", |ui| {
+                        let selected_name = state.mempool_endpoint_name(&self.mempool_access_url);
+                        egui::ComboBox::from_label("Endpoint")
+                            .selected_text(selected_name)
+                            .show_ui(ui, |ui| {
+                                for endpoint in &state.config.mempool_endpoints {
+                                    ui.selectable_value(&mut self.mempool_access_url, endpoint.url.clone(), &endpoint.name);
+                                }
+                            });
+                        let access = &mut self.mempool_access_input;
                         
-                        // Refresh mempool data with default URL
-                        let state_clone = self.state.clone();
-                        std::thread::spawn(move || {
-                            refresh_mempool_data(state_clone);
+                        egui::ComboBox::from_label("Authentication")
+                            .selected_text(access.auth.name())
+                            .show_ui(ui, |ui| {
+                                for method in MempoolAuthMethod::ALL {
+                                    ui.selectable_value(&mut access.auth, method, method.name());
+                                }
+                            });
+                        match access.auth {
+                            MempoolAuthMethod::None => {},
+                            MempoolAuthMethod::Basic => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Username:");
+                                    ui.text_edit_singleline(&mut access.username);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Password:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.mempool_secret_input)
+                                        .password(true)
+                                        .hint_text("Leave empty to keep the stored password"));
+                                });
+                            },
+                            MempoolAuthMethod::Bearer => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Token:");
+                                    ui.add(egui::TextEdit::singleline(&mut self.mempool_secret_input)
+                                        .password(true)
+                                        .hint_text("Leave empty to keep the stored token"));
+                                });
+                            },
+                        }
+                        
+                        ui.label("Custom headers (one \"Name: value\" per line):");
+                        let headers_hint = if access.custom_headers {
+                            "Stored in the keyring; enter new headers to replace them"
+                        } else {
+                            "X-Api-Key: ..."
+                        };
+                        ui.add(egui::TextEdit::multiline(&mut self.mempool_headers_input)
+                            .desired_rows(2)
+                            .hint_text(headers_hint));
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("CA bundle (PEM):");
+                            ui.add(egui::TextEdit::singleline(&mut access.ca_bundle).hint_text("/path/to/ca.pem"));
                         });
-                    }
+                        ui.horizontal(|ui| {
+                            ui.label("Pinned certificate SHA-256:");
+                            ui.add(egui::TextEdit::singleline(&mut access.pinned_sha256).hint_text("AB:CD:..."));
+                        });
+                        ui.weak("Passwords, tokens and headers are stored in the OS keyring, not in config.json. A pinned certificate is trusted even when self-signed.");
+                        
+                        ui.horizontal(|ui| {
+                            if ui.button("Save").clicked() {
+                                match mempool_auth::validate(access, &self.mempool_headers_input) {
+                                    Ok(()) => {
+                                        access.custom_headers |= !self.mempool_headers_input.trim().is_empty();
+                                        let access = access.clone();
+                                        let secret = std::mem::take(&mut self.mempool_secret_input);
+                                        let headers = std::mem::take(&mut self.mempool_headers_input);
+                                        state.mempool_access_status = Some(Ok("Saving...".to_string()));
+                                        
+                                        // The keyring may ask the user to unlock it
+                                        let state_clone = self.state.clone();
+                                        std::thread::spawn(move || {
+                                            save_mempool_access(state_clone, access, secret, headers);
+                                        });
+                                    },
+                                    Err(e) => state.mempool_access_status = Some(Err(e.to_string())),
+                                }
+                            }
+                            if ui.button("Forget stored secrets").clicked() {
+                                access.auth = MempoolAuthMethod::None;
+                                access.username.clear();
+                                access.custom_headers = false;
+                                let access = access.clone();
+                                self.mempool_secret_input.clear();
+                                self.mempool_headers_input.clear();
+                                state.mempool_access_status = Some(Ok("Removing...".to_string()));
+                                
+                                let state_clone = self.state.clone();
+                                std::thread::spawn(move || {
+                                    forget_mempool_access(state_clone, access);
+                                });
+                            }
+                        });
+                        match &state.mempool_access_status {
+                            Some(Ok(note)) => { ui.colored_label(egui::Color32::from_rgb(0, 150, 0), note); },
+                            Some(Err(error)) => { ui.colored_label(egui::Color32::from_rgb(200, 0, 0), error); },
+                            None => {},
+                        }
+                    });
                     
                     // Current status
                     ui.separator();
-                    ui.label(format!("Currently using: {}", state.get_active_mempool_url()));
+                    match state.config.network_backend {
+                        NetworkBackend::Mempool => {
+                            let url = state.mempool_served_by.clone()
+                                .unwrap_or_else(|| state.get_active_mempool_url().to_string());
+                            ui.label(format!("Currently using: {} ({})", state.mempool_endpoint_name(&url), url));
+                        },
+                        NetworkBackend::BitcoinCore => {
+                            ui.label(format!(
+                                "Currently using: {} (mempool API: {})",
+                                state.config.bitcoin_rpc_url,
+                                state.get_active_mempool_url()
+                            ));
+                        },
+                        NetworkBackend::Electrum => {
+                            ui.label(format!(
+                                "Currently using: {} (mempool API: {})",
+                                state.config.electrum_server,
+                                state.get_active_mempool_url()
+                            ));
+                        },
+                    }
+                    
+                    // New block notifications and timeline length
+                    ui.separator();
+                    let mut block_notifications = state.config.block_notifications_enabled;
+                    if ui.checkbox(&mut block_notifications, "Notify when a new block is found").changed() {
+                        state.config.block_notifications_enabled = block_notifications;
+                        state.save_config();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Recent blocks listed:");
+                        let mut count = state.config.recent_blocks_count;
+                        if ui.add(egui::DragValue::new(&mut count).range(1..=100)).changed() {
+                            state.config.recent_blocks_count = count;
+                            state.block_tracker.set_capacity(count);
+                            state.save_config();
+                        }
+                    });
+                    
+                    // Fee alerts
+                    ui.separator();
+                    ui.label("Fee alerts:");
+                    let mut fee_alerts_changed = false;
+                    let mut remove = None;
+                    for (index, alert) in state.config.fee_alerts.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            let description = alert.describe();
+                            fee_alerts_changed |= ui.checkbox(&mut alert.enabled, description).changed();
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        state.config.fee_alerts.remove(index);
+                        fee_alerts_changed = true;
+                    }
+                    
+                    ui.horizontal(|ui| {
+                        let new_alert = &mut self.new_fee_alert;
+                        egui::ComboBox::from_id_salt("fee_alert_target")
+                            .selected_text(new_alert.target.name())
+                            .show_ui(ui, |ui| {
+                                for target in FeeTarget::ALL {
+                                    ui.selectable_value(&mut new_alert.target, target, target.name());
+                                }
+                            });
+                        ui.label("fee ≤");
+                        ui.add(egui::DragValue::new(&mut new_alert.max_fee).range(1..=1000));
+                        ui.label("sat/vB for");
+                        ui.add(egui::DragValue::new(&mut new_alert.min_minutes).range(0..=1440));
+                        ui.label("min");
+                        if ui.button("Add").clicked() {
+                            state.config.fee_alerts.push(new_alert.clone());
+                            fee_alerts_changed = true;
+                        }
+                    });
+                    
+                    if fee_alerts_changed {
+                        state.save_config();
+                    }
                     
+                    ui.separator();
                     // Close button
                     if ui.button("Close").clicked() {
                         self.show_settings = false;
@@ -276,43 +1095,950 @@ impl eframe::App for BitcoinApp {
                 });
         }
         
-        // Top panel for mempool info
-        egui::TopBottomPanel::top("mempool_info").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                let state = self.state.lock().unwrap();
-                
-                ui.vertical(|ui| {
-                    ui.heading("Bitcoin Network");
-                    ui.horizontal(|ui| {
-                        ui.label(format!("Block Height: {}", state.block_height));
-                        ui.label("|");
-                        ui.label(format!("Block Time: {}", state.block_time));
-                        ui.label("|");
-                        ui.label(format!("Last Updated: {}", state.mempool_last_updated));
-                    });
+        // Price source window
+        if self.show_price_source {
+            egui::Window::new("Price Source")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
                     
-                    ui.horizontal(|ui| {
-                        ui.label("Fees (sat/vB):");
-                        ui.label(format!("Fastest: {}", state.fastest_fee));
-                        ui.label("|");
-                        ui.label(format!("30m: {}", state.half_hour_fee));
-                        ui.label("|");
-                        ui.label(format!("1h: {}", state.hour_fee));
-                        ui.label("|");
-                        ui.label(format!("Economy: {}", state.economy_fee));
+                    ui.label("Exchange used for the price and chart:");
+                    let mut selected = state.config.price_source;
+                    for source in PriceSource::ALL {
+                        ui.radio_value(&mut selected, source, source.name());
+                    }
+                    
+                    let mut needs_refresh = false;
+                    if selected != state.config.price_source {
+                        state.set_price_source(selected);
+                        needs_refresh = true;
+                    }
+                    
+                    // Quote currency selection
+                    let mut currency = state.config.quote_currency;
+                    egui::ComboBox::from_label("Quote currency")
+                        .selected_text(currency.code())
+                        .show_ui(ui, |ui| {
+                            for option in QuoteCurrency::ALL {
+                                ui.selectable_value(&mut currency, option, format!("{} ({})", option.code(), option.symbol()));
+                            }
+                        });
+                    if currency != state.config.quote_currency {
+                        state.set_quote_currency(currency);
+                        needs_refresh = true;
+                    }
+                    
+                    // Live trade streaming
+                    let mut streaming_enabled = state.config.price_streaming_enabled;
+                    ui.add_enabled(
+                        state.config.price_source == PriceSource::Bitstamp,
+                        egui::Checkbox::new(&mut streaming_enabled, "Stream live trades (Bitstamp only)"),
+                    );
+                    if streaming_enabled != state.config.price_streaming_enabled {
+                        state.config.price_streaming_enabled = streaming_enabled;
+                        state.save_config();
+                    }
+                    
+                    // Median aggregation across several exchanges
+                    ui.separator();
+                    let mut aggregation_enabled = state.config.price_aggregation_enabled;
+                    ui.checkbox(&mut aggregation_enabled, "Use median price across exchanges");
+                    if aggregation_enabled != state.config.price_aggregation_enabled {
+                        state.config.price_aggregation_enabled = aggregation_enabled;
+                        if !aggregation_enabled {
+                            state.price_aggregate = None;
+                        }
+                        state.save_config();
+                        needs_refresh = true;
+                    }
+                    
+                    ui.add_enabled_ui(aggregation_enabled, |ui| {
+                        ui.label("Sources to aggregate:");
+                        ui.horizontal(|ui| {
+                            for source in PriceSource::ALL {
+                                let mut included = state.config.aggregation_sources.contains(&source);
+                                let checkbox = ui.add_enabled(
+                                    source.supports(currency),
+                                    egui::Checkbox::new(&mut included, source.name()),
+                                ).on_disabled_hover_text(format!("No BTC/{} market", currency.code()));
+                                if checkbox.changed() {
+                                    if included {
+                                        state.config.aggregation_sources.push(source);
+                                    } else {
+                                        state.config.aggregation_sources.retain(|s| *s != source);
+                                    }
+                                    state.save_config();
+                                }
+                            }
+                        });
+                        
+                        let mut max_deviation = state.config.max_price_deviation_percent;
+                        let slider = egui::Slider::new(&mut max_deviation, 0.1..=10.0)
+                            .text("max deviation from median (%)");
+                        if ui.add(slider).changed() {
+                            state.config.max_price_deviation_percent = max_deviation;
+                            state.save_config();
+                        }
                     });
+                    
+                    if needs_refresh {
+                        // Refresh price and chart data with the new settings
+                        let state_clone = self.state.clone();
+                        std::thread::spawn(move || {
+                            refresh_bitcoin_price(state_clone);
+                        });
+                    }
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_price_source = false;
+                    }
                 });
-            });
-            ui.separator();
-        });
+        }
         
-        egui::CentralPanel::default().show(ctx, |ui| {
-            
+        // Price alerts window
+        if self.show_price_alerts {
+            egui::Window::new("Price Alerts")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    let currency = state.config.quote_currency;
+                    
+                    if state.config.price_alerts.is_empty() {
+                        ui.label("No price alerts configured.");
+                    }
+                    
+                    let mut changed = false;
+                    let mut remove = None;
+                    for (index, alert) in state.config.price_alerts.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            let description = alert.describe(currency);
+                            changed |= ui.checkbox(&mut alert.enabled, description).changed();
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        state.config.price_alerts.remove(index);
+                        changed = true;
+                    }
+                    
+                    // Compose a new alert
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let price = state.price;
+                        let kind = &mut self.new_price_alert;
+                        if ui.radio(matches!(kind, PriceAlertKind::Above { .. }), "Above").clicked() {
+                            *kind = PriceAlertKind::Above { level: price.round() };
+                        }
+                        if ui.radio(matches!(kind, PriceAlertKind::Below { .. }), "Below").clicked() {
+                            *kind = PriceAlertKind::Below { level: price.round() };
+                        }
+                        if ui.radio(matches!(kind, PriceAlertKind::PercentMove { .. }), "Percent move").clicked() {
+                            *kind = PriceAlertKind::PercentMove { percent: 5.0, window_minutes: 60 };
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        match &mut self.new_price_alert {
+                            PriceAlertKind::Above { level } | PriceAlertKind::Below { level } => {
+                                ui.label(format!("Level ({}):", currency.symbol()));
+                                ui.add(egui::DragValue::new(level).speed(100.0).range(0.0..=f64::MAX));
+                            },
+                            PriceAlertKind::PercentMove { percent, window_minutes } => {
+                                ui.label("Move (%):");
+                                ui.add(egui::DragValue::new(percent).speed(0.1).range(0.1..=100.0));
+                                ui.label("within (min):");
+                                ui.add(egui::DragValue::new(window_minutes).range(1..=1440));
+                            },
+                        }
+                        if ui.button("Add").clicked() {
+                            state.config.price_alerts.push(PriceAlert {
+                                kind: self.new_price_alert.clone(),
+                                enabled: true,
+                            });
+                            changed = true;
+                        }
+                    });
+                    
+                    ui.separator();
+                    let mut hysteresis = state.config.alert_hysteresis_percent;
+                    let slider = egui::Slider::new(&mut hysteresis, 0.0..=5.0)
+                        .text("re-arm margin (%)");
+                    if ui.add(slider).changed() {
+                        state.config.alert_hysteresis_percent = hysteresis;
+                        changed = true;
+                    }
+                    
+                    if changed {
+                        state.save_config();
+                    }
+                    
+                    if ui.button("Close").clicked() {
+                        self.show_price_alerts = false;
+                    }
+                });
+        }
+        
+        // Address watch list window
+        if self.show_watched_addresses {
+            egui::Window::new("Watched Addresses")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    
+                    if state.config.watched_addresses.is_empty() {
+                        ui.label("No addresses are being watched.");
+                    }
+                    
+                    let mut remove = None;
+                    for (index, watched) in state.config.watched_addresses.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(watched.display_name()).on_hover_text(&watched.address);
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        let removed = state.config.watched_addresses.remove(index);
+                        state.address_balances.remove(&removed.address);
+                        let watched = state.config.watched_addresses.clone();
+                        state.address_watcher.retain(&watched);
+                        state.save_config();
+                    }
+                    
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Address:");
+                        ui.text_edit_singleline(&mut self.new_address_input);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Label:");
+                        ui.text_edit_singleline(&mut self.new_address_label);
+                    });
+                    
+                    let address = self.new_address_input.trim().to_string();
+                    let already_watched = state.config.watched_addresses.iter().any(|w| w.address == address);
+                    if ui.add_enabled(!address.is_empty() && !already_watched, egui::Button::new("Add")).clicked() {
+                        state.config.watched_addresses.push(WatchedAddress {
+                            address,
+                            label: self.new_address_label.trim().to_string(),
+                        });
+                        state.save_config();
+                        self.new_address_input.clear();
+                        self.new_address_label.clear();
+                        
+                        // Fetch the new address's balance right away
+                        let state_clone = self.state.clone();
+                        std::thread::spawn(move || {
+                            refresh_watched_addresses(state_clone);
+                        });
+                    }
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_watched_addresses = false;
+                    }
+                });
+        }
+        
+        // Watch-only wallet configuration
+        if self.show_wallets {
+            egui::Window::new("Watch-only Wallets")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    
+                    if state.config.watched_wallets.is_empty() {
+                        ui.label("No wallets are being watched.");
+                    }
+                    
+                    let mut remove = None;
+                    for (index, wallet) in state.config.watched_wallets.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} (gap limit {})", wallet.display_name(), wallet.gap_limit))
+                                .on_hover_text(&wallet.xpub);
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        let removed = state.config.watched_wallets.remove(index);
+                        state.wallet_summaries.remove(&removed.xpub);
+                        state.save_config();
+                    }
+                    
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("xpub/ypub/zpub:");
+                        ui.text_edit_singleline(&mut self.new_wallet.xpub);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Label:");
+                        ui.text_edit_singleline(&mut self.new_wallet.label);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Gap limit:");
+                        ui.add(egui::DragValue::new(&mut self.new_wallet.gap_limit).range(1..=200));
+                    });
+                    
+                    if ui.add_enabled(!self.new_wallet.xpub.trim().is_empty(), egui::Button::new("Add")).clicked() {
+                        let mut wallet = self.new_wallet.clone();
+                        wallet.xpub = wallet.xpub.trim().to_string();
+                        wallet.label = wallet.label.trim().to_string();
+                        
+                        // Check the key before saving it
+                        match hd_wallet::AccountKey::parse(&wallet.xpub) {
+                            Ok(_) if state.config.watched_wallets.iter().any(|w| w.xpub == wallet.xpub) => {
+                                self.new_wallet_error = Some("This wallet is already watched".to_string());
+                            },
+                            Ok(_) => {
+                                state.config.watched_wallets.push(wallet);
+                                state.save_config();
+                                self.new_wallet.xpub.clear();
+                                self.new_wallet.label.clear();
+                                self.new_wallet_error = None;
+                                
+                                let state_clone = self.state.clone();
+                                std::thread::spawn(move || {
+                                    refresh_watched_wallets(state_clone);
+                                });
+                            },
+                            Err(e) => self.new_wallet_error = Some(e.to_string()),
+                        }
+                    }
+                    if let Some(error) = &self.new_wallet_error {
+                        ui.colored_label(egui::Color32::from_rgb(200, 0, 0), error);
+                    }
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_wallets = false;
+                    }
+                });
+        }
+        
+        // SOCKS5/Tor proxy for all outgoing connections
+        if self.show_proxy {
+            egui::Window::new("Proxy")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.checkbox(&mut self.proxy_enabled_input, "Route connections through a SOCKS5 proxy (e.g. Tor)");
+                    ui.horizontal(|ui| {
+                        ui.label("Host:");
+                        ui.add_enabled(self.proxy_enabled_input, egui::TextEdit::singleline(&mut self.proxy_host_input).desired_width(140.0));
+                        ui.label("Port:");
+                        ui.add_enabled(self.proxy_enabled_input, egui::DragValue::new(&mut self.proxy_port_input).range(1..=65535));
+                    });
+                    ui.checkbox(&mut self.proxy_tor_only_input, "Tor only: never connect directly, except to this machine");
+                    
+                    ui.separator();
+                    ui.label("Per-source routes:");
+                    egui::Grid::new("proxy_routes_grid").num_columns(2).show(ui, |ui| {
+                        for source in ProxySource::ALL {
+                            ui.label(source.name());
+                            ui.horizontal(|ui| {
+                                let current = self.proxy_overrides_input.get(&source).cloned();
+                                let selected = match &current {
+                                    None => "Default",
+                                    Some(ProxyRoute::Direct) => "Direct",
+                                    Some(ProxyRoute::Socks5 { .. }) => "Other proxy",
+                                };
+                                egui::ComboBox::from_id_salt(("proxy_route", source.name()))
+                                    .selected_text(selected)
+                                    .show_ui(ui, |ui| {
+                                        if ui.selectable_label(current.is_none(), "Default").clicked() {
+                                            self.proxy_overrides_input.remove(&source);
+                                        }
+                                        if ui.selectable_label(current == Some(ProxyRoute::Direct), "Direct").clicked() {
+                                            self.proxy_overrides_input.insert(source, ProxyRoute::Direct);
+                                        }
+                                        let is_socks = matches!(current, Some(ProxyRoute::Socks5 { .. }));
+                                        if ui.selectable_label(is_socks, "Other proxy").clicked() && !is_socks {
+                                            self.proxy_overrides_input.insert(source, ProxyRoute::Socks5 {
+                                                host: proxy::DEFAULT_PROXY_HOST.to_string(),
+                                                port: proxy::DEFAULT_PROXY_PORT,
+                                            });
+                                        }
+                                    });
+                                if let Some(ProxyRoute::Socks5 { host, port }) = self.proxy_overrides_input.get_mut(&source) {
+                                    ui.add(egui::TextEdit::singleline(host).desired_width(120.0));
+                                    ui.add(egui::DragValue::new(port).range(1..=65535));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    
+                    if self.proxy_tor_only_input && !self.proxy_enabled_input {
+                        ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "Without a proxy, Tor only mode blocks everything but local servers.");
+                    }
+                    
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            let mut state = self.state.lock().unwrap();
+                            state.config.proxy_enabled = self.proxy_enabled_input;
+                            state.config.proxy_host = self.proxy_host_input.trim().to_string();
+                            state.config.proxy_port = self.proxy_port_input;
+                            state.config.proxy_tor_only = self.proxy_tor_only_input;
+                            state.config.proxy_overrides = self.proxy_overrides_input.clone();
+                            state.apply_proxy_settings();
+                            drop(state);
+                            
+                            // Fetch fresh data over the new routes
+                            let state_clone = self.state.clone();
+                            std::thread::spawn(move || {
+                                refresh_bitcoin_price(state_clone.clone());
+                                refresh_mempool_data(state_clone);
+                            });
+                        }
+                        if ui.button("Close").clicked() {
+                            self.show_proxy = false;
+                        }
+                    });
+                });
+        }
+        
+        // Trade holdings with cost basis, and the watch-only wallets
+        if self.show_portfolio {
+            egui::Window::new("Portfolio")
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    let currency = state.config.quote_currency;
+                    
+                    ui.heading("Holdings");
+                    let mut method = state.config.cost_basis_method;
+                    ui.horizontal(|ui| {
+                        ui.label("Cost basis:");
+                        for option in CostBasisMethod::ALL {
+                            ui.selectable_value(&mut method, option, option.name());
+                        }
+                    });
+                    if method != state.config.cost_basis_method {
+                        state.set_cost_basis_method(method);
+                    }
+                    trade_summary(ui, &state);
+                    
+                    egui::CollapsingHeader::new(format!("Trades ({})", state.trades.len()))
+                        .show(ui, |ui| {
+                            let mut remove = None;
+                            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                egui::Grid::new("trades_grid").striped(true).show(ui, |ui| {
+                                    for (index, trade) in state.trades.iter().enumerate() {
+                                        ui.label(mempool_client::format_unix_timestamp(trade.timestamp as u32));
+                                        ui.label(trade.side.name());
+                                        ui.label(format!("{:.8} BTC", trade.amount));
+                                        ui.label(format!("@ {}", trade.currency.format_price(trade.price)));
+                                        ui.label(format!("fee {}", trade.currency.format_price(trade.fee)));
+                                        if ui.small_button("Remove").clicked() {
+                                            remove = Some(index);
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                            if let Some(index) = remove {
+                                state.remove_trade(index);
+                            }
+                            
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut self.new_trade.side, TradeSide::Buy, "Buy");
+                                ui.selectable_value(&mut self.new_trade.side, TradeSide::Sell, "Sell");
+                                ui.label("Date:");
+                                ui.add(egui::TextEdit::singleline(&mut self.new_trade_date).desired_width(90.0));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Amount (BTC):");
+                                ui.add(egui::DragValue::new(&mut self.new_trade.amount).speed(0.001).range(0.0..=21_000_000.0).max_decimals(8));
+                                ui.label(format!("Price ({}):", currency.code()));
+                                ui.add(egui::DragValue::new(&mut self.new_trade.price).speed(100.0).range(0.0..=f64::MAX));
+                                ui.label("Fee:");
+                                ui.add(egui::DragValue::new(&mut self.new_trade.fee).speed(1.0).range(0.0..=f64::MAX));
+                            });
+                            
+                            let valid = self.new_trade.amount > 0.0 && self.new_trade.price > 0.0;
+                            if ui.add_enabled(valid, egui::Button::new("Add Trade")).clicked() {
+                                match chrono::NaiveDate::parse_from_str(self.new_trade_date.trim(), "%Y-%m-%d") {
+                                    Ok(date) => {
+                                        let mut trade = self.new_trade.clone();
+                                        trade.timestamp = date.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp();
+                                        trade.currency = currency;
+                                        state.add_trade(trade);
+                                        self.new_trade.amount = 0.0;
+                                        self.new_trade.fee = 0.0;
+                                        self.portfolio_message = None;
+                                    },
+                                    Err(_) => {
+                                        self.portfolio_message = Some("Enter the date as YYYY-MM-DD".to_string());
+                                    },
+                                }
+                            }
+                            
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.label("Bitstamp CSV:");
+                                ui.text_edit_singleline(&mut self.csv_path_input);
+                                if ui.add_enabled(!self.csv_path_input.trim().is_empty(), egui::Button::new("Import")).clicked() {
+                                    let result = std::fs::read_to_string(self.csv_path_input.trim())
+                                        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", self.csv_path_input.trim(), e))
+                                        .and_then(|content| portfolio::parse_bitstamp_csv(&content));
+                                    self.portfolio_message = Some(match result {
+                                        Ok(import) => {
+                                            let found = import.trades.len();
+                                            let added = state.import_trades(import.trades);
+                                            format!(
+                                                "Imported {} of {} trades ({} already recorded, {} other rows skipped)",
+                                                added, found, found - added, import.skipped
+                                            )
+                                        },
+                                        Err(e) => e.to_string(),
+                                    });
+                                }
+                            });
+                            if let Some(message) = &self.portfolio_message {
+                                ui.label(message);
+                            }
+                        });
+                    
+                    ui.separator();
+                    egui::CollapsingHeader::new("Watch-only Wallets")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            wallet_portfolio(ui, &state);
+                        });
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_portfolio = false;
+                    }
+                });
+        }
+        
+        // Dollar-cost averaging simulator
+        if self.show_dca {
+            egui::Window::new("DCA Simulator")
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    let currency = state.config.quote_currency;
+                    
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Buy ({}):", currency.code()));
+                        ui.add(egui::DragValue::new(&mut self.dca_amount).speed(10.0).range(1.0..=f64::MAX));
+                        for frequency in DcaFrequency::ALL {
+                            ui.selectable_value(&mut self.dca_frequency, frequency, frequency.name());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("From:");
+                        ui.add(egui::TextEdit::singleline(&mut self.dca_start).desired_width(90.0));
+                        ui.label("To:");
+                        ui.add(egui::TextEdit::singleline(&mut self.dca_end).desired_width(90.0));
+                        
+                        if ui.add_enabled(!state.dca_running, egui::Button::new("Simulate")).clicked() {
+                            let parse_date = |input: &str| {
+                                chrono::NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d")
+                                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+                                    .map_err(|_| format!("Invalid date {} (use YYYY-MM-DD)", input.trim()))
+                            };
+                            match (parse_date(&self.dca_start), parse_date(&self.dca_end)) {
+                                (Ok(start), Ok(end)) => {
+                                    let plan = DcaPlan {
+                                        amount: self.dca_amount,
+                                        frequency: self.dca_frequency,
+                                        start,
+                                        end,
+                                    };
+                                    state.dca_running = true;
+                                    let state_clone = self.state.clone();
+                                    std::thread::spawn(move || {
+                                        run_dca_simulation(state_clone, plan);
+                                    });
+                                },
+                                (Err(e), _) | (_, Err(e)) => state.dca_result = Some(Err(e)),
+                            }
+                        }
+                        if state.dca_running {
+                            ui.spinner();
+                        }
+                    });
+                    
+                    match &state.dca_result {
+                        Some(Ok(result)) => {
+                            ui.separator();
+                            dca_summary(ui, result);
+                        },
+                        Some(Err(error)) => {
+                            ui.colored_label(egui::Color32::from_rgb(200, 0, 0), error);
+                        },
+                        None => {},
+                    }
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_dca = false;
+                    }
+                });
+        }
+        
+        // Transaction confirmation tracker
+        if self.show_transactions {
+            egui::Window::new("Tracked Transactions")
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Txid:");
+                        ui.text_edit_singleline(&mut self.txid_input);
+                        
+                        let txid = self.txid_input.trim().to_lowercase();
+                        let valid = tx_tracker::is_valid_txid(&txid);
+                        if ui.add_enabled(valid, egui::Button::new("Track")).clicked() {
+                            if state.track_tx(&txid) {
+                                let state_clone = self.state.clone();
+                                std::thread::spawn(move || {
+                                    refresh_tracked_txs(state_clone);
+                                });
+                            }
+                            self.txid_input.clear();
+                        }
+                    });
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Notify at confirmations:");
+                        let mut target = state.config.tx_confirmation_target;
+                        if ui.add(egui::DragValue::new(&mut target).range(1..=100)).changed() {
+                            state.config.tx_confirmation_target = target;
+                            state.save_config();
+                        }
+                    });
+                    
+                    ui.separator();
+                    let backend = state.config.network_backend;
+                    if !backend.supports_address_lookups() {
+                        ui.label(format!("Transaction tracking is not available with {}", backend.name()));
+                    } else if state.tracked_txs.is_empty() {
+                        ui.label("No transactions are being tracked.");
+                    } else {
+                        let mut untrack = None;
+                        egui::Grid::new("tracked_txs_grid").striped(true).show(ui, |ui| {
+                            ui.strong("Transaction");
+                            ui.strong("Fee rate");
+                            ui.strong("Status");
+                            ui.strong("ETA");
+                            ui.end_row();
+                            
+                            for tracked in &state.tracked_txs {
+                                ui.label(address_watch::shorten_address(&tracked.txid)).on_hover_text(&tracked.txid);
+                                let (fee_rate, status, eta) = tx_status_columns(&state, tracked);
+                                ui.label(fee_rate);
+                                ui.label(status);
+                                ui.label(eta);
+                                if ui.small_button("Remove").clicked() {
+                                    untrack = Some(tracked.txid.clone());
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(txid) = untrack {
+                            state.untrack_tx(&txid);
+                        }
+                    }
+                    
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        self.show_transactions = false;
+                    }
+                });
+        }
+        
+        // Log of recently fired alerts
+        if self.show_alert_log {
+            egui::Window::new("Alert Log")
+                .collapsible(false)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    
+                    if state.alert_log.is_empty() {
+                        ui.label("No alerts have fired yet.");
+                    } else {
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            egui::Grid::new("alert_log_grid").striped(true).show(ui, |ui| {
+                                for event in state.alert_log.iter().rev() {
+                                    ui.label(time_info(event.timestamp).map(|info| info.formatted_time).unwrap_or_default());
+                                    ui.strong(&event.title);
+                                    ui.label(&event.message);
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+                    
+                    ui.horizontal(|ui| {
+                        if ui.button("Clear").clicked() {
+                            state.alert_log.clear();
+                        }
+                        if ui.button("Close").clicked() {
+                            self.show_alert_log = false;
+                        }
+                    });
+                });
+        }
+        
+        // Banners for fired alerts
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.alert_banners.is_empty() {
+                egui::TopBottomPanel::top("alert_banners").show(ctx, |ui| {
+                    let mut dismiss = None;
+                    for (index, event) in state.alert_banners.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(egui::Color32::from_rgb(255, 140, 0), format!("🔔 {}", event.title));
+                            ui.label(&event.message);
+                            if let Some(info) = time_info(event.timestamp) {
+                                ui.weak(info.formatted_time);
+                            }
+                            if ui.small_button("Dismiss").clicked() {
+                                dismiss = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = dismiss {
+                        state.alert_banners.remove(index);
+                    }
+                });
+            }
+        }
+        
+        // Per-source breakdown of the aggregated price
+        if self.show_price_details {
+            egui::Window::new("Price Sources")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let state = self.state.lock().unwrap();
+                    let currency = state.config.quote_currency;
+                    
+                    match &state.price_aggregate {
+                        Some(aggregate) => {
+                            egui::Grid::new("price_sources_grid").striped(true).show(ui, |ui| {
+                                ui.strong("Exchange");
+                                ui.strong("Price");
+                                ui.strong("Status");
+                                ui.end_row();
+                                
+                                for quote in &aggregate.quotes {
+                                    ui.label(quote.source.name());
+                                    match (quote.price, &quote.error) {
+                                        (Some(price), _) => {
+                                            ui.label(currency.format_price(price));
+                                            if quote.rejected {
+                                                ui.colored_label(egui::Color32::from_rgb(255, 140, 0), "Rejected (outlier)");
+                                            } else {
+                                                ui.colored_label(egui::Color32::from_rgb(0, 200, 0), "Used");
+                                            }
+                                        },
+                                        (None, error) => {
+                                            ui.label("-");
+                                            ui.colored_label(
+                                                egui::Color32::from_rgb(200, 0, 0),
+                                                format!("Failed: {}", error.as_deref().unwrap_or("unknown error")),
+                                            );
+                                        },
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                            
+                            if let Some(median) = aggregate.median {
+                                ui.separator();
+                                ui.label(format!(
+                                    "Median: {} | Spread: {}",
+                                    currency.format_price(median),
+                                    currency.format_price(aggregate.spread)
+                                ));
+                            }
+                        },
+                        None => {
+                            ui.label("Price aggregation is disabled.");
+                        },
+                    }
+                    
+                    if ui.button("Close").clicked() {
+                        self.show_price_details = false;
+                    }
+                });
+        }
+        
+        // Top panel for mempool info
+        egui::TopBottomPanel::top("mempool_info").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let state = self.state.lock().unwrap();
+                
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.heading("Bitcoin Network");
+                        health_badge(ui, &state.block_health);
+                        health_badge(ui, &state.fee_health);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Block Height: {}", state.block_height));
+                        ui.label("|");
+                        ui.label(format!("Block Time: {}", state.block_time));
+                        ui.label("|");
+                        ui.label(format!("Last Updated: {}", state.mempool_last_updated));
+                        
+                        // Endpoint the mempool data came from
+                        if state.config.network_backend == NetworkBackend::Mempool {
+                            if let Some(url) = &state.mempool_served_by {
+                                let latency = state.endpoint_pool.status(url)
+                                    .and_then(|status| status.latency)
+                                    .map(|latency| format!(" ({})", mempool_endpoints::format_latency(latency)))
+                                    .unwrap_or_default();
+                                ui.label("|");
+                                ui.label(format!("Served by: {}{}", state.mempool_endpoint_name(url), latency))
+                                    .on_hover_text(url);
+                            }
+                        }
+                    });
+                    
+                    // Latency or failure of every configured mempool endpoint
+                    if state.config.network_backend == NetworkBackend::Mempool {
+                        egui::CollapsingHeader::new("Mempool Endpoints").show(ui, |ui| {
+                            egui::Grid::new("mempool_endpoint_status_grid").striped(true).show(ui, |ui| {
+                                for endpoint in &state.config.mempool_endpoints {
+                                    let serving = state.mempool_served_by.as_deref() == Some(endpoint.url.as_str());
+                                    let name = if serving {
+                                        format!("● {}", endpoint.name)
+                                    } else {
+                                        endpoint.name.clone()
+                                    };
+                                    ui.label(name).on_hover_text(&endpoint.url);
+                                    endpoint_status_label(ui, state.endpoint_pool.status(&endpoint.url));
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("Fees (sat/vB):");
+                        ui.label(format!("Fastest: {}", state.fastest_fee));
+                        ui.label("|");
+                        ui.label(format!("30m: {}", state.half_hour_fee));
+                        ui.label("|");
+                        ui.label(format!("1h: {}", state.hour_fee));
+                        ui.label("|");
+                        ui.label(format!("Economy: {}", state.economy_fee));
+                    });
+                    
+                    if let Some(stats) = &state.mempool_stats {
+                        // Electrum servers only report the fee histogram, not the transaction count
+                        let count = if stats.count > 0 {
+                            format!("{} txs | ", block_tracker::format_count(stats.count))
+                        } else {
+                            String::new()
+                        };
+                        ui.label(format!(
+                            "Mempool: {}{:.2} MvB | {:.4} BTC in fees",
+                            count,
+                            stats.vsize as f64 / 1_000_000.0,
+                            stats.total_fee as f64 / 100_000_000.0
+                        ));
+                    }
+                    
+                    // Next blocks the mempool would fill
+                    if !state.projected_blocks.is_empty() {
+                        egui::CollapsingHeader::new("Projected Blocks").default_open(true).show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                for (index, block) in state.projected_blocks.iter().take(PROJECTED_BLOCK_TILES).enumerate() {
+                                    projected_block_tile(ui, index, block);
+                                }
+                            });
+                        });
+                    }
+                    
+                    // Difficulty epoch and halving countdown
+                    if state.block_height > 0 {
+                        egui::CollapsingHeader::new("Difficulty & Halving").show(ui, |ui| {
+                            difficulty_panel(ui, &state);
+                        });
+                    }
+                    
+                    // Timeline of the most recent blocks
+                    egui::CollapsingHeader::new("Recent Blocks").show(ui, |ui| {
+                        egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                            egui::Grid::new("recent_blocks_grid").striped(true).show(ui, |ui| {
+                                ui.strong("Height");
+                                ui.strong("Mined");
+                                ui.strong("Txs");
+                                ui.strong("Size");
+                                ui.strong("Interval");
+                                ui.end_row();
+                                
+                                for block in state.block_tracker.recent() {
+                                    ui.label(block.height.to_string()).on_hover_text(&block.hash);
+                                    ui.label(mempool_client::format_unix_timestamp(block.timestamp));
+                                    // Header-only backends don't know the transactions
+                                    if block.tx_count > 0 {
+                                        ui.label(block_tracker::format_count(block.tx_count));
+                                        ui.label(block_tracker::format_size(block.size));
+                                    } else {
+                                        ui.label("-");
+                                        ui.label("-");
+                                    }
+                                    ui.label(block.since_previous.map(block_tracker::format_interval).unwrap_or_else(|| "-".to_string()));
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    });
+                });
+            });
+            ui.separator();
+        });
+        
+        // Balances of the watched addresses
+        {
             let state = self.state.lock().unwrap();
+            if !state.config.watched_addresses.is_empty() {
+                egui::TopBottomPanel::bottom("watched_addresses").show(ctx, |ui| {
+                    watched_addresses_panel(ui, &state);
+                });
+            }
+        }
+        
+        egui::CentralPanel::default().show(ctx, |ui| {
+            
+            let mut state = self.state.lock().unwrap();
+            let currency = state.config.quote_currency;
             let price_text = if state.price > 0.0 {
-                // Calculate satoshis per dollar (1 BTC = 100,000,000 satoshis)
-                let sats_per_dollar = 100_000_000.0 / state.price;
-                format!("${:.2} | {:.0} sats/$", state.price, sats_per_dollar)
+                // Calculate satoshis per unit of currency (1 BTC = 100,000,000 satoshis)
+                let sats_per_unit = 100_000_000.0 / state.price;
+                // Keep some precision for currencies with a small unit value like JPY
+                let precision = if sats_per_unit < 100.0 { 2 } else { 0 };
+                format!(
+                    "{} | {:.*} sats/{}",
+                    currency.format_price(state.price),
+                    precision,
+                    sats_per_unit,
+                    currency.symbol()
+                )
             } else {
                 "Loading...".to_string()
             };
@@ -321,13 +2047,103 @@ impl eframe::App for BitcoinApp {
             // Center the price and last updated information
             ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                 ui.heading(price_text);
+                ui.horizontal(|ui| {
+                    health_badge(ui, &state.price_health);
+                    health_badge(ui, &state.history_health);
+                });
                 ui.add_space(5.0);
-                ui.label(format!("Last updated: {}", state.last_updated));
+                if let Some(aggregate) = &state.price_aggregate {
+                    ui.label(format!("Last updated: {}", state.last_updated));
+                    let spread_percent = if state.price > 0.0 { aggregate.spread / state.price * 100.0 } else { 0.0 };
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Median of {}/{} sources | Spread: {} ({:.2}%)",
+                            aggregate.accepted_count(),
+                            aggregate.quotes.len(),
+                            currency.format_price(aggregate.spread),
+                            spread_percent
+                        ));
+                        if ui.small_button("Details").clicked() {
+                            self.show_price_details = !self.show_price_details;
+                        }
+                    });
+                } else if state.price_stream_connected {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::from_rgb(0, 200, 0), "● Live");
+                        ui.label(format!("Last trade: {} via {}", state.last_updated, state.config.price_source.name()));
+                    });
+                } else {
+                    ui.label(format!("Last updated: {} via {}", state.last_updated, state.config.price_source.name()));
+                }
             });
             
             ui.add_space(10.0);
             
-            if !self.price_history.is_empty() {
+            // Chart selection
+            ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                ui.horizontal(|ui| {
+                    let mut view = state.chart_view;
+                    ui.selectable_value(&mut view, ChartView::Price, "Price");
+                    ui.selectable_value(&mut view, ChartView::Hashrate, "Hashrate & Difficulty");
+                    ui.selectable_value(&mut view, ChartView::FeeHistogram, "Mempool Fees");
+                    
+                    let mut hashrate_timeframe = state.hashrate_timeframe;
+                    if view == ChartView::Hashrate {
+                        ui.separator();
+                        for timeframe in HashrateTimeframe::ALL {
+                            ui.selectable_value(&mut hashrate_timeframe, timeframe, timeframe.description());
+                        }
+                    }
+                    
+                    if view != state.chart_view || hashrate_timeframe != state.hashrate_timeframe {
+                        state.chart_view = view;
+                        if hashrate_timeframe != state.hashrate_timeframe {
+                            state.set_hashrate_timeframe(hashrate_timeframe);
+                        }
+                        if state.wants_hashrate_refresh() {
+                            let state_clone = self.state.clone();
+                            std::thread::spawn(move || {
+                                refresh_hashrate_history(state_clone);
+                            });
+                        }
+                    }
+                });
+            });
+            
+            if state.chart_view == ChartView::Hashrate {
+                ui.add_space(10.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    let backend = state.config.network_backend;
+                    match &state.hashrate_history {
+                        _ if !backend.supports_mempool_charts() => {
+                            ui.label(format!("Hashrate history is not available with {}", backend.name()));
+                        },
+                        Some(history) => hashrate_chart(ui, history, state.hashrate_changed),
+                        None => {
+                            ui.label("Loading hashrate history...");
+                        },
+                    }
+                });
+                state.hashrate_changed = false;
+            }
+            
+            if state.chart_view == ChartView::FeeHistogram {
+                ui.add_space(10.0);
+                ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+                    match &state.mempool_stats {
+                        // Bitcoin Core has no fee histogram
+                        _ if state.config.network_backend == NetworkBackend::BitcoinCore => {
+                            ui.label(format!("Fee histogram is not available with {}", NetworkBackend::BitcoinCore.name()));
+                        },
+                        Some(stats) => fee_histogram_chart(ui, stats),
+                        None => {
+                            ui.label("Loading mempool stats...");
+                        },
+                    }
+                });
+            }
+            
+            if state.chart_view == ChartView::Price && !self.price_history.is_empty() {
                 ui.add_space(10.0);
                 
                 // Center the chart and its label
@@ -392,7 +2208,7 @@ impl eframe::App for BitcoinApp {
                             //println!("Created {} candles for chart", candles.len());
                             
                             // Create a named box plot with the candles
-                            let box_plot = BoxPlot::new("BTC/USD", candles);
+                            let box_plot = BoxPlot::new(format!("BTC/{}", currency.code()), candles);
                             
                             // Calculate the min and max y values for better scaling
                             let mut min_price = f64::MAX;
@@ -408,24 +2224,12 @@ impl eframe::App for BitcoinApp {
                             let min_y = (min_price - (price_range * 0.05)).max(0.0); // 5% padding below, but not below 0
                             let max_y = max_price + (price_range * 0.05); // 5% padding above
                             
-                            // Create a custom formatter for the x-axis to show time
-                            let time_formatter = |_name: &str, value: &egui_plot::PlotPoint| -> String {
-                                // Try to convert the timestamp back to a readable format
-                                if let Some(utc_dt) = Utc.timestamp_opt(value.x as i64, 0).single() {
-                                    // Convert UTC to local time
-                                    let local_time = Local.from_utc_datetime(&utc_dt.naive_utc());
-                                    // Format as HH:MM in local time
-                                    format!("{:02}  {:02}:{:02}", local_time.date_naive(), local_time.hour(), local_time.minute())
-                                } else {
-                                    format!("{:.1}", value.x) // Fallback
-                                }
-                            };
                             
                             // Get available width from UI
                             let available_width = ui.available_width();
                             
                             // Calculate height based on width (maintain aspect ratio)
-                            let chart_height = (available_width / 2.5).min(300.0).max(150.0);
+                            let chart_height = (available_width / 2.5).clamp(150.0, 300.0);
                             
                             // Display the plot using available width
                             // Create the plot with base settings
@@ -438,9 +2242,10 @@ impl eframe::App for BitcoinApp {
                                 .allow_drag(true)
                                 .min_size(egui::vec2(300.0, 150.0)) // Set reasonable minimum size
                                 .y_axis_min_width(0.5)   // Make y-axis more visible
-                                .y_axis_label("Price ($)")
+                                .y_axis_label(format!("Price ({})", currency.symbol()))
                                 .x_axis_label("Time (Local)")
-                                .label_formatter(time_formatter)
+                                .label_formatter(|_name, value| format_plot_time(value.x))
+                                .x_axis_formatter(plot_time_axis)
                                 .legend(Legend::default().position(Corner::RightTop));
                                 
                             // Reset the view when timeframe changes
@@ -482,7 +2287,7 @@ impl eframe::App for BitcoinApp {
                                                 [start_x, state.price],
                                                 [end_x, state.price],
                                             ];
-                                            let line = egui_plot::Line::new(format!("Current Price: ${:.2}", state.price), points)
+                                            let line = egui_plot::Line::new(format!("Current Price: {}", currency.format_price(state.price)), points)
                                             .stroke(line_stroke);
                                             
                                             plot_ui.line(line);
@@ -516,71 +2321,21 @@ fn main() -> Result<(), eframe::Error> {
             .with_resizable(true),
         ..Default::default()
     };
-    
-    // Create shared state
-    let bitcoin_state = Arc::new(Mutex::new(BitcoinState::new()));
-    
-        // Fetch historical data and initial price
-    let init_state = bitcoin_state.clone();
-    
-    thread::spawn(move || {
-        // First try to get historical data
-        if let Ok(historical_data) = BitstampClient::new().fetch_historical_prices(ChartTimeframe::Hours24) {
-            // Print debug info about the data
-            print_historical_data(&historical_data);
-            
-            // Process historical data
-            let mut history = Vec::new();
-            
-            // Convert historical data to our format
-            for point in historical_data.data.ohlc.iter() {
-                // Debug print point
-                println!("Processing point: timestamp={}, close={}", point.timestamp, point.close);
-                
-                if let (Ok(timestamp), Ok(open), Ok(high), Ok(low), Ok(close)) = (
-                    point.timestamp.parse::<i64>(),
-                    point.open.parse::<f64>(),
-                    point.high.parse::<f64>(),
-                    point.low.parse::<f64>(),
-                    point.close.parse::<f64>()
-                ) {
-                    // Debug timestamp parsing
-                    println!("  Parsed timestamp: {}", timestamp);
-                    
-                    // Create candle data
-                    let candle = CandleData {
-                        open,
-                        high,
-                        low,
-                        close
-                    };
-                    
-                    // Convert unix timestamp to ISO 8601
-                    if let Some(datetime) = Utc.timestamp_opt(timestamp, 0).single() {
-                        let rfc3339 = datetime.to_rfc3339();
-                        let formatted_time = bitstamp_client::format_unix_timestamp(&point.timestamp);
-                        println!("  Formatted time: {}", formatted_time);
-                        history.push((TimeInfo {
-                            raw_timestamp: timestamp,
-                            formatted_time,
-                            rfc3339,
-                        }, candle));
-                    } else {
-                        println!("  Invalid timestamp: {}", timestamp);
-                    }
-                } else {
-                    println!("  Failed to parse timestamp or price values");
-                }
-            }
-            
-            println!("Total processed history points: {}", history.len());
-            
-            // Store the historical data in the app state
-            if !history.is_empty() {
-                let mut state = init_state.lock().unwrap();
-                state.historical_data = history;
-                
-                // Set the current price from the latest historical data point
+    
+    // Create shared state
+    let bitcoin_state = Arc::new(Mutex::new(BitcoinState::new()));
+    
+        // Fetch historical data and initial price
+    let init_state = bitcoin_state.clone();
+    
+    thread::spawn(move || {
+        // First bring the chart history up to date (cached candles are already shown)
+        refresh_historical_data(init_state.clone());
+        
+        {
+            let mut state = init_state.lock().unwrap();
+            // Set the current price from the latest historical data point
+            if state.price == 0.0 {
                 if let Some((_, latest_candle)) = state.historical_data.last() {
                     state.price = latest_candle.close;
                     state.last_updated = get_current_timestamp();
@@ -588,7 +2343,16 @@ fn main() -> Result<(), eframe::Error> {
             }
         }
         refresh_bitcoin_price(init_state.clone());
-        refresh_mempool_data(init_state);
+        refresh_mempool_data(init_state.clone());
+        refresh_watched_addresses(init_state.clone());
+        refresh_tracked_txs(init_state.clone());
+        refresh_watched_wallets(init_state);
+    });
+
+    // Stream live trades when enabled
+    let stream_state = bitcoin_state.clone();
+    thread::spawn(move || {
+        run_price_stream(stream_state);
     });
 
     // Set up a periodic timer for price updates
@@ -596,16 +2360,63 @@ fn main() -> Result<(), eframe::Error> {
     thread::spawn(move || {
         loop {
             thread::sleep(Duration::from_secs(60)); // Update every minute
-            refresh_bitcoin_price(timer_state.clone());
+            
+            // While the stream delivers live prices only the chart history needs polling
+            let streaming = timer_state.lock().unwrap().price_stream_connected;
+            if streaming {
+                refresh_historical_data(timer_state.clone());
+            } else {
+                refresh_bitcoin_price(timer_state.clone());
+            }
         }
     });
     
+    // Push new blocks from the Electrum server when it is the network backend
+    let electrum_state = bitcoin_state.clone();
+    thread::spawn(move || {
+        run_electrum_subscription(electrum_state);
+    });
+    
+    // Stream mempool updates when enabled
+    let mempool_stream_state = bitcoin_state.clone();
+    thread::spawn(move || {
+        run_mempool_stream(mempool_stream_state);
+    });
+    
+    // Check the mempool endpoints so failing ones are skipped and recovered ones used again
+    let endpoint_check_state = bitcoin_state.clone();
+    thread::spawn(move || {
+        run_endpoint_health_checks(endpoint_check_state);
+    });
+    
     // Set up a periodic timer for mempool data updates
     let mempool_timer_state = bitcoin_state.clone();
     thread::spawn(move || {
         loop {
             thread::sleep(Duration::from_secs(120)); // Update every 2 minutes
-            refresh_mempool_data(mempool_timer_state.clone());
+            
+            // The Electrum subscription refreshes network data on every new block,
+            // and the mempool stream pushes everything but the chart data
+            let (subscribed, streaming) = {
+                let state = mempool_timer_state.lock().unwrap();
+                (state.electrum_subscribed, state.mempool_stream_connected)
+            };
+            if streaming {
+                refresh_mempool_charts(mempool_timer_state.clone());
+            } else if !subscribed {
+                refresh_mempool_data(mempool_timer_state.clone());
+            }
+            refresh_watched_addresses(mempool_timer_state.clone());
+            refresh_tracked_txs(mempool_timer_state.clone());
+        }
+    });
+
+    // Wallet scans take many requests, so rescan less often than the mempool data
+    let wallet_timer_state = bitcoin_state.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_secs(600)); // Update every 10 minutes
+            refresh_watched_wallets(wallet_timer_state.clone());
         }
     });
 
@@ -652,13 +2463,23 @@ fn main() -> Result<(), eframe::Error> {
             ]);
             
             // Create the tray icon
-            let _tray_icon = TrayIconBuilder::new()
+            let tray_icon = TrayIconBuilder::new()
                 .with_menu(Box::new(tray_menu))
                 .with_icon(icon)
                 .with_tooltip("BTC Ticker")
                 .build()
                 .unwrap();
             
+            // Keep the tooltip in sync with the price and data freshness
+            let tooltip_state = linux_state.clone();
+            gtk::glib::timeout_add_seconds_local(5, move || {
+                let tooltip = tray_tooltip(&tooltip_state.lock().unwrap());
+                if let Err(e) = tray_icon.set_tooltip(Some(tooltip)) {
+                    eprintln!("Failed to update tray tooltip: {}", e);
+                }
+                gtk::glib::ControlFlow::Continue
+            });
+            
             // Start a thread to handle menu events
             thread::spawn(move || {
                 // Use the built-in event receiver from tray-icon
@@ -678,8 +2499,7 @@ fn main() -> Result<(), eframe::Error> {
                         "timeframe-24h" => {
                             let mut state = state_for_menu_events.lock().unwrap();
                             if state.chart_timeframe != ChartTimeframe::Hours24 {
-                                state.chart_timeframe = ChartTimeframe::Hours24;
-                                state.timeframe_changed = true;
+                                state.set_chart_timeframe(ChartTimeframe::Hours24);
                                 // Drop lock before refreshing
                                 drop(state);
                                 refresh_bitcoin_price(state_for_menu_events.clone());
@@ -688,8 +2508,7 @@ fn main() -> Result<(), eframe::Error> {
                         "timeframe-week" => {
                             let mut state = state_for_menu_events.lock().unwrap();
                             if state.chart_timeframe != ChartTimeframe::Week {
-                                state.chart_timeframe = ChartTimeframe::Week;
-                                state.timeframe_changed = true;
+                                state.set_chart_timeframe(ChartTimeframe::Week);
                                 // Drop lock before refreshing
                                 drop(state);
                                 refresh_bitcoin_price(state_for_menu_events.clone());
@@ -698,8 +2517,7 @@ fn main() -> Result<(), eframe::Error> {
                         "timeframe-month" => {
                             let mut state = state_for_menu_events.lock().unwrap();
                             if state.chart_timeframe != ChartTimeframe::Month {
-                                state.chart_timeframe = ChartTimeframe::Month;
-                                state.timeframe_changed = true;
+                                state.set_chart_timeframe(ChartTimeframe::Month);
                                 // Drop lock before refreshing
                                 drop(state);
                                 refresh_bitcoin_price(state_for_menu_events.clone());
@@ -708,8 +2526,7 @@ fn main() -> Result<(), eframe::Error> {
                         "timeframe-year" => {
                             let mut state = state_for_menu_events.lock().unwrap();
                             if state.chart_timeframe != ChartTimeframe::Year {
-                                state.chart_timeframe = ChartTimeframe::Year;
-                                state.timeframe_changed = true;
+                                state.set_chart_timeframe(ChartTimeframe::Year);
                                 // Drop lock before refreshing
                                 drop(state);
                                 refresh_bitcoin_price(state_for_menu_events.clone());
@@ -802,67 +2619,1558 @@ fn load_icon(path: &std::path::Path) -> tray_icon::Icon {
         let rgba = image.into_raw();
         (rgba, width, height)
     };
-    tray_icon::Icon::from_rgba(icon_rgba, icon_width, icon_height).expect("Failed to open icon")
+    tray_icon::Icon::from_rgba(icon_rgba, icon_width, icon_height).expect("Failed to open icon")
+}
+
+// Latency of a mempool endpoint, or why it is failing
+fn endpoint_status_label(ui: &mut egui::Ui, status: Option<&EndpointStatus>) {
+    match status {
+        Some(EndpointStatus { error: Some(error), .. }) => {
+            ui.colored_label(egui::Color32::from_rgb(200, 0, 0), "failing").on_hover_text(error);
+        },
+        Some(EndpointStatus { latency: Some(latency), .. }) => {
+            ui.colored_label(egui::Color32::from_rgb(0, 150, 0), mempool_endpoints::format_latency(*latency));
+        },
+        _ => {
+            ui.weak("not checked");
+        },
+    }
+}
+
+// Colored badge showing whether a data source is fresh, stale or failing
+fn health_badge(ui: &mut egui::Ui, health: &SourceHealth) {
+    let color = match health.status() {
+        SourceStatus::Unknown => egui::Color32::GRAY,
+        SourceStatus::Fresh => egui::Color32::from_rgb(0, 200, 0),
+        SourceStatus::Stale { .. } => egui::Color32::from_rgb(255, 140, 0),
+        SourceStatus::Failing { .. } => egui::Color32::from_rgb(200, 0, 0),
+    };
+    let description = health.describe();
+    
+    // Keep the badge short and show long error messages on hover
+    let short = match description.split_once(": ") {
+        Some((status, _)) => status.to_string(),
+        None => description.clone(),
+    };
+    ui.colored_label(color, format!("● {}: {}", health.name, short))
+        .on_hover_text(description);
+}
+
+// Hover label time for chart x values (Unix timestamps), in local time
+fn format_plot_time(x: f64) -> String {
+    if let Some(utc_dt) = Utc.timestamp_opt(x as i64, 0).single() {
+        // Convert UTC to local time
+        let local_time = Local.from_utc_datetime(&utc_dt.naive_utc());
+        // Format as date and HH:MM in local time
+        format!("{:02}  {:02}:{:02}", local_time.date_naive(), local_time.hour(), local_time.minute())
+    } else {
+        format!("{:.1}", x) // Fallback
+    }
+}
+
+// Time axis labels shared by the charts, coarser as the grid spacing grows
+fn plot_time_axis(mark: GridMark, _range: &std::ops::RangeInclusive<f64>) -> String {
+    let Some(utc_dt) = Utc.timestamp_opt(mark.value as i64, 0).single() else {
+        return String::new();
+    };
+    let local_time = Local.from_utc_datetime(&utc_dt.naive_utc());
+    
+    if mark.step_size >= 30.0 * 86_400.0 {
+        local_time.format("%Y-%m").to_string()
+    } else if mark.step_size >= 86_400.0 {
+        local_time.format("%m-%d").to_string()
+    } else {
+        local_time.format("%H:%M").to_string()
+    }
+}
+
+// Hashrate with difficulty drawn as the hashrate it implies, so both share one axis
+fn hashrate_chart(ui: &mut egui::Ui, history: &MempoolHashrateHistory, reset: bool) {
+    // Expected hashes per block are difficulty * 2^32, found every 600 seconds
+    let implied_hashrate = |difficulty: f64| difficulty * 4_294_967_296.0 / 600.0 / 1e18;
+    
+    ui.label(format!(
+        "Hashrate: {:.1} EH/s | Difficulty: {:.2} T",
+        history.current_hashrate / 1e18,
+        history.current_difficulty / 1e12
+    ));
+    ui.add_space(5.0);
+    
+    let hashrate_points: Vec<[f64; 2]> = history.hashrates.iter()
+        .map(|point| [point.timestamp as f64, point.avg_hashrate / 1e18])
+        .collect();
+    
+    // Difficulty only changes at retargets, so draw it as steps
+    let mut difficulty_points: Vec<[f64; 2]> = Vec::with_capacity(history.difficulty.len() * 2);
+    for point in &history.difficulty {
+        if let Some(&[_, previous]) = difficulty_points.last() {
+            difficulty_points.push([point.time as f64, previous]);
+        }
+        difficulty_points.push([point.time as f64, implied_hashrate(point.difficulty)]);
+    }
+    if let (Some(&[_, last]), Some(end)) = (difficulty_points.last(), hashrate_points.last()) {
+        difficulty_points.push([end[0], last]);
+    }
+    
+    let available_width = ui.available_width();
+    let chart_height = (available_width / 2.5).clamp(150.0, 300.0);
+    
+    let mut plot = Plot::new("network_hashrate_history")
+        .height(chart_height)
+        .width(available_width.min(1200.0))
+        .allow_zoom(true)
+        .allow_scroll(true)
+        .allow_drag(true)
+        .min_size(egui::vec2(300.0, 150.0))
+        .y_axis_label("Hashrate (EH/s)")
+        .x_axis_label("Time (Local)")
+        .label_formatter(|name, value| {
+            if name.is_empty() {
+                format_plot_time(value.x)
+            } else {
+                format!("{}: {:.1} EH/s\n{}", name, value.y, format_plot_time(value.x))
+            }
+        })
+        .x_axis_formatter(plot_time_axis)
+        .include_y(0.0)
+        .legend(Legend::default().position(Corner::LeftTop));
+    
+    if reset {
+        plot = plot.reset();
+    }
+    
+    plot.show(ui, |plot_ui| {
+        plot_ui.line(
+            Line::new("Hashrate", hashrate_points)
+                .stroke(egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 140, 0)))
+        );
+        plot_ui.line(
+            Line::new("Difficulty (implied hashrate)", difficulty_points)
+                .stroke(egui::Stroke::new(1.5, egui::Color32::from_rgb(100, 150, 255)))
+        );
+    });
+}
+
+// Bar chart of the mempool's virtual size per fee band
+fn fee_histogram_chart(ui: &mut egui::Ui, stats: &MempoolStats) {
+    let bands = stats.fee_bands();
+    let labels: Vec<String> = bands.iter()
+        .map(|band| match band.max_fee {
+            Some(max_fee) => format!("{}-{}", band.min_fee, max_fee),
+            None => format!("{}+", band.min_fee),
+        })
+        .collect();
+    
+    let bars: Vec<Bar> = bands.iter().zip(&labels).enumerate()
+        .map(|(index, (band, label))| {
+            Bar::new(index as f64, band.vsize / 1_000_000.0)
+                .name(format!("{} sat/vB", label))
+                .fill(fee_color(band.min_fee))
+                .width(0.8)
+        })
+        .collect();
+    let chart = BarChart::new("Mempool vsize", bars)
+        .element_formatter(Box::new(|bar, _| format!("{}\n{:.3} MvB", bar.name, bar.value)));
+    
+    let available_width = ui.available_width();
+    let chart_height = (available_width / 2.5).clamp(150.0, 300.0);
+    
+    Plot::new("mempool_fee_histogram")
+        .height(chart_height)
+        .width(available_width.min(1200.0))
+        .allow_zoom(true)
+        .allow_scroll(true)
+        .allow_drag(true)
+        .min_size(egui::vec2(300.0, 150.0))
+        .y_axis_label("Virtual size (MvB)")
+        .x_axis_label("Fee rate (sat/vB)")
+        .x_axis_formatter(|mark, _| {
+            // Label whole grid marks with their fee band
+            if mark.value.fract() == 0.0 && mark.value >= 0.0 {
+                labels.get(mark.value as usize).cloned().unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .label_formatter(|_, _| String::new())
+        .include_y(0.0)
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(chart);
+        });
+}
+
+// Totals and cumulative value chart of a DCA simulation
+fn dca_summary(ui: &mut egui::Ui, result: &DcaResult) {
+    let currency = result.currency;
+    
+    egui::Grid::new("dca_summary_grid").show(ui, |ui| {
+        ui.label("Purchases:");
+        ui.label(result.purchases.to_string());
+        ui.end_row();
+        ui.label("Invested:");
+        ui.label(currency.format_price(result.total_invested));
+        ui.end_row();
+        ui.label("BTC acquired:");
+        ui.label(format!("{:.8} BTC", result.total_btc));
+        ui.end_row();
+        ui.label("Average price:");
+        ui.label(currency.format_price(result.average_price));
+        ui.end_row();
+        ui.label("Current value:");
+        let color = if result.profit >= 0.0 {
+            egui::Color32::from_rgb(0, 150, 0)
+        } else {
+            egui::Color32::from_rgb(200, 0, 0)
+        };
+        ui.colored_label(
+            color,
+            format!("{} ({:+.2}%)", currency.format_price(result.current_value), result.profit_percent),
+        );
+        ui.end_row();
+    });
+    ui.add_space(5.0);
+    
+    let invested_points: Vec<[f64; 2]> = result.series.iter()
+        .map(|point| [point.timestamp as f64, point.invested])
+        .collect();
+    let value_points: Vec<[f64; 2]> = result.series.iter()
+        .map(|point| [point.timestamp as f64, point.value])
+        .collect();
+    
+    let available_width = ui.available_width();
+    let chart_height = (available_width / 2.5).clamp(150.0, 300.0);
+    
+    Plot::new("dca_cumulative_value")
+        .height(chart_height)
+        .width(available_width.min(1200.0))
+        .allow_zoom(true)
+        .allow_scroll(true)
+        .allow_drag(true)
+        .min_size(egui::vec2(300.0, 150.0))
+        .y_axis_label(format!("Value ({})", currency.code()))
+        .x_axis_label("Time (Local)")
+        .label_formatter(move |name, value| {
+            if name.is_empty() {
+                format_plot_time(value.x)
+            } else {
+                format!("{}: {}\n{}", name, currency.format_price(value.y), format_plot_time(value.x))
+            }
+        })
+        .x_axis_formatter(plot_time_axis)
+        .include_y(0.0)
+        .legend(Legend::default().position(Corner::LeftTop))
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new("Invested", invested_points)
+                    .stroke(egui::Stroke::new(1.5, egui::Color32::from_rgb(100, 150, 255)))
+            );
+            plot_ui.line(
+                Line::new("Value", value_points)
+                    .stroke(egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 140, 0)))
+            );
+        });
+}
+
+// Holdings, cost basis and P&L of the trades in the current currency
+fn trade_summary(ui: &mut egui::Ui, state: &BitcoinState) {
+    let currency = state.config.quote_currency;
+    let trades: Vec<Trade> = state.trades.iter()
+        .filter(|trade| trade.currency == currency)
+        .cloned()
+        .collect();
+    
+    if trades.is_empty() {
+        ui.label(format!("No {} trades recorded.", currency.code()));
+    } else {
+        let summary = portfolio::summarize(&trades, state.config.cost_basis_method, state.price);
+        let pnl_color = |value: f64| if value >= 0.0 {
+            egui::Color32::from_rgb(0, 150, 0)
+        } else {
+            egui::Color32::from_rgb(200, 0, 0)
+        };
+        
+        egui::Grid::new("portfolio_summary_grid").show(ui, |ui| {
+            ui.label("Holdings:");
+            ui.label(format!("{:.8} BTC", summary.holdings));
+            ui.end_row();
+            ui.label("Cost basis:");
+            ui.label(currency.format_price(summary.cost_basis));
+            ui.end_row();
+            ui.label("Average cost:");
+            ui.label(currency.format_price(summary.average_cost));
+            ui.end_row();
+            ui.label("Market value:");
+            if state.price > 0.0 {
+                ui.label(currency.format_price(summary.market_value));
+            } else {
+                ui.label("Waiting for price...");
+            }
+            ui.end_row();
+            if state.price > 0.0 {
+                ui.label("Unrealized P&L:");
+                ui.colored_label(
+                    pnl_color(summary.unrealized_pnl),
+                    format!("{} ({:+.2}%)", currency.format_price(summary.unrealized_pnl), summary.unrealized_percent),
+                );
+                ui.end_row();
+            }
+            ui.label("Realized P&L:");
+            ui.colored_label(pnl_color(summary.realized_pnl), currency.format_price(summary.realized_pnl));
+            ui.end_row();
+        });
+        
+        if summary.unmatched_sells > 0.0 {
+            ui.colored_label(
+                egui::Color32::from_rgb(200, 120, 0),
+                format!("{:.8} BTC was sold without matching buys", summary.unmatched_sells),
+            );
+        }
+    }
+    
+    let other = state.trades.len() - trades.len();
+    if other > 0 {
+        ui.label(format!("{} trades in other currencies are not included.", other));
+    }
+}
+
+// Balances, addresses and history of each watch-only wallet plus their total
+fn wallet_portfolio(ui: &mut egui::Ui, state: &BitcoinState) {
+    let currency = state.config.quote_currency;
+    let with_fiat = |sats: i64| -> String {
+        if state.price > 0.0 {
+            format!(
+                "{} ({})",
+                address_watch::format_btc(sats),
+                currency.format_price(sats as f64 / 100_000_000.0 * state.price)
+            )
+        } else {
+            address_watch::format_btc(sats)
+        }
+    };
+    
+    if state.config.watched_wallets.is_empty() {
+        ui.label("Add a watch-only wallet under Settings > Watch-only Wallets.");
+        return;
+    }
+    let backend = state.config.network_backend;
+    if !backend.supports_address_lookups() {
+        ui.label(format!("Watch-only wallets are not available with {}", backend.name()));
+        return;
+    }
+    
+    let mut total = 0;
+    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        for wallet in &state.config.watched_wallets {
+            ui.heading(wallet.display_name());
+            match state.wallet_summaries.get(&wallet.xpub) {
+                Some(Ok(summary)) => {
+                    total += summary.confirmed + summary.unconfirmed;
+                    ui.label(format!("Type: {} | Used addresses: {}", summary.kind.name(), summary.used_addresses));
+                    ui.label(format!("Confirmed: {}", with_fiat(summary.confirmed)));
+                    if summary.unconfirmed != 0 {
+                        ui.label(format!("Unconfirmed: {}", with_fiat(summary.unconfirmed)));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Next receive address:");
+                        ui.monospace(&summary.next_receive_address);
+                        if ui.small_button("Copy").clicked() {
+                            ui.ctx().copy_text(summary.next_receive_address.clone());
+                        }
+                    });
+                    
+                    egui::CollapsingHeader::new(format!("History ({} transactions)", summary.history.len()))
+                        .id_salt(&wallet.xpub)
+                        .show(ui, |ui| {
+                            egui::Grid::new(format!("wallet_history_{}", wallet.xpub)).striped(true).show(ui, |ui| {
+                                for tx in &summary.history {
+                                    match tx.block_time {
+                                        Some(time) if tx.confirmed => ui.label(mempool_client::format_unix_timestamp(time)),
+                                        _ => ui.label("Unconfirmed"),
+                                    };
+                                    ui.label(address_watch::format_btc(tx.net_value));
+                                    ui.monospace(address_watch::shorten_address(&tx.txid)).on_hover_text(&tx.txid);
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                },
+                Some(Err(error)) => {
+                    ui.colored_label(egui::Color32::from_rgb(200, 0, 0), format!("Failed to scan wallet: {}", error));
+                },
+                None => {
+                    ui.label("Scanning addresses...");
+                },
+            }
+            ui.separator();
+        }
+    });
+    
+    ui.strong(format!("Total: {}", with_fiat(total)));
+}
+
+// Fee rate, status and ETA columns for a tracked transaction
+fn tx_status_columns(state: &BitcoinState, tracked: &TrackedTx) -> (String, String, String) {
+    let Some(progress) = state.tx_progress.get(&tracked.txid) else {
+        return ("-".to_string(), "Loading...".to_string(), "-".to_string());
+    };
+    if let Some(error) = &progress.last_error {
+        return ("-".to_string(), format!("Failing: {}", error), "-".to_string());
+    }
+    
+    let fee_rate = progress.fee_rate
+        .map(|rate| format!("{:.1} sat/vB ({} vB)", rate, progress.vsize))
+        .unwrap_or_else(|| "-".to_string());
+    
+    if progress.confirmed_height.is_some() {
+        let confirmations = tx_tracker::confirmations(state.block_height, progress.confirmed_height);
+        let target = state.config.tx_confirmation_target;
+        let eta = if confirmations >= target {
+            "Done".to_string()
+        } else {
+            format!("~{}", chain_schedule::format_countdown(tx_tracker::estimated_wait_secs((target - confirmations - 1) as usize)))
+        };
+        return (fee_rate, format!("{} confirmations", confirmations), eta);
+    }
+    
+    // Unconfirmed: place it among the projected blocks
+    match progress.fee_rate.and_then(|rate| tx_tracker::projected_block_position(rate, &state.projected_blocks)) {
+        Some(position) => (
+            fee_rate,
+            format!("Unconfirmed, projected block {}", position + 1),
+            format!("~{}", chain_schedule::format_countdown(tx_tracker::estimated_wait_secs(position))),
+        ),
+        None => (fee_rate, "Unconfirmed, below projected blocks".to_string(), "Unknown".to_string()),
+    }
+}
+
+// Confirmed and unconfirmed balances of the watched addresses in BTC and fiat
+fn watched_addresses_panel(ui: &mut egui::Ui, state: &BitcoinState) {
+    let currency = state.config.quote_currency;
+    let fiat = |sats: i64| -> String {
+        if state.price > 0.0 {
+            currency.format_price(sats as f64 / 100_000_000.0 * state.price)
+        } else {
+            "-".to_string()
+        }
+    };
+    
+    ui.add_space(5.0);
+    ui.heading("Watched Addresses");
+    let backend = state.config.network_backend;
+    if !backend.supports_address_lookups() {
+        ui.label(format!("Address balances are not available with {}", backend.name()));
+        return;
+    }
+    egui::Grid::new("watched_addresses_grid").striped(true).show(ui, |ui| {
+        ui.strong("Address");
+        ui.strong("Confirmed");
+        ui.strong("Unconfirmed");
+        ui.strong("Transactions");
+        ui.end_row();
+        
+        let mut total_confirmed = 0;
+        let mut total_unconfirmed = 0;
+        for watched in &state.config.watched_addresses {
+            ui.label(watched.display_name()).on_hover_text(&watched.address);
+            match state.address_balances.get(&watched.address) {
+                Some(balance) => {
+                    total_confirmed += balance.confirmed;
+                    total_unconfirmed += balance.unconfirmed;
+                    ui.label(format!("{} ({})", address_watch::format_btc(balance.confirmed), fiat(balance.confirmed)));
+                    ui.label(format!("{} ({})", address_watch::format_btc(balance.unconfirmed), fiat(balance.unconfirmed)));
+                    match &balance.last_error {
+                        Some(error) => {
+                            ui.colored_label(egui::Color32::from_rgb(200, 0, 0), "Failing").on_hover_text(error);
+                        },
+                        None => {
+                            ui.label(balance.tx_count.to_string());
+                        },
+                    }
+                },
+                None => {
+                    ui.label("Loading...");
+                    ui.label("-");
+                    ui.label("-");
+                },
+            }
+            ui.end_row();
+        }
+        
+        if state.config.watched_addresses.len() > 1 {
+            ui.strong("Total");
+            ui.strong(format!("{} ({})", address_watch::format_btc(total_confirmed), fiat(total_confirmed)));
+            ui.strong(format!("{} ({})", address_watch::format_btc(total_unconfirmed), fiat(total_unconfirmed)));
+            ui.end_row();
+        }
+    });
+    ui.add_space(5.0);
+}
+
+// A projected block tile with its median fee, fee range, tx count and size
+fn projected_block_tile(ui: &mut egui::Ui, index: usize, block: &MempoolProjectedBlock) {
+    egui::Frame::group(ui.style())
+        .fill(fee_color(block.median_fee).linear_multiply(0.35))
+        .show(ui, |ui| {
+            ui.set_min_width(110.0);
+            ui.vertical(|ui| {
+                // Blocks are found roughly every ten minutes
+                ui.strong(format!("~{} min", (index + 1) * 10));
+                ui.label(format!("~{:.0} sat/vB", block.median_fee));
+                if let (Some(low), Some(high)) = (block.fee_range.first(), block.fee_range.last()) {
+                    ui.small(format!("{:.0} - {:.0} sat/vB", low, high));
+                }
+                ui.small(format!("{} txs", block_tracker::format_count(block.n_tx)));
+                ui.small(format!("{:.2} MvB", block.block_v_size / 1_000_000.0));
+            });
+        });
+}
+
+// Green for cheap fee rates, through orange, to red for expensive ones
+fn fee_color(fee_rate: f64) -> egui::Color32 {
+    // Place the rate on a log scale between 1 and 200 sat/vB
+    let t = (fee_rate.max(1.0).ln() / 200f64.ln()).clamp(0.0, 1.0) as f32;
+    let green = egui::Color32::from_rgb(0, 200, 0);
+    let orange = egui::Color32::from_rgb(255, 140, 0);
+    let red = egui::Color32::from_rgb(200, 0, 0);
+    if t < 0.5 {
+        green.lerp_to_gamma(orange, t * 2.0)
+    } else {
+        orange.lerp_to_gamma(red, (t - 0.5) * 2.0)
+    }
+}
+
+// Difficulty epoch progress and halving countdown for the current block height
+fn difficulty_panel(ui: &mut egui::Ui, state: &BitcoinState) {
+    let now = Utc::now().timestamp();
+    let adjustment = state.difficulty_adjustment.as_ref();
+    // Average block time of the current epoch, falling back to the 10 minute target
+    let avg_block_secs = adjustment
+        .map(|adjustment| adjustment.time_avg as f64 / 1000.0)
+        .unwrap_or(chain_schedule::TARGET_BLOCK_SECS);
+    
+    let epoch = chain_schedule::epoch_progress(state.block_height, now, avg_block_secs);
+    // Prefer mempool's estimates, which also account for the epoch's start time
+    let (retarget_date, change_percent) = match adjustment {
+        Some(adjustment) => (adjustment.estimated_retarget_date / 1000, adjustment.difficulty_change),
+        None => (epoch.estimated_retarget, epoch.estimated_change_percent),
+    };
+    
+    ui.label(format!(
+        "Difficulty epoch: {}/{} blocks, retarget at block {} in ~{}",
+        epoch.blocks_into_epoch,
+        chain_schedule::BLOCKS_PER_EPOCH,
+        epoch.next_retarget_height,
+        chain_schedule::format_countdown(retarget_date - now)
+    ));
+    ui.add(egui::ProgressBar::new((epoch.progress_percent / 100.0) as f32).show_percentage());
+    ui.label(format!(
+        "Estimated retarget: {} ({:+.2}%)",
+        time_info(retarget_date).map(|info| info.formatted_time).unwrap_or_default(),
+        change_percent
+    ));
+    if let Some(adjustment) = adjustment {
+        ui.weak(format!(
+            "Average block time: {} | Previous retarget: {:+.2}%",
+            block_tracker::format_interval(avg_block_secs as i64),
+            adjustment.previous_retarget
+        ));
+    }
+    
+    ui.add_space(5.0);
+    let halving = chain_schedule::halving_countdown(state.block_height, now, avg_block_secs);
+    ui.label(format!(
+        "Next halving: block {} in {} blocks (~{})",
+        halving.next_halving_height,
+        block_tracker::format_count(halving.remaining_blocks),
+        chain_schedule::format_countdown(halving.estimated_date - now)
+    ));
+    ui.add(egui::ProgressBar::new((halving.progress_percent / 100.0) as f32).show_percentage());
+    ui.label(format!(
+        "Estimated date: {} | Subsidy: {} → {} BTC",
+        time_info(halving.estimated_date).map(|info| info.formatted_time).unwrap_or_default(),
+        halving.current_subsidy_sats as f64 / 100_000_000.0,
+        halving.next_subsidy_sats as f64 / 100_000_000.0
+    ));
+}
+
+// Tray tooltip with the current price and any data source that isn't fresh
+fn tray_tooltip(state: &BitcoinState) -> String {
+    let mut tooltip = if state.price > 0.0 {
+        format!("BTC Ticker - {}", state.config.quote_currency.format_price(state.price))
+    } else {
+        "BTC Ticker".to_string()
+    };
+    
+    for health in [&state.price_health, &state.block_health, &state.fee_health] {
+        if matches!(health.status(), SourceStatus::Stale { .. } | SourceStatus::Failing { .. }) {
+            tooltip.push_str(&format!("\n{}: {}", health.name, health.describe()));
+        }
+    }
+    
+    tooltip
+}
+
+// Helper function to get formatted timestamp
+fn get_current_timestamp() -> String {
+    let dt = chrono::Local::now();
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// Formatted time of the newest candle in the history
+fn latest_candle_time(history: &[(TimeInfo, CandleData)]) -> String {
+    history.last()
+        .map(|(info, _)| info.formatted_time.clone())
+        .unwrap_or_else(|| "Never".to_string())
+}
+
+// Build the chart time info for a Unix timestamp
+fn time_info(timestamp: i64) -> Option<TimeInfo> {
+    let datetime = Utc.timestamp_opt(timestamp, 0).single()?;
+    let local_time: DateTime<Local> = DateTime::from(datetime);
+    Some(TimeInfo {
+        raw_timestamp: timestamp,
+        formatted_time: local_time.format("%Y-%m-%d %H:%M").to_string(),
+        rfc3339: datetime.to_rfc3339(),
+    })
+}
+
+// Convert normalized exchange candles into the chart's history format
+fn candles_to_history(candles: &[PriceCandle]) -> Vec<(TimeInfo, CandleData)> {
+    let mut history = Vec::with_capacity(candles.len());
+    
+    // Candles with a timestamp chrono can't represent are skipped
+    for candle in candles {
+        if let Some(info) = time_info(candle.timestamp) {
+            history.push((info, CandleData {
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+            }));
+        }
+    }
+    
+    history
+}
+
+// Fold a live price into the in-progress candle of `step` seconds, opening a
+// new candle when the interval rolls over and keeping at most `max_candles`
+fn update_live_candle(history: &mut Vec<(TimeInfo, CandleData)>, timestamp: i64, price: f64, step: u32, max_candles: usize) {
+    let bucket = timestamp - timestamp.rem_euclid(step as i64);
+    
+    // Right after a timeframe switch the history still holds candles of the old
+    // step until it is re-fetched, so don't mix candle sizes
+    if let [.., (previous, _), (last, _)] = history.as_slice() {
+        if last.raw_timestamp - previous.raw_timestamp != step as i64 {
+            return;
+        }
+    }
+    
+    match history.last_mut() {
+        Some((info, candle)) if info.raw_timestamp == bucket => {
+            candle.high = candle.high.max(price);
+            candle.low = candle.low.min(price);
+            candle.close = price;
+        },
+        // Late price for a candle that has already been closed
+        Some((info, _)) if info.raw_timestamp > bucket => {},
+        _ => {
+            if let Some(info) = time_info(bucket) {
+                history.push((info, CandleData {
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                }));
+            }
+            if history.len() > max_candles {
+                let excess = history.len() - max_candles;
+                history.drain(..excess);
+            }
+        },
+    }
+}
+
+// Apply a live price (streamed trade or polled ticker) to the current candle
+fn apply_live_price(state: &mut BitcoinState, timestamp: i64, price: f64) {
+    state.price = price;
+    state.last_updated = get_current_timestamp();
+    state.new_price_fetched = true;
+    
+    let (step, limit) = state.chart_timeframe.api_params();
+    update_live_candle(&mut state.historical_data, timestamp, price, step, limit as usize);
+    
+    check_price_alerts(state, timestamp, price);
+}
+
+// Evaluate the configured price alerts and surface the ones that fired
+fn check_price_alerts(state: &mut BitcoinState, timestamp: i64, price: f64) {
+    let events = state.price_alert_evaluator.evaluate(
+        &state.config.price_alerts,
+        timestamp,
+        price,
+        state.config.alert_hysteresis_percent,
+        state.config.quote_currency,
+    );
+    
+    for event in events {
+        raise_alert(state, event);
+    }
+}
+
+// Evaluate the configured fee alerts against new fee estimates
+fn check_fee_alerts(state: &mut BitcoinState, fees: &mempool_client::MempoolFeeEstimate) {
+    let events = state.fee_alert_evaluator.evaluate(&state.config.fee_alerts, Utc::now().timestamp(), fees);
+    
+    for event in events {
+        raise_alert(state, event);
+    }
+}
+
+// Deliver a fired alert as a desktop notification, banner and alert log entry
+fn raise_alert(state: &mut BitcoinState, event: AlertEvent) {
+    println!("Alert: {} - {}", event.title, event.message);
+    notifications::notify(&event.title, &event.message);
+    state.alert_banners.push(event.clone());
+    
+    state.alert_log.push(event);
+    if state.alert_log.len() > MAX_ALERT_LOG {
+        state.alert_log.remove(0);
+    }
+}
+
+// Stream live trades while streaming is enabled. Polling in the price timer
+// takes over whenever the stream is not connected.
+fn run_price_stream(state: Arc<Mutex<BitcoinState>>) {
+    let stream = BitstampPriceStream::new();
+    
+    loop {
+        let proxy_generation = proxy::generation();
+        let currency = {
+            let state = state.lock().unwrap();
+            if state.wants_price_stream() {
+                Some(state.config.quote_currency)
+            } else {
+                None
+            }
+        };
+        
+        let Some(currency) = currency else {
+            thread::sleep(Duration::from_secs(5));
+            continue;
+        };
+        
+        stream.run(currency, |event| {
+            let mut state = state.lock().unwrap();
+            match event {
+                StreamEvent::Connected => state.price_stream_connected = true,
+                StreamEvent::Trade(trade) => {
+                    state.price_stream_connected = true;
+                    apply_live_price(&mut state, trade.timestamp, trade.price);
+                    state.price_health.record_success();
+                },
+                StreamEvent::Disconnected(reason) => {
+                    println!("Price stream unavailable, falling back to polling: {}", reason);
+                    state.price_stream_connected = false;
+                },
+                StreamEvent::Idle => {},
+            }
+            
+            // Stop streaming when it was disabled, the currency changed or the
+            // connection has to be rerouted
+            let keep_streaming = state.wants_price_stream()
+                && state.config.quote_currency == currency
+                && proxy::generation() == proxy_generation;
+            if !keep_streaming {
+                state.price_stream_connected = false;
+            }
+            keep_streaming
+        });
+    }
+}
+
+// Subscribe to the Electrum server's block headers while it is the network
+// backend. The mempool timer polls whenever the subscription is down.
+fn run_electrum_subscription(state: Arc<Mutex<BitcoinState>>) {
+    loop {
+        let server = {
+            let state = state.lock().unwrap();
+            if state.config.network_backend == NetworkBackend::Electrum {
+                Some((state.config.electrum_server.clone(), state.config.electrum_accept_invalid_certs))
+            } else {
+                None
+            }
+        };
+        
+        let Some((server, accept_invalid_certs)) = server else {
+            thread::sleep(Duration::from_secs(5));
+            continue;
+        };
+        
+        let proxy_generation = proxy::generation();
+        let client = ElectrumClient::with_url(&server, accept_invalid_certs);
+        client.subscribe_headers(|event| {
+            let mut locked = state.lock().unwrap();
+            match event {
+                HeaderEvent::Connected(height) | HeaderEvent::NewTip(height) => {
+                    println!("Electrum server reports tip at height {}", height);
+                    locked.electrum_subscribed = true;
+                    
+                    let state_clone = state.clone();
+                    std::thread::spawn(move || {
+                        refresh_mempool_data(state_clone.clone());
+                        refresh_watched_addresses(state_clone.clone());
+                        refresh_tracked_txs(state_clone);
+                    });
+                },
+                HeaderEvent::Disconnected(reason) => {
+                    println!("Electrum subscription unavailable, falling back to polling: {}", reason);
+                    locked.electrum_subscribed = false;
+                },
+                HeaderEvent::Idle => {},
+            }
+            
+            // Stop when another backend, server or proxy was configured
+            let keep_subscribed = locked.config.network_backend == NetworkBackend::Electrum
+                && locked.config.electrum_server == server
+                && locked.config.electrum_accept_invalid_certs == accept_invalid_certs
+                && proxy::generation() == proxy_generation;
+            if !keep_subscribed {
+                locked.electrum_subscribed = false;
+            }
+            keep_subscribed
+        });
+    }
+}
+
+// Store the secrets entered for a mempool endpoint in the keyring, then save
+// its settings and reload the credentials
+fn save_mempool_access(state: Arc<Mutex<BitcoinState>>, access: MempoolEndpointAccess, secret: String, headers: String) {
+    if let Err(e) = mempool_auth::store_secrets(&access, &secret, &headers) {
+        eprintln!("Failed to store mempool credentials: {}", e);
+        state.lock().unwrap().mempool_access_status = Some(Err(format!("Failed to store secrets in the keyring: {}", e)));
+        return;
+    }
+    apply_mempool_access(state, access, "Saved");
+}
+
+// Remove the stored secrets of a mempool endpoint and stop sending credentials to it
+fn forget_mempool_access(state: Arc<Mutex<BitcoinState>>, access: MempoolEndpointAccess) {
+    if let Err(e) = mempool_auth::forget_secrets(&access.url) {
+        eprintln!("Failed to remove mempool credentials: {}", e);
+        state.lock().unwrap().mempool_access_status = Some(Err(format!("Failed to remove secrets from the keyring: {}", e)));
+        return;
+    }
+    apply_mempool_access(state, access, "Stored secrets removed");
+}
+
+fn apply_mempool_access(state: Arc<Mutex<BitcoinState>>, access: MempoolEndpointAccess, note: &str) {
+    let url = access.url.clone();
+    let endpoints = {
+        let mut state = state.lock().unwrap();
+        state.set_mempool_endpoint_access(access);
+        state.config.mempool_endpoint_access.clone()
+    };
+    // Reading the keyring can block, so don't hold the state meanwhile
+    mempool_auth::configure(&endpoints);
+    
+    state.lock().unwrap().mempool_access_status = Some(match mempool_auth::check(&url) {
+        Ok(()) => Ok(note.to_string()),
+        Err(e) => Err(e.to_string()),
+    });
+    refresh_mempool_data(state);
 }
 
-// Helper function to get formatted timestamp
-fn get_current_timestamp() -> String {
-    let dt = chrono::Local::now();
-    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+// Stream blocks, fees and projected blocks from the mempool WebSocket while
+// it is enabled. The mempool timer polls whenever the stream is not connected.
+fn run_mempool_stream(state: Arc<Mutex<BitcoinState>>) {
+    loop {
+        let api_url = {
+            let state = state.lock().unwrap();
+            if state.wants_mempool_stream() {
+                Some(state.get_active_mempool_url().to_string())
+            } else {
+                None
+            }
+        };
+        
+        let Some(api_url) = api_url else {
+            thread::sleep(Duration::from_secs(5));
+            continue;
+        };
+        
+        let ws_url = match mempool_stream::websocket_url(&api_url) {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("Mempool stream unavailable: {}", e);
+                thread::sleep(Duration::from_secs(60));
+                continue;
+            }
+        };
+        
+        let access_generation = mempool_auth::generation();
+        let (headers, tls) = match mempool_auth::websocket_access(&api_url) {
+            Ok(access) => access,
+            Err(e) => {
+                eprintln!("Mempool stream unavailable: {}", e);
+                thread::sleep(Duration::from_secs(60));
+                continue;
+            }
+        };
+        
+        let proxy_generation = proxy::generation();
+        let stream = MempoolStream::with_url(&ws_url).with_access(headers, tls);
+        stream.run(|event| {
+            let mut locked = state.lock().unwrap();
+            match event {
+                MempoolEvent::Connected => {
+                    locked.mempool_stream_connected = true;
+                    locked.mempool_served_by = Some(api_url.clone());
+                },
+                MempoolEvent::Blocks(blocks) => apply_blocks(&mut locked, &blocks),
+                MempoolEvent::Fees(fees) => apply_fee_estimates(&mut locked, &fees),
+                MempoolEvent::MempoolSize { count, vsize, total_fee } => {
+                    // Keep the last polled fee histogram
+                    let fee_histogram = locked.mempool_stats.take()
+                        .map(|stats| stats.fee_histogram)
+                        .unwrap_or_default();
+                    locked.mempool_stats = Some(MempoolStats { count, vsize, total_fee, fee_histogram });
+                },
+                MempoolEvent::ProjectedBlocks(blocks) => locked.projected_blocks = blocks,
+                MempoolEvent::DifficultyAdjustment(adjustment) => locked.difficulty_adjustment = Some(adjustment),
+                MempoolEvent::Disconnected(reason) => {
+                    println!("Mempool stream unavailable, falling back to polling: {}", reason);
+                    locked.mempool_stream_connected = false;
+                },
+                MempoolEvent::Idle => {},
+            }
+            
+            // Stop when streaming was disabled, or another instance, proxy or
+            // credentials were configured
+            let keep_streaming = locked.wants_mempool_stream()
+                && locked.get_active_mempool_url() == api_url
+                && proxy::generation() == proxy_generation
+                && mempool_auth::generation() == access_generation;
+            if !keep_streaming {
+                locked.mempool_stream_connected = false;
+            }
+            keep_streaming
+        });
+        
+        // Catch up on anything missed while switching
+        refresh_mempool_data(state.clone());
+    }
 }
 
 // Helper function to refresh mempool data
 fn refresh_mempool_data(state: Arc<Mutex<BitcoinState>>) {
     println!("Refreshing mempool data...");
     
-    // Get the configured mempool URL
-    let mempool_url = {
-        let state = state.lock().unwrap();
-        state.get_active_mempool_url().to_string()
-    };
-    
-    // Update state to indicate we're updating
-    {
+    // Get the configured backend and the mempool endpoints in the order to try them
+    let (backend, endpoints) = {
         let mut state = state.lock().unwrap();
         state.mempool_updating = true;
-    }
-    
-    // Create client with the configured URL
-    let client = MempoolClient::with_url(&mempool_url);
+        (state.config.network_backend, state.endpoint_pool.failover_order(&state.config.mempool_endpoints))
+    };
     
-    // Fetch latest block info
-    match client.fetch_latest_block() {
+    // Fetch latest block info. The mempool backend fails over between the
+    // endpoints; the other backends only use the first one for mempool API requests.
+    let (mempool_url, provider, latest_block) = if backend == NetworkBackend::Mempool {
+        let (mempool_url, latest_block) = fetch_latest_block_with_failover(&state, &endpoints);
+        let provider = network_provider::create_network_provider(&state.lock().unwrap().config, &mempool_url);
+        (mempool_url, provider, latest_block)
+    } else {
+        let mempool_url = endpoints.first()
+            .map(|endpoint| endpoint.url.clone())
+            .unwrap_or_else(|| DEFAULT_MEMPOOL_API_URL.to_string());
+        let provider = network_provider::create_network_provider(&state.lock().unwrap().config, &mempool_url);
+        let latest_block = provider.fetch_latest_block();
+        (mempool_url, provider, latest_block)
+    };
+    match latest_block {
         Ok(block_info) => {
             println!("Updated block height: {}", block_info.height);
-            let mut state = state.lock().unwrap();
-            state.block_height = block_info.height;
-            state.block_time = mempool_client::format_unix_timestamp(block_info.timestamp);
+            let (known_tip, limit) = {
+                let state = state.lock().unwrap();
+                (state.block_tracker.tip_height(), state.config.recent_blocks_count)
+            };
+            let blocks = fetch_new_blocks(provider.as_ref(), block_info, known_tip, limit);
+            apply_blocks(&mut state.lock().unwrap(), &blocks);
         },
         Err(e) => {
             eprintln!("Failed to fetch block info: {}", e);
+            state.lock().unwrap().block_health.record_failure(e);
         }
     }
     
     // Fetch fee estimates
-    match client.fetch_fee_estimates() {
+    match provider.fetch_fee_estimates() {
         Ok(fees) => {
             println!("Updated fee estimates: fastest={} sat/vB", fees.fastest_fee);
             let mut state = state.lock().unwrap();
-            state.fastest_fee = fees.fastest_fee;
-            state.half_hour_fee = fees.half_hour_fee;
-            state.hour_fee = fees.hour_fee;
-            state.economy_fee = fees.economy_fee;
-            state.mempool_last_updated = get_current_timestamp();
             state.mempool_updating = false;
+            apply_fee_estimates(&mut state, &fees);
         },
         Err(e) => {
             eprintln!("Failed to fetch fee estimates: {}", e);
             let mut state = state.lock().unwrap();
             state.mempool_updating = false;
+            // Fail over to the next endpoint on the next refresh
+            if backend == NetworkBackend::Mempool {
+                state.endpoint_pool.record_failure(&mempool_url, &e);
+            }
+            state.fee_health.record_failure(e);
+        }
+    }
+    
+    // Fetch the mempool size and fee histogram
+    match provider.fetch_mempool_stats() {
+        Ok(stats) => {
+            println!("Updated mempool stats: {} transactions", stats.count);
+            state.lock().unwrap().mempool_stats = Some(stats);
+        },
+        Err(e) => {
+            eprintln!("Failed to fetch mempool stats: {}", e);
+            if backend == NetworkBackend::Mempool {
+                state.lock().unwrap().endpoint_pool.record_failure(&mempool_url, &e);
+            }
+        }
+    }
+    
+    // The rest is only served by the mempool API, which a node operator
+    // may not want to query
+    if backend != NetworkBackend::Mempool {
+        return;
+    }
+    let client = MempoolClient::with_url(&mempool_url);
+    
+    // Fetch the difficulty epoch progress
+    match client.fetch_difficulty_adjustment() {
+        Ok(adjustment) => {
+            println!("Updated difficulty adjustment: {:.2}% estimated change", adjustment.difficulty_change);
+            state.lock().unwrap().difficulty_adjustment = Some(adjustment);
+        },
+        Err(e) => {
+            eprintln!("Failed to fetch difficulty adjustment: {}", e);
+            state.lock().unwrap().endpoint_pool.record_failure(&mempool_url, &e);
+        }
+    }
+    
+    // Fetch the projected mempool blocks
+    match client.fetch_projected_blocks() {
+        Ok(blocks) => {
+            println!("Updated projected blocks: {} blocks", blocks.len());
+            state.lock().unwrap().projected_blocks = blocks;
+        },
+        Err(e) => {
+            eprintln!("Failed to fetch projected blocks: {}", e);
+            state.lock().unwrap().endpoint_pool.record_failure(&mempool_url, &e);
+        }
+    }
+    
+    // Keep the hashrate chart current while it is shown
+    let wants_hashrate = state.lock().unwrap().wants_hashrate_refresh();
+    if wants_hashrate {
+        refresh_hashrate_history(state);
+    }
+}
+
+// Ask the mempool endpoints in turn for the latest block until one answers,
+// marking those that fail. Returns the URL of the endpoint to use for the
+// rest of the refresh along with the block.
+fn fetch_latest_block_with_failover(state: &Arc<Mutex<BitcoinState>>, endpoints: &[MempoolEndpoint]) -> (String, Result<MempoolBlockInfo>) {
+    let mut last_error = anyhow::anyhow!("No mempool endpoints configured");
+    for endpoint in endpoints {
+        match MempoolClient::with_url(&endpoint.url).fetch_latest_block() {
+            Ok(block) => {
+                let mut state = state.lock().unwrap();
+                state.endpoint_pool.record_success(&endpoint.url);
+                state.mempool_served_by = Some(endpoint.url.clone());
+                return (endpoint.url.clone(), Ok(block));
+            },
+            Err(e) => {
+                eprintln!("Mempool endpoint {} failed, trying the next one: {}", endpoint.name, e);
+                state.lock().unwrap().endpoint_pool.record_failure(&endpoint.url, &e);
+                last_error = e;
+            }
+        }
+    }
+    
+    // Nothing answered; stay with the first endpoint for the remaining requests
+    state.lock().unwrap().mempool_served_by = None;
+    let mempool_url = endpoints.first()
+        .map(|endpoint| endpoint.url.clone())
+        .unwrap_or_else(|| DEFAULT_MEMPOOL_API_URL.to_string());
+    (mempool_url, Err(last_error))
+}
+
+// Measure the latency of every mempool endpoint, marking the ones that don't answer
+fn check_mempool_endpoints(state: &Arc<Mutex<BitcoinState>>) {
+    let (backend, endpoints) = {
+        let state = state.lock().unwrap();
+        (state.config.network_backend, state.config.mempool_endpoints.clone())
+    };
+    mempool_endpoints::check_all(backend, &endpoints, |endpoint, result| {
+        let mut state = state.lock().unwrap();
+        match result {
+            Ok(latency) => state.endpoint_pool.record_latency(&endpoint.url, latency),
+            Err(e) => {
+                eprintln!("Mempool endpoint {} failed its health check: {}", endpoint.name, e);
+                state.endpoint_pool.record_failure(&endpoint.url, e);
+            }
+        }
+    });
+}
+
+// Check the mempool endpoints periodically while the mempool API is the
+// backend. When that changes which endpoint is preferred (e.g. the first one
+// is back up), refresh from it right away.
+fn run_endpoint_health_checks(state: Arc<Mutex<BitcoinState>>) {
+    loop {
+        let (backend, preferred) = {
+            let state = state.lock().unwrap();
+            (state.config.network_backend, state.get_active_mempool_url().to_string())
+        };
+        if backend != NetworkBackend::Mempool {
+            thread::sleep(HEALTH_CHECK_INTERVAL);
+            continue;
+        }
+        check_mempool_endpoints(&state);
+        
+        let switched = state.lock().unwrap().get_active_mempool_url() != preferred;
+        if switched {
+            println!("Preferred mempool endpoint changed, refreshing");
+            refresh_mempool_data(state.clone());
+        }
+        thread::sleep(HEALTH_CHECK_INTERVAL);
+    }
+}
+
+// Show the newest of the given blocks as the tip and notify about blocks
+// found since the last update
+fn apply_blocks(state: &mut BitcoinState, blocks: &[MempoolBlockInfo]) {
+    if let Some(tip) = blocks.iter().max_by_key(|block| block.height) {
+        state.block_height = tip.height;
+        state.block_time = mempool_client::format_unix_timestamp(tip.timestamp);
+    }
+    state.block_health.record_success();
+    
+    let new_blocks = state.block_tracker.record(blocks);
+    for block in new_blocks {
+        println!("New block {}: {}", block.height, block.summary());
+        if state.config.block_notifications_enabled {
+            notifications::notify(&format!("New block {}", block.height), &block.summary());
+        }
+    }
+}
+
+fn apply_fee_estimates(state: &mut BitcoinState, fees: &mempool_client::MempoolFeeEstimate) {
+    state.fastest_fee = fees.fastest_fee;
+    state.half_hour_fee = fees.half_hour_fee;
+    state.hour_fee = fees.hour_fee;
+    state.economy_fee = fees.economy_fee;
+    state.mempool_last_updated = get_current_timestamp();
+    state.fee_health.record_success();
+    check_fee_alerts(state, fees);
+}
+
+// Refresh the chart data the mempool WebSocket doesn't carry: the fee
+// histogram and, while it is shown, the hashrate history
+fn refresh_mempool_charts(state: Arc<Mutex<BitcoinState>>) {
+    let (backend, mempool_url) = {
+        let state = state.lock().unwrap();
+        (state.config.network_backend, state.get_active_mempool_url().to_string())
+    };
+    if !backend.supports_mempool_charts() {
+        return;
+    }
+    
+    let client = MempoolClient::with_url(&mempool_url);
+    match client.fetch_mempool_stats() {
+        Ok(stats) => {
+            println!("Updated mempool fee histogram: {} bands", stats.fee_histogram.len());
+            state.lock().unwrap().mempool_stats = Some(stats);
+        },
+        Err(e) => {
+            eprintln!("Failed to fetch mempool stats: {}", e);
+            state.lock().unwrap().endpoint_pool.record_failure(&mempool_url, &e);
+        }
+    }
+    
+    let wants_hashrate = state.lock().unwrap().wants_hashrate_refresh();
+    if wants_hashrate {
+        refresh_hashrate_history(state);
+    }
+}
+
+// Check the Bitcoin Core RPC connection and report the node's chain and sync state
+fn check_bitcoin_node(state: Arc<Mutex<BitcoinState>>) {
+    let client = {
+        let state = state.lock().unwrap();
+        BitcoinRpcClient::with_url(&state.config.bitcoin_rpc_url, network_provider::rpc_auth(&state.config))
+    };
+    
+    let status = client.fetch_blockchain_info().map(|info| {
+        let sync = if info.initialblockdownload {
+            format!("syncing, {:.2}%", info.verificationprogress * 100.0)
+        } else {
+            "synced".to_string()
+        };
+        format!("Connected to {} chain at height {} ({})", info.chain, info.blocks, sync)
+    });
+    if let Err(e) = &status {
+        eprintln!("Bitcoin Core connection test failed: {}", e);
+    }
+    
+    state.lock().unwrap().node_status = Some(status.map_err(|e| e.to_string()));
+}
+
+// Fetch the hashrate and difficulty history for the selected period
+fn refresh_hashrate_history(state: Arc<Mutex<BitcoinState>>) {
+    let (backend, mempool_url, timeframe) = {
+        let state = state.lock().unwrap();
+        (state.config.network_backend, state.get_active_mempool_url().to_string(), state.hashrate_timeframe)
+    };
+    if !backend.supports_mempool_charts() {
+        return;
+    }
+    
+    let client = MempoolClient::with_url(&mempool_url);
+    match client.fetch_hashrate_history(timeframe) {
+        Ok(history) => {
+            println!("Updated hashrate history: {} points", history.hashrates.len());
+            let mut state = state.lock().unwrap();
+            
+            // Drop the result if another period was selected while we were fetching
+            if state.hashrate_timeframe == timeframe {
+                // Fit the chart to the data the first time a period is shown
+                state.hashrate_changed = state.hashrate_history.is_none();
+                state.hashrate_history = Some(history);
+                state.hashrate_fetched_at = Utc::now().timestamp();
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to fetch hashrate history: {}", e);
+            state.lock().unwrap().endpoint_pool.record_failure(&mempool_url, &e);
+        }
+    }
+}
+
+// Update the balances of the watched addresses and alert on new transactions
+fn refresh_watched_addresses(state: Arc<Mutex<BitcoinState>>) {
+    let (backend, mempool_url, addresses, electrum) = {
+        let state = state.lock().unwrap();
+        (
+            state.config.network_backend,
+            state.get_active_mempool_url().to_string(),
+            state.config.watched_addresses.clone(),
+            electrum_address_client(&state.config),
+        )
+    };
+    if addresses.is_empty() || !backend.supports_address_lookups() {
+        return;
+    }
+    
+    // Electrum servers index addresses themselves
+    if let Some(client) = electrum {
+        refresh_electrum_addresses(state, &client, addresses);
+        return;
+    }
+    
+    let client = MempoolClient::with_url(&mempool_url);
+    for watched in addresses {
+        let result = client.fetch_address(&watched.address)
+            .and_then(|info| Ok((info, client.fetch_address_txs(&watched.address)?)));
+        
+        let mut state = state.lock().unwrap();
+        // Skip addresses removed while we were fetching
+        if !state.config.watched_addresses.contains(&watched) {
+            continue;
+        }
+        
+        match result {
+            Ok((info, txs)) => {
+                state.address_balances.insert(watched.address.clone(), AddressBalance {
+                    confirmed: info.confirmed_balance(),
+                    unconfirmed: info.unconfirmed_balance(),
+                    tx_count: info.chain_stats.tx_count + info.mempool_stats.tx_count,
+                    last_error: None,
+                });
+                
+                let events: Vec<AlertEvent> = state.address_watcher.new_transactions(&watched.address, &txs)
+                    .into_iter()
+                    .map(|tx| address_tx_event(&state, &watched, tx))
+                    .collect();
+                for event in events {
+                    raise_alert(&mut state, event);
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to refresh watched address {}: {}", watched.address, e);
+                state.address_balances.entry(watched.address.clone()).or_default().last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// Update the watched addresses from an Electrum server. Its history only has
+// txids, so alerts report the balance change instead of each transaction's value.
+fn refresh_electrum_addresses(state: Arc<Mutex<BitcoinState>>, client: &ElectrumClient, addresses: Vec<WatchedAddress>) {
+    for watched in addresses {
+        let result = client.fetch_address_balance(&watched.address)
+            .and_then(|balance| Ok((balance, client.fetch_address_history(&watched.address)?)));
+        
+        let mut state = state.lock().unwrap();
+        // Skip addresses removed while we were fetching
+        if !state.config.watched_addresses.contains(&watched) {
+            continue;
+        }
+        
+        match result {
+            Ok((balance, history)) => {
+                let previous_total = state.address_balances.get(&watched.address)
+                    .map(|previous| previous.confirmed + previous.unconfirmed)
+                    .unwrap_or(0);
+                state.address_balances.insert(watched.address.clone(), AddressBalance {
+                    confirmed: balance.confirmed,
+                    unconfirmed: balance.unconfirmed,
+                    tx_count: history.len() as u32,
+                    last_error: None,
+                });
+                
+                let txids: Vec<&str> = history.iter().map(|tx| tx.tx_hash.as_str()).collect();
+                let new_txids = state.address_watcher.new_txids(&watched.address, &txids);
+                if !new_txids.is_empty() {
+                    let change = balance.confirmed + balance.unconfirmed - previous_total;
+                    let title = if change >= 0 {
+                        format!("Incoming payment to {}", watched.display_name())
+                    } else {
+                        format!("Outgoing payment from {}", watched.display_name())
+                    };
+                    let mut message = format!("{} in {} new transaction(s)", address_watch::format_btc(change), new_txids.len());
+                    if state.price > 0.0 {
+                        let fiat = change.unsigned_abs() as f64 / 100_000_000.0 * state.price;
+                        message.push_str(&format!(" ({})", state.config.quote_currency.format_price(fiat)));
+                    }
+                    
+                    raise_alert(&mut state, AlertEvent {
+                        title,
+                        message,
+                        timestamp: Utc::now().timestamp(),
+                    });
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to refresh watched address {}: {}", watched.address, e);
+                state.address_balances.entry(watched.address.clone()).or_default().last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// Alert describing a new transaction touching a watched address
+fn address_tx_event(state: &BitcoinState, watched: &WatchedAddress, tx: &mempool_client::MempoolTx) -> AlertEvent {
+    let value = tx.net_value_for(&watched.address);
+    let title = if value >= 0 {
+        format!("Incoming payment to {}", watched.display_name())
+    } else {
+        format!("Outgoing payment from {}", watched.display_name())
+    };
+    
+    let mut message = address_watch::format_btc(value);
+    if state.price > 0.0 {
+        let fiat = value.unsigned_abs() as f64 / 100_000_000.0 * state.price;
+        message.push_str(&format!(" ({})", state.config.quote_currency.format_price(fiat)));
+    }
+    match tx.status.block_height {
+        Some(height) if tx.status.confirmed => message.push_str(&format!(", confirmed in block {}", height)),
+        _ => message.push_str(", unconfirmed"),
+    }
+    
+    AlertEvent {
+        title,
+        message,
+        timestamp: Utc::now().timestamp(),
+    }
+}
+
+// Load the daily history for a DCA plan and replay it
+fn run_dca_simulation(state: Arc<Mutex<BitcoinState>>, plan: DcaPlan) {
+    let (currency, price) = {
+        let state = state.lock().unwrap();
+        (state.config.quote_currency, state.price)
+    };
+    
+    let result = dca::load_daily_history(currency, plan.start, plan.end)
+        .and_then(|candles| dca::simulate(&plan, &candles, currency, price));
+    if let Err(e) = &result {
+        eprintln!("DCA simulation failed: {}", e);
+    }
+    
+    let mut state = state.lock().unwrap();
+    state.dca_result = Some(result.map_err(|e| e.to_string()));
+    state.dca_running = false;
+}
+
+// Client for address and transaction lookups when the Electrum backend is selected
+fn electrum_address_client(config: &AppConfig) -> Option<ElectrumClient> {
+    (config.network_backend == NetworkBackend::Electrum)
+        .then(|| ElectrumClient::with_url(&config.electrum_server, config.electrum_accept_invalid_certs))
+}
+
+// Rescan the watch-only wallets
+fn refresh_watched_wallets(state: Arc<Mutex<BitcoinState>>) {
+    let (backend, mempool_url, wallets, electrum) = {
+        let state = state.lock().unwrap();
+        (
+            state.config.network_backend,
+            state.get_active_mempool_url().to_string(),
+            state.config.watched_wallets.clone(),
+            electrum_address_client(&state.config),
+        )
+    };
+    if !backend.supports_address_lookups() {
+        return;
+    }
+    
+    let index: Box<dyn AddressIndex> = match electrum {
+        Some(client) => Box::new(client),
+        None => Box::new(MempoolClient::with_url(&mempool_url)),
+    };
+    for wallet in wallets {
+        let result = watch_wallet::scan_wallet(index.as_ref(), &wallet);
+        if let Err(e) = &result {
+            eprintln!("Failed to scan wallet {}: {}", wallet.display_name(), e);
+        }
+        
+        let mut state = state.lock().unwrap();
+        // Skip wallets removed while we were scanning
+        if state.config.watched_wallets.contains(&wallet) {
+            state.wallet_summaries.insert(wallet.xpub.clone(), result.map_err(|e| e.to_string()));
+        }
+    }
+}
+
+// Poll the tracked transactions and notify on their first confirmation and the target depth
+fn refresh_tracked_txs(state: Arc<Mutex<BitcoinState>>) {
+    let (backend, mempool_url, tracked, electrum) = {
+        let state = state.lock().unwrap();
+        let tracked: Vec<(String, bool)> = state.tracked_txs.iter()
+            .map(|tx| (tx.txid.clone(), state.tx_progress.get(&tx.txid).is_some_and(|p| p.fee_rate.is_some())))
+            .collect();
+        (state.config.network_backend, state.get_active_mempool_url().to_string(), tracked, electrum_address_client(&state.config))
+    };
+    if tracked.is_empty() || !backend.supports_address_lookups() {
+        return;
+    }
+    
+    let client = MempoolClient::with_url(&mempool_url);
+    for (txid, fee_known) in tracked {
+        let result = match &electrum {
+            // Electrum reports the fee only while the transaction is unconfirmed
+            Some(electrum) => electrum.fetch_tx_status(&txid).map(|status| {
+                let fee = status.fee.map(|fee| (fee as f64 / status.vsize.max(1) as f64, status.vsize));
+                let status = MempoolTxStatus {
+                    confirmed: status.confirmed_height.is_some(),
+                    block_height: status.confirmed_height,
+                    block_hash: None,
+                    block_time: None,
+                };
+                (fee, status)
+            }),
+            // The full transaction is only needed once for its fee rate
+            None if fee_known => client.fetch_tx_status(&txid).map(|status| (None, status)),
+            None => client.fetch_tx(&txid).map(|tx| (Some((tx.fee_rate(), tx.weight.div_ceil(4))), tx.status)),
+        };
+        
+        let mut state = state.lock().unwrap();
+        match result {
+            Ok((fee, status)) => {
+                let progress = state.tx_progress.entry(txid.clone()).or_default();
+                if let Some((fee_rate, vsize)) = fee {
+                    progress.fee_rate = Some(fee_rate);
+                    progress.vsize = vsize;
+                }
+                progress.confirmed_height = if status.confirmed { status.block_height } else { None };
+                progress.last_error = None;
+                let confirmed_height = progress.confirmed_height;
+                
+                let confirmations = tx_tracker::confirmations(state.block_height, confirmed_height);
+                let target = state.config.tx_confirmation_target;
+                let mut events = Vec::new();
+                let mut changed = false;
+                if let Some(tracked) = state.tracked_txs.iter_mut().find(|tx| tx.txid == txid) {
+                    if confirmations >= 1 && !tracked.notified_confirmed {
+                        tracked.notified_confirmed = true;
+                        changed = true;
+                        events.push(AlertEvent {
+                            title: "Transaction confirmed".to_string(),
+                            message: format!(
+                                "{} confirmed in block {}",
+                                address_watch::shorten_address(&txid),
+                                confirmed_height.unwrap_or_default()
+                            ),
+                            timestamp: Utc::now().timestamp(),
+                        });
+                    }
+                    if confirmations >= target && !tracked.notified_target {
+                        tracked.notified_target = true;
+                        changed = true;
+                        events.push(AlertEvent {
+                            title: format!("Transaction reached {} confirmations", target),
+                            message: format!("{} has {} confirmations", address_watch::shorten_address(&txid), confirmations),
+                            timestamp: Utc::now().timestamp(),
+                        });
+                    }
+                }
+                
+                if changed {
+                    state.save_tracked_txs();
+                }
+                for event in events {
+                    raise_alert(&mut state, event);
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to refresh tracked transaction {}: {}", txid, e);
+                state.tx_progress.entry(txid).or_default().last_error = Some(e.to_string());
+            }
         }
     }
 }
 
+// Collect the tip and any blocks found since `known_tip` (at most `limit`),
+// newest first. Without a known tip the last `limit` blocks are fetched.
+fn fetch_new_blocks(client: &dyn NetworkProvider, tip: MempoolBlockInfo, known_tip: Option<u32>, limit: usize) -> Vec<MempoolBlockInfo> {
+    let oldest_wanted = match known_tip {
+        Some(known) if known >= tip.height => return vec![tip],
+        Some(known) => known + 1,
+        None => 0,
+    }.max(tip.height.saturating_sub(limit.saturating_sub(1) as u32));
+    
+    let mut next_height = tip.height;
+    let mut blocks = vec![tip];
+    
+    // Page backwards through the blocks endpoint until we reach the known tip
+    while next_height > oldest_wanted {
+        let page = match client.fetch_blocks(next_height - 1) {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("Failed to fetch recent blocks: {}", e);
+                break;
+            }
+        };
+        let Some(oldest) = page.iter().map(|block| block.height).min() else {
+            break;
+        };
+        
+        blocks.extend(page.into_iter().filter(|block| block.height >= oldest_wanted));
+        next_height = oldest;
+    }
+    
+    blocks
+}
+
 // Helper function to refresh Bitcoin price
 fn refresh_bitcoin_price(state: Arc<Mutex<BitcoinState>>) {
     println!("Refreshing Bitcoin price and historical data...");
@@ -872,35 +4180,58 @@ fn refresh_bitcoin_price(state: Arc<Mutex<BitcoinState>>) {
         state.updating = true;
     }
     
-    // Create a reusable API client
-    let client = BitstampClient::new();
+    // Create a reusable API client for the configured exchange
+    let (source, currency, aggregation_sources, max_deviation) = {
+        let state = state.lock().unwrap();
+        let aggregation_sources = if state.config.price_aggregation_enabled {
+            Some(state.config.aggregation_sources.clone())
+        } else {
+            None
+        };
+        (
+            state.config.price_source,
+            state.config.quote_currency,
+            aggregation_sources,
+            state.config.max_price_deviation_percent,
+        )
+    };
+    let client = source.create_provider();
+    
+    // First fetch the current price, either from the selected exchange or as
+    // the median across all configured exchanges
+    let price_result = match aggregation_sources {
+        Some(sources) => {
+            let quotes = price_aggregator::fetch_quotes(&sources, currency);
+            let aggregate = price_aggregator::aggregate(quotes, max_deviation);
+            let median = aggregate.median;
+            state.lock().unwrap().price_aggregate = Some(aggregate);
+            median.ok_or_else(|| anyhow::anyhow!("No price source returned a usable price"))
+        },
+        None => client.fetch_current_price(currency),
+    };
     
-    // First fetch the current price
-    match client.fetch_current_price() {
+    match price_result {
         Ok(price) => {
-            println!("Updated BTC price: ${:.2}", price);
+            println!("Updated BTC price: {}", currency.format_price(price));
             let mut state = state.lock().unwrap();
             
-            // Set flag if price changed
-            if state.price != price {
-                state.new_price_fetched = true;
-            }
-            
-            state.price = price;
-            state.last_updated = get_current_timestamp();
+            // Update the in-progress candle with the latest spot price
+            apply_live_price(&mut state, Utc::now().timestamp(), price);
             state.updating = false;
+            state.price_health.record_success();
         },
         Err(e) => {
             eprintln!("Failed to fetch BTC price: {}", e);
             let mut state = state.lock().unwrap();
             state.updating = false;
+            state.price_health.record_failure(e);
             
-            // If we have historical data, we can use the latest price as a fallback
-            if !state.historical_data.is_empty() {
+            // Without any price yet, show the latest close; the failing badge makes its age explicit
+            if state.price == 0.0 {
                 if let Some((_, latest_candle)) = state.historical_data.last() {
-                    println!("Using last historical price as fallback: ${:.2}", latest_candle.close);
+                    println!("Using last historical price as fallback: {}", currency.format_price(latest_candle.close));
                     state.price = latest_candle.close;
-                    state.last_updated = format!("{}* (fallback)", get_current_timestamp());
+                    state.last_updated = latest_candle_time(&state.historical_data);
                 }
             }
             
@@ -909,51 +4240,58 @@ fn refresh_bitcoin_price(state: Arc<Mutex<BitcoinState>>) {
     }
     
     // Then fetch historical data to update the chart
-    let timeframe;
-    {
-        // Temporarily lock state to get current timeframe
+    refresh_historical_data(state);
+}
+
+// Helper function to refresh the chart candles from the configured exchange
+fn refresh_historical_data(state: Arc<Mutex<BitcoinState>>) {
+    let (source, currency, timeframe) = {
+        // Temporarily lock state to get current settings
         let locked_state = state.lock().unwrap();
-        timeframe = locked_state.chart_timeframe;
-    }
+        (locked_state.config.price_source, locked_state.config.quote_currency, locked_state.chart_timeframe)
+    };
+    let (step, limit) = timeframe.api_params();
+    let client = source.create_provider();
+    let store = CandleStore::new();
     
-    match BitstampClient::new().fetch_historical_prices(timeframe) {
-        Ok(historical_data) => {
-            let mut history = Vec::new();
-            
-            // Convert historical data to our format
-            for point in historical_data.data.ohlc.iter() {
-                // Parse the timestamp
-                if let Ok(timestamp) = point.timestamp.parse::<i64>() {
-                    if let Some(datetime) = Utc.timestamp_opt(timestamp, 0).single() {
-                        // Create candle data structure using values from BitstampOHLC
-                        let candle = CandleData {
-                            open: point.open.parse::<f64>().unwrap_or(0.0),
-                            high: point.high.parse::<f64>().unwrap_or(0.0),
-                            low: point.low.parse::<f64>().unwrap_or(0.0),
-                            close: point.close.parse::<f64>().unwrap_or(0.0)
-                        };
-                        
-                        // Create human-readable time for debugging
-                        let human_time: String = bitstamp_client::format_unix_timestamp(&point.timestamp);
-                        
-                        history.push((TimeInfo {
-                            raw_timestamp: timestamp,
-                            formatted_time: human_time,
-                            rfc3339: datetime.to_rfc3339(),
-                        }, candle));
-                    }
+    // Only fetch candles newer than the last stored one when the cache covers the timeframe
+    let window_start = Utc::now().timestamp() - step as i64 * limit as i64;
+    let last_stored = store.load(source, currency, step).last().map(|candle| candle.timestamp);
+    let fetched = match last_stored {
+        Some(last) if last >= window_start => client.fetch_candles_since(timeframe, currency, last),
+        _ => client.fetch_historical_prices(timeframe, currency),
+    };
+    
+    match fetched {
+        Ok(candles) => {
+            let candles = match store.merge(source, currency, step, &candles) {
+                Ok(all_candles) => all_candles,
+                Err(e) => {
+                    eprintln!("Failed to update candle cache: {}", e);
+                    candles
                 }
-            }
+            };
+            let skip = candles.len().saturating_sub(limit as usize);
+            let history = candles_to_history(&candles[skip..]);
             
             // Update historical data in state
             if !history.is_empty() {
                 let mut state = state.lock().unwrap();
-                state.historical_data = history;
-                state.new_price_fetched = true; // Force chart update
+                
+                // Drop the result if the selection changed while we were fetching
+                if state.chart_timeframe == timeframe
+                    && state.config.price_source == source
+                    && state.config.quote_currency == currency
+                {
+                    state.historical_data = history;
+                    state.new_price_fetched = true; // Force chart update
+                    state.history_health.record_success();
+                }
             }
         },
         Err(e) => {
-            eprintln!("Failed to fetch historical data: {}", e);
+            eprintln!("Failed to fetch historical data from {}: {}", client.name(), e);
+            state.lock().unwrap().history_health.record_failure(e);
         }
     }
 }
@@ -0,0 +1,530 @@
+use std::fs;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hyper_util::client::legacy::connect::Connection;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde::{Serialize, Deserialize};
+
+use crate::mempool_client::MempoolClient;
+use crate::secret_store;
+use crate::transport::{self, TlsOptions};
+
+// How requests to a mempool endpoint authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolAuthMethod {
+    #[default]
+    None,
+    // HTTP basic auth with a username and a password from the keyring
+    Basic,
+    // Authorization: Bearer with a token from the keyring
+    Bearer,
+}
+
+impl MempoolAuthMethod {
+    pub const ALL: [MempoolAuthMethod; 3] = [
+        MempoolAuthMethod::None,
+        MempoolAuthMethod::Basic,
+        MempoolAuthMethod::Bearer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MempoolAuthMethod::None => "None",
+            MempoolAuthMethod::Basic => "Basic auth",
+            MempoolAuthMethod::Bearer => "Bearer token",
+        }
+    }
+}
+
+// Credentials and TLS settings for one mempool API endpoint. Passwords, tokens
+// and custom headers are kept in the OS keyring, never in config.json.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MempoolEndpointAccess {
+    // API URL the settings apply to
+    pub url: String,
+    pub auth: MempoolAuthMethod,
+    // Basic auth username
+    pub username: String,
+    // Whether custom headers are stored in the keyring
+    pub custom_headers: bool,
+    // PEM file with CA certificates to trust in addition to the system ones
+    pub ca_bundle: String,
+    // SHA-256 fingerprint of the server certificate (hex, colons optional)
+    pub pinned_sha256: String,
+}
+
+impl MempoolEndpointAccess {
+    pub fn new(url: &str) -> Self {
+        MempoolEndpointAccess {
+            url: MempoolClient::normalize_url(url),
+            ..Default::default()
+        }
+    }
+
+    // Nothing to apply, so the entry needn't be kept
+    pub fn is_empty(&self) -> bool {
+        self.auth == MempoolAuthMethod::None
+            && !self.custom_headers
+            && self.ca_bundle.trim().is_empty()
+            && self.pinned_sha256.trim().is_empty()
+    }
+}
+
+// Kinds of secret kept in the keyring for an endpoint
+#[derive(Debug, Clone, Copy)]
+enum SecretKind {
+    Password,
+    Token,
+    Headers,
+}
+
+impl SecretKind {
+    const ALL: [SecretKind; 3] = [SecretKind::Password, SecretKind::Token, SecretKind::Headers];
+
+    fn key(&self) -> &'static str {
+        match self {
+            SecretKind::Password => "password",
+            SecretKind::Token => "token",
+            SecretKind::Headers => "headers",
+        }
+    }
+}
+
+// What requests to an endpoint need, with the secrets loaded from the keyring
+#[derive(Clone, Default)]
+struct EndpointCredentials {
+    // Authorization and custom headers
+    headers: Vec<(String, String)>,
+    // PEM encoded certificates from the CA bundle
+    root_certificates: Vec<String>,
+    pinned_sha256: Option<[u8; 32]>,
+}
+
+// Credentials of every configured endpoint by normalized URL. Endpoints whose
+// settings couldn't be loaded keep the error, so requests to them fail instead
+// of going out without credentials.
+static ENDPOINTS: RwLock<Vec<(String, Result<EndpointCredentials, String>)>> = RwLock::new(Vec::new());
+
+// Bumped whenever the endpoints are reloaded
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Load the credentials of `endpoints`, reading their secrets from the keyring
+pub fn configure(endpoints: &[MempoolEndpointAccess]) {
+    let endpoints = endpoints.iter()
+        .filter(|access| !access.is_empty())
+        .map(|access| {
+            let credentials = resolve(access).map_err(|e| {
+                eprintln!("Failed to load credentials for {}: {}", access.url, e);
+                e.to_string()
+            });
+            (MempoolClient::normalize_url(&access.url), credentials)
+        })
+        .collect();
+
+    *ENDPOINTS.write().unwrap() = endpoints;
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Changes whenever the credentials are reloaded, so long-lived connections know to reconnect
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+fn resolve(access: &MempoolEndpointAccess) -> Result<EndpointCredentials> {
+    let mut headers = Vec::new();
+    match access.auth {
+        MempoolAuthMethod::None => {},
+        MempoolAuthMethod::Basic => {
+            let password = load_secret(&access.url, SecretKind::Password)?
+                .ok_or_else(|| anyhow!("No basic auth password stored in the keyring"))?;
+            let credentials = BASE64.encode(format!("{}:{}", access.username.trim(), password));
+            headers.push((AUTHORIZATION.to_string(), format!("Basic {}", credentials)));
+        },
+        MempoolAuthMethod::Bearer => {
+            let token = load_secret(&access.url, SecretKind::Token)?
+                .ok_or_else(|| anyhow!("No bearer token stored in the keyring"))?;
+            headers.push((AUTHORIZATION.to_string(), format!("Bearer {}", token.trim())));
+        },
+    }
+    if access.custom_headers {
+        if let Some(text) = load_secret(&access.url, SecretKind::Headers)? {
+            headers.extend(parse_headers(&text)?);
+        }
+    }
+
+    let root_certificates = match access.ca_bundle.trim() {
+        "" => Vec::new(),
+        path => load_ca_bundle(path)?,
+    };
+    let pinned_sha256 = match access.pinned_sha256.trim() {
+        "" => None,
+        fingerprint => Some(parse_fingerprint(fingerprint)?),
+    };
+
+    Ok(EndpointCredentials { headers, root_certificates, pinned_sha256 })
+}
+
+// Credentials configured for the endpoint at `url`, if any
+fn credentials(url: &str) -> Result<Option<EndpointCredentials>> {
+    let url = MempoolClient::normalize_url(url);
+    let endpoints = ENDPOINTS.read().unwrap();
+    match endpoints.iter().find(|(endpoint, _)| *endpoint == url) {
+        Some((_, Ok(credentials))) => Ok(Some(credentials.clone())),
+        Some((_, Err(e))) => Err(anyhow!("Credentials for {} are unavailable: {}", url, e)),
+        None => Ok(None),
+    }
+}
+
+/// Fail if the credentials of the endpoint at `url` couldn't be loaded
+pub fn check(url: &str) -> Result<()> {
+    credentials(url).map(|_| ())
+}
+
+/// Apply the credentials, CA bundle and certificate pin configured for the
+/// endpoint at `url` to a client builder
+pub fn configure_http(url: &str, builder: reqwest::blocking::ClientBuilder) -> Result<reqwest::blocking::ClientBuilder> {
+    match credentials(url)? {
+        Some(credentials) => apply_credentials(&credentials, builder),
+        None => Ok(builder),
+    }
+}
+
+fn apply_credentials(credentials: &EndpointCredentials, builder: reqwest::blocking::ClientBuilder) -> Result<reqwest::blocking::ClientBuilder> {
+    let mut builder = builder;
+
+    if !credentials.headers.is_empty() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &credentials.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| anyhow!("Invalid header name '{}': {}", name, e))?;
+            let mut value = HeaderValue::from_str(value)
+                .map_err(|e| anyhow!("Invalid value for header {}: {}", name, e))?;
+            // Keep secrets out of debug output
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        // A redirect would carry custom headers along to wherever it points
+        builder = builder
+            .default_headers(headers)
+            .redirect(reqwest::redirect::Policy::none());
+    }
+
+    for pem in &credentials.root_certificates {
+        let certificate = reqwest::Certificate::from_pem(pem.as_bytes())
+            .map_err(|e| anyhow!("Invalid CA certificate: {}", e))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+
+    if let Some(pinned_sha256) = credentials.pinned_sha256 {
+        // A pinned certificate is trusted on its own; PinLayer checks it
+        // before a request is written to the connection
+        builder = builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .tls_info(true)
+            .connector_layer(PinLayer { pinned_sha256 });
+    }
+
+    Ok(builder)
+}
+
+/// Handshake headers and TLS options for a WebSocket connection to the
+/// endpoint whose API URL is `api_url`
+pub fn websocket_access(api_url: &str) -> Result<(Vec<(String, String)>, TlsOptions)> {
+    let Some(credentials) = credentials(api_url)? else {
+        return Ok((Vec::new(), TlsOptions::default()));
+    };
+
+    let root_certificates = credentials.root_certificates.iter()
+        .map(|pem| native_tls::Certificate::from_pem(pem.as_bytes())
+            .map_err(|e| anyhow!("Invalid CA certificate: {}", e)))
+        .collect::<Result<Vec<_>>>()?;
+    let tls = TlsOptions {
+        accept_invalid_certs: false,
+        root_certificates,
+        pinned_sha256: credentials.pinned_sha256,
+    };
+    Ok((credentials.headers, tls))
+}
+
+/// Check `access` and the custom headers about to be stored for it, so
+/// mistakes show up when saving rather than on the next request
+pub fn validate(access: &MempoolEndpointAccess, headers: &str) -> Result<()> {
+    parse_headers(headers)?;
+    if !access.ca_bundle.trim().is_empty() {
+        load_ca_bundle(access.ca_bundle.trim())?;
+    }
+    if !access.pinned_sha256.trim().is_empty() {
+        parse_fingerprint(&access.pinned_sha256)?;
+    }
+    Ok(())
+}
+
+/// Save the secrets entered for `access` in the keyring. Empty values keep
+/// what is already stored: `secret` is the password or token for the auth
+/// method and `headers` holds one "Name: value" per line.
+pub fn store_secrets(access: &MempoolEndpointAccess, secret: &str, headers: &str) -> Result<()> {
+    let kind = match access.auth {
+        MempoolAuthMethod::None => None,
+        MempoolAuthMethod::Basic => Some(SecretKind::Password),
+        MempoolAuthMethod::Bearer => Some(SecretKind::Token),
+    };
+    if let Some(kind) = kind {
+        if !secret.is_empty() {
+            store_secret(&access.url, kind, secret)?;
+        }
+    }
+    if !headers.trim().is_empty() {
+        store_secret(&access.url, SecretKind::Headers, headers.trim())?;
+    }
+    Ok(())
+}
+
+/// Remove every secret stored for the endpoint at `url` from the keyring
+pub fn forget_secrets(url: &str) -> Result<()> {
+    for kind in SecretKind::ALL {
+        let attributes = secret_attributes(url, kind);
+        secret_store::delete(&borrow_attributes(&attributes))?;
+    }
+    Ok(())
+}
+
+fn secret_attributes(url: &str, kind: SecretKind) -> [(&'static str, String); 2] {
+    [("endpoint", MempoolClient::normalize_url(url)), ("secret", kind.key().to_string())]
+}
+
+fn load_secret(url: &str, kind: SecretKind) -> Result<Option<String>> {
+    let attributes = secret_attributes(url, kind);
+    secret_store::load(&borrow_attributes(&attributes))
+}
+
+fn store_secret(url: &str, kind: SecretKind, secret: &str) -> Result<()> {
+    let attributes = secret_attributes(url, kind);
+    let label = format!("BTC Ticker mempool {} for {}", kind.key(), url);
+    secret_store::store(&label, &borrow_attributes(&attributes), secret)
+}
+
+fn borrow_attributes<'a>(attributes: &'a [(&'static str, String); 2]) -> Vec<(&'a str, &'a str)> {
+    attributes.iter().map(|(name, value)| (*name, value.as_str())).collect()
+}
+
+// Parse "Name: value" lines, skipping blank ones
+fn parse_headers(text: &str) -> Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header '{}' (expected Name: value)", line))?;
+        let (name, value) = (name.trim(), value.trim());
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow!("Invalid header name '{}'", name))?;
+        HeaderValue::from_str(value)
+            .map_err(|_| anyhow!("Invalid value for header {}", name))?;
+        headers.push((name.to_string(), value.to_string()));
+    }
+    Ok(headers)
+}
+
+// Split a PEM bundle into its certificates
+fn load_ca_bundle(path: &str) -> Result<Vec<String>> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let bundle = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read CA bundle {}: {}", path, e))?;
+
+    let mut certificates = Vec::new();
+    let mut rest = bundle.as_str();
+    while let Some(start) = rest.find(BEGIN) {
+        let end = rest[start..].find(END)
+            .ok_or_else(|| anyhow!("Unterminated certificate in CA bundle {}", path))?;
+        let pem = &rest[start..start + end + END.len()];
+        native_tls::Certificate::from_pem(pem.as_bytes())
+            .map_err(|e| anyhow!("Invalid certificate in CA bundle {}: {}", path, e))?;
+        certificates.push(pem.to_string());
+        rest = &rest[start + end + END.len()..];
+    }
+
+    if certificates.is_empty() {
+        return Err(anyhow!("No PEM certificates found in CA bundle {}", path));
+    }
+    Ok(certificates)
+}
+
+// SHA-256 fingerprint as 64 hex digits, optionally separated by colons or spaces
+fn parse_fingerprint(text: &str) -> Result<[u8; 32]> {
+    let hex: String = text.chars().filter(|c| !matches!(c, ':' | ' ')).collect();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Certificate pin must be a SHA-256 fingerprint of 64 hex digits"));
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (index, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)?;
+    }
+    Ok(fingerprint)
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Connector layer that drops connections whose server certificate doesn't
+// match the pin, before any request is sent over them
+#[derive(Clone)]
+struct PinLayer {
+    pinned_sha256: [u8; 32],
+}
+
+impl<S> tower_layer::Layer<S> for PinLayer {
+    type Service = PinService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PinService { inner, pinned_sha256: self.pinned_sha256 }
+    }
+}
+
+#[derive(Clone)]
+struct PinService<S> {
+    inner: S,
+    pinned_sha256: [u8; 32],
+}
+
+impl<S, R> tower_service::Service<R> for PinService<S>
+where
+    S: tower_service::Service<R>,
+    S::Response: Connection + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        let pinned_sha256 = self.pinned_sha256;
+
+        Box::pin(async move {
+            let connection = connecting.await.map_err(Into::into)?;
+
+            let mut extensions = http::Extensions::new();
+            connection.connected().get_extras(&mut extensions);
+            let certificate = extensions.get::<reqwest::tls::TlsInfo>()
+                .and_then(|info| info.peer_certificate())
+                .ok_or("The server sent no TLS certificate to check the pin against")?;
+            // reqwest's error text leaves out the cause, so log it here
+            if let Err(e) = transport::check_pin("the mempool server", certificate, &pinned_sha256) {
+                eprintln!("{}", e);
+                return Err(e.into());
+            }
+
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::test_support::{self, LOCALHOST_CERT};
+
+    fn localhost_fingerprint() -> [u8; 32] {
+        let der = native_tls::Certificate::from_pem(LOCALHOST_CERT.as_bytes()).unwrap().to_der().unwrap();
+        transport::certificate_fingerprint(&der)
+    }
+
+    // TLS server counting the requests that get through to it
+    fn serve_counted() -> (String, Arc<AtomicU64>) {
+        let requests = Arc::new(AtomicU64::new(0));
+        let counter = requests.clone();
+        let url = test_support::serve_tls(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            (200, "{}".to_string())
+        });
+        (url, requests)
+    }
+
+    fn get(credentials: &EndpointCredentials, url: &str) -> Result<reqwest::blocking::Response> {
+        let client = apply_credentials(credentials, reqwest::blocking::Client::builder().no_proxy())?.build()?;
+        Ok(client.get(url).send()?)
+    }
+
+    #[test]
+    fn matching_pin_trusts_a_self_signed_certificate() {
+        let url = test_support::serve_tls(|_| (200, "{}".to_string()));
+        let credentials = EndpointCredentials { pinned_sha256: Some(localhost_fingerprint()), ..Default::default() };
+        assert_eq!(get(&credentials, &url).unwrap().status(), 200);
+    }
+
+    #[test]
+    fn mismatched_pin_is_rejected_before_the_request_is_sent() {
+        let (url, requests) = serve_counted();
+        let mut pinned = localhost_fingerprint();
+        pinned[0] ^= 0xff;
+        let credentials = EndpointCredentials { pinned_sha256: Some(pinned), ..Default::default() };
+        assert!(get(&credentials, &url).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn self_signed_certificate_without_pin_is_rejected() {
+        let (url, requests) = serve_counted();
+        assert!(get(&EndpointCredentials::default(), &url).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn ca_bundle_trusts_its_certificates() {
+        let url = test_support::serve_tls(|_| (200, "{}".to_string()));
+        let credentials = EndpointCredentials { root_certificates: vec![LOCALHOST_CERT.to_string()], ..Default::default() };
+        assert_eq!(get(&credentials, &url).unwrap().status(), 200);
+    }
+
+    #[test]
+    fn headers_are_sent_without_following_redirects() {
+        let url = test_support::serve(|request| {
+            assert_eq!(request.header("Authorization"), Some("Bearer token"));
+            assert_eq!(request.header("X-Api-Key"), Some("key"));
+            (302, String::new())
+        });
+        let credentials = EndpointCredentials {
+            headers: vec![
+                ("Authorization".to_string(), "Bearer token".to_string()),
+                ("X-Api-Key".to_string(), "key".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(get(&credentials, &url).unwrap().status(), 302);
+    }
+
+    #[test]
+    fn fingerprints_with_or_without_colons() {
+        let fingerprint = localhost_fingerprint();
+        let formatted = transport::format_fingerprint(&fingerprint);
+        assert_eq!(parse_fingerprint(&formatted).unwrap(), fingerprint);
+        assert_eq!(parse_fingerprint(&formatted.replace(':', "").to_lowercase()).unwrap(), fingerprint);
+        assert!(parse_fingerprint("AB:CD").is_err());
+    }
+
+    #[test]
+    fn header_lines() {
+        let headers = parse_headers("X-Api-Key: abc\n\n  X-Other:  d:e  \n").unwrap();
+        assert_eq!(headers, [
+            ("X-Api-Key".to_string(), "abc".to_string()),
+            ("X-Other".to_string(), "d:e".to_string()),
+        ]);
+        assert!(parse_headers("no colon").is_err());
+        assert!(parse_headers("Bad Name: value").is_err());
+    }
+}
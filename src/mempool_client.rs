@@ -3,8 +3,11 @@ use serde::{Deserialize};
 use chrono::{Utc, TimeZone, DateTime, Local};
 use url::Url;
 
+use crate::mempool_auth;
+use crate::proxy::{self, HttpClient, ProxySource};
 
-#[derive(Debug, Deserialize)]
+
+#[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
 pub struct MempoolBlockInfo {
     pub id: String,
@@ -33,12 +36,251 @@ pub struct MempoolFeeEstimate {
     pub economy_fee: u32,
 }
 
+// Progress of the current difficulty epoch from /v1/difficulty-adjustment
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct MempoolDifficultyAdjustment {
+    pub progress_percent: f64,
+    // Estimated difficulty change at the next retarget (percent)
+    pub difficulty_change: f64,
+    // Unix timestamp in milliseconds
+    pub estimated_retarget_date: i64,
+    pub remaining_blocks: u32,
+    // Milliseconds
+    pub remaining_time: i64,
+    // Change at the previous retarget (percent)
+    pub previous_retarget: f64,
+    pub next_retarget_height: u32,
+    // Average block time in this epoch, in milliseconds
+    pub time_avg: i64,
+}
+
+// Projected next block from /v1/fees/mempool-blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct MempoolProjectedBlock {
+    pub block_size: u64,
+    pub block_v_size: f64,
+    pub n_tx: u32,
+    // Satoshis
+    pub total_fees: u64,
+    pub median_fee: f64,
+    // Fee rate percentiles from lowest to highest (sat/vB)
+    pub fee_range: Vec<f64>,
+}
+
+// Lower bounds of the fee bands (sat/vB) the mempool histogram is grouped into
+pub const FEE_BANDS: [f64; 26] = [
+    0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0, 12.0, 15.0, 20.0, 30.0,
+    40.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0, 125.0, 150.0, 200.0, 300.0, 500.0, 1000.0,
+];
+
+// Mempool backlog from /mempool
+#[derive(Debug, Clone, Deserialize)]
+pub struct MempoolStats {
+    pub count: u32,
+    pub vsize: u64,
+    // Satoshis
+    pub total_fee: u64,
+    // (fee rate in sat/vB, vsize) pairs, highest fee rate first
+    pub fee_histogram: Vec<(f64, f64)>,
+}
+
+// Virtual size of the mempool transactions within one fee band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeBand {
+    pub min_fee: f64,
+    // None for the open-ended top band
+    pub max_fee: Option<f64>,
+    pub vsize: f64,
+}
+
+impl MempoolStats {
+    /// Group the fee histogram into `FEE_BANDS`, dropping empty bands above the highest fee rate
+    pub fn fee_bands(&self) -> Vec<FeeBand> {
+        let mut bands: Vec<FeeBand> = FEE_BANDS.iter().enumerate()
+            .map(|(index, &min_fee)| FeeBand {
+                min_fee,
+                max_fee: FEE_BANDS.get(index + 1).copied(),
+                vsize: 0.0,
+            })
+            .collect();
+        
+        for &(fee_rate, vsize) in &self.fee_histogram {
+            let index = FEE_BANDS.iter().rposition(|&min_fee| fee_rate >= min_fee).unwrap_or(0);
+            bands[index].vsize += vsize;
+        }
+        
+        let used = bands.iter().rposition(|band| band.vsize > 0.0).map_or(0, |index| index + 1);
+        bands.truncate(used);
+        bands
+    }
+}
+
+// Funding and spending totals of an address, from /address/{addr}
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolAddressStats {
+    pub funded_txo_count: u32,
+    // Satoshis
+    pub funded_txo_sum: u64,
+    pub spent_txo_count: u32,
+    pub spent_txo_sum: u64,
+    pub tx_count: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolAddress {
+    pub address: String,
+    pub chain_stats: MempoolAddressStats,
+    pub mempool_stats: MempoolAddressStats,
+}
+
+impl MempoolAddress {
+    /// Confirmed balance in satoshis
+    pub fn confirmed_balance(&self) -> i64 {
+        self.chain_stats.funded_txo_sum as i64 - self.chain_stats.spent_txo_sum as i64
+    }
+    
+    /// Net effect of unconfirmed transactions in satoshis (negative while spending)
+    pub fn unconfirmed_balance(&self) -> i64 {
+        self.mempool_stats.funded_txo_sum as i64 - self.mempool_stats.spent_txo_sum as i64
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolTxStatus {
+    pub confirmed: bool,
+    pub block_height: Option<u32>,
+    pub block_hash: Option<String>,
+    pub block_time: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MempoolTxOutput {
+    pub scriptpubkey_address: Option<String>,
+    // Satoshis
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolTxInput {
+    pub txid: String,
+    pub vout: u32,
+    // Missing for coinbase inputs
+    pub prevout: Option<MempoolTxOutput>,
+}
+
+// Transaction from /tx/{txid} or /address/{addr}/txs
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolTx {
+    pub txid: String,
+    pub vin: Vec<MempoolTxInput>,
+    pub vout: Vec<MempoolTxOutput>,
+    pub size: u32,
+    pub weight: u32,
+    // Satoshis
+    pub fee: u64,
+    pub status: MempoolTxStatus,
+}
+
+impl MempoolTx {
+    /// Net satoshis this transaction moves into (positive) or out of (negative) `address`
+    pub fn net_value_for(&self, address: &str) -> i64 {
+        let received: u64 = self.vout.iter()
+            .filter(|output| output.scriptpubkey_address.as_deref() == Some(address))
+            .map(|output| output.value)
+            .sum();
+        let spent: u64 = self.vin.iter()
+            .filter_map(|input| input.prevout.as_ref())
+            .filter(|prevout| prevout.scriptpubkey_address.as_deref() == Some(address))
+            .map(|prevout| prevout.value)
+            .sum();
+        received as i64 - spent as i64
+    }
+    
+    /// Fee rate in sat/vB
+    pub fn fee_rate(&self) -> f64 {
+        let vsize = (self.weight as f64 / 4.0).ceil();
+        if vsize > 0.0 { self.fee as f64 / vsize } else { 0.0 }
+    }
+}
+
+// Period of the hashrate and difficulty history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashrateTimeframe {
+    Month,
+    ThreeMonths,
+    Year,
+    All,
+}
+
+impl HashrateTimeframe {
+    pub const ALL: [HashrateTimeframe; 4] = [
+        HashrateTimeframe::Month,
+        HashrateTimeframe::ThreeMonths,
+        HashrateTimeframe::Year,
+        HashrateTimeframe::All,
+    ];
+    
+    pub fn description(&self) -> &'static str {
+        match self {
+            HashrateTimeframe::Month => "1M",
+            HashrateTimeframe::ThreeMonths => "3M",
+            HashrateTimeframe::Year => "1Y",
+            HashrateTimeframe::All => "All",
+        }
+    }
+    
+    // Period parameter of the mining/hashrate endpoint
+    pub fn api_period(&self) -> &'static str {
+        match self {
+            HashrateTimeframe::Month => "1m",
+            HashrateTimeframe::ThreeMonths => "3m",
+            HashrateTimeframe::Year => "1y",
+            HashrateTimeframe::All => "all",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolHashratePoint {
+    pub timestamp: i64,
+    // Hashes per second
+    pub avg_hashrate: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
+pub struct MempoolDifficultyPoint {
+    pub time: i64,
+    pub height: u32,
+    pub difficulty: f64,
+}
+
+// Hashrate and difficulty history from /v1/mining/hashrate/{period}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolHashrateHistory {
+    pub hashrates: Vec<MempoolHashratePoint>,
+    pub difficulty: Vec<MempoolDifficultyPoint>,
+    pub current_hashrate: f64,
+    pub current_difficulty: f64,
+}
+
 // Default mempool.space API URL
 pub const DEFAULT_MEMPOOL_API_URL: &str = "https://mempool.space/api";
 
 // Mempool API client for handling all API interactions
 pub struct MempoolClient {
-    client: reqwest::blocking::Client,
+    client: HttpClient,
     base_url: String,
 }
 
@@ -51,13 +293,16 @@ impl MempoolClient {
     
     /// Create a new MempoolClient with a custom API URL
     pub fn with_url(base_url: &str) -> Self {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(10)) // 10 second timeout
-            .build()
-            .unwrap_or_else(|_| reqwest::blocking::Client::new());
-        
         // Normalize the URL to ensure it doesn't end with a slash
         let normalized_url = Self::normalize_url(base_url);
+        
+        let builder = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10)); // 10 second timeout
+        // Credentials and certificate checks configured for this endpoint
+        let client = match mempool_auth::configure_http(&normalized_url, builder) {
+            Ok(builder) => HttpClient::build(ProxySource::Mempool, &normalized_url, builder),
+            Err(e) => HttpClient::unavailable(e),
+        };
             
         MempoolClient {
             client,
@@ -68,14 +313,18 @@ impl MempoolClient {
     /// Normalize a URL to ensure it's properly formatted
     /// - Validates that the URL is valid
     /// - Removes trailing slashes
-    /// - Ensures the URL has a scheme (http/https)
+    /// - Ensures the URL has a scheme (http/https, or http for .onion hosts)
     pub fn normalize_url(input_url: &str) -> String {
-        // Try to parse the URL
+        // Try to parse the URL. Without "://", "host:port" would parse with the
+        // host as its scheme.
         let parsed_url = match Url::parse(input_url) {
-            Ok(url) => url,
-            Err(_) => {
-                // If parsing fails, try adding https:// prefix and parse again
-                match Url::parse(&format!("https://{}", input_url)) {
+            Ok(url) if input_url.contains("://") => url,
+            _ => {
+                // If parsing fails, add a scheme and parse again. Onion services
+                // are usually served over plain http since Tor already encrypts.
+                let host = input_url.split(['/', ':']).next().unwrap_or_default();
+                let scheme = if proxy::is_onion(host) { "http" } else { "https" };
+                match Url::parse(&format!("{}://{}", scheme, input_url)) {
                     Ok(url) => url,
                     Err(_) => {
                         // If still fails, just return the original URL
@@ -106,13 +355,13 @@ impl MempoolClient {
         &self.base_url
     }
 
-    pub fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+    /// Height of the chain tip
+    pub fn fetch_tip_height(&self) -> Result<u32> {
         let url = format!("{}/blocks/tip/height", self.base_url);
         
         println!("Fetching latest block height from: {}", url);
         
-        // First get the latest block height
-        let response = self.client.get(&url)
+        let response = self.client.get(&url)?
             .send()
             .map_err(|e| anyhow!("Failed to fetch block height: {}", e))?;
             
@@ -120,14 +369,20 @@ impl MempoolClient {
             return Err(anyhow!("API returned error status: {}", response.status()));
         }
         
-        let height: u32 = response.text()
+        response.text()
             .map_err(|e| anyhow!("Failed to parse block height: {}", e))?
+            .trim()
             .parse()
-            .map_err(|e| anyhow!("Failed to parse block height as number: {}", e))?;
+            .map_err(|e| anyhow!("Failed to parse block height as number: {}", e))
+    }
+
+    pub fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        // First get the latest block height
+        let height = self.fetch_tip_height()?;
             
         // Now get the block details
         let block_url = format!("{}/block-height/{}", self.base_url, height);
-        let block_hash_response = self.client.get(&block_url)
+        let block_hash_response = self.client.get(&block_url)?
             .send()
             .map_err(|e| anyhow!("Failed to fetch block hash: {}", e))?;
             
@@ -140,7 +395,7 @@ impl MempoolClient {
             
         // Finally get the block details
         let block_details_url = format!("{}/block/{}", self.base_url, block_hash);
-        let block_details_response = self.client.get(&block_details_url)
+        let block_details_response = self.client.get(&block_details_url)?
             .send()
             .map_err(|e| anyhow!("Failed to fetch block details: {}", e))?;
             
@@ -154,12 +409,186 @@ impl MempoolClient {
         Ok(block_info)
     }
     
+    /// Fetch up to 10 blocks ending at `start_height`, newest first
+    pub fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        let url = format!("{}/blocks/{}", self.base_url, start_height);
+        
+        println!("Fetching blocks from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch blocks: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let blocks: Vec<MempoolBlockInfo> = response.json()
+            .map_err(|e| anyhow!("Failed to parse blocks: {}", e))?;
+            
+        Ok(blocks)
+    }
+    
+    pub fn fetch_difficulty_adjustment(&self) -> Result<MempoolDifficultyAdjustment> {
+        let url = format!("{}/v1/difficulty-adjustment", self.base_url);
+        
+        println!("Fetching difficulty adjustment from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch difficulty adjustment: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let adjustment: MempoolDifficultyAdjustment = response.json()
+            .map_err(|e| anyhow!("Failed to parse difficulty adjustment: {}", e))?;
+            
+        Ok(adjustment)
+    }
+    
+    pub fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        let url = format!("{}/mempool", self.base_url);
+        
+        println!("Fetching mempool stats from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch mempool stats: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let stats: MempoolStats = response.json()
+            .map_err(|e| anyhow!("Failed to parse mempool stats: {}", e))?;
+            
+        Ok(stats)
+    }
+    
+    pub fn fetch_address(&self, address: &str) -> Result<MempoolAddress> {
+        let url = format!("{}/address/{}", self.base_url, address);
+        
+        println!("Fetching address from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch address: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let address: MempoolAddress = response.json()
+            .map_err(|e| anyhow!("Failed to parse address: {}", e))?;
+            
+        Ok(address)
+    }
+    
+    /// Fetch the unconfirmed and most recent confirmed transactions of an address, newest first
+    pub fn fetch_address_txs(&self, address: &str) -> Result<Vec<MempoolTx>> {
+        let url = format!("{}/address/{}/txs", self.base_url, address);
+        
+        println!("Fetching address transactions from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch address transactions: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let txs: Vec<MempoolTx> = response.json()
+            .map_err(|e| anyhow!("Failed to parse address transactions: {}", e))?;
+            
+        Ok(txs)
+    }
+    
+    pub fn fetch_tx(&self, txid: &str) -> Result<MempoolTx> {
+        let url = format!("{}/tx/{}", self.base_url, txid);
+        
+        println!("Fetching transaction from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch transaction: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let tx: MempoolTx = response.json()
+            .map_err(|e| anyhow!("Failed to parse transaction: {}", e))?;
+            
+        Ok(tx)
+    }
+    
+    pub fn fetch_tx_status(&self, txid: &str) -> Result<MempoolTxStatus> {
+        let url = format!("{}/tx/{}/status", self.base_url, txid);
+        
+        println!("Fetching transaction status from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch transaction status: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let status: MempoolTxStatus = response.json()
+            .map_err(|e| anyhow!("Failed to parse transaction status: {}", e))?;
+            
+        Ok(status)
+    }
+    
+    /// Fetch the blocks the mempool would fill next, starting with the next block
+    pub fn fetch_projected_blocks(&self) -> Result<Vec<MempoolProjectedBlock>> {
+        let url = format!("{}/v1/fees/mempool-blocks", self.base_url);
+        
+        println!("Fetching projected blocks from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch projected blocks: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let blocks: Vec<MempoolProjectedBlock> = response.json()
+            .map_err(|e| anyhow!("Failed to parse projected blocks: {}", e))?;
+            
+        Ok(blocks)
+    }
+    
+    pub fn fetch_hashrate_history(&self, timeframe: HashrateTimeframe) -> Result<MempoolHashrateHistory> {
+        let url = format!("{}/v1/mining/hashrate/{}", self.base_url, timeframe.api_period());
+        
+        println!("Fetching hashrate history from: {}", url);
+        
+        let response = self.client.get(&url)?
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch hashrate history: {}", e))?;
+            
+        if !response.status().is_success() {
+            return Err(anyhow!("API returned error status: {}", response.status()));
+        }
+        
+        let history: MempoolHashrateHistory = response.json()
+            .map_err(|e| anyhow!("Failed to parse hashrate history: {}", e))?;
+            
+        Ok(history)
+    }
+    
     pub fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
         let url = format!("{}/v1/fees/recommended", self.base_url);
         
         println!("Fetching fee estimates from: {}", url);
         
-        let response = self.client.get(&url)
+        let response = self.client.get(&url)?
             .send()
             .map_err(|e| anyhow!("Failed to fetch fee estimates: {}", e))?;
             
@@ -183,3 +612,25 @@ pub fn format_unix_timestamp(timestamp: u32) -> String {
     }
     "Invalid timestamp".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_url_adds_scheme_and_api_path() {
+        assert_eq!(MempoolClient::normalize_url("mempool.space"), "https://mempool.space/api");
+        assert_eq!(MempoolClient::normalize_url("https://mempool.space/api/"), "https://mempool.space/api");
+        assert_eq!(MempoolClient::normalize_url("192.168.1.5:8999"), "https://192.168.1.5:8999/api");
+        assert_eq!(MempoolClient::normalize_url("http://umbrel.local:3006"), "http://umbrel.local:3006/api");
+    }
+
+    #[test]
+    fn normalize_url_uses_http_for_onion_services() {
+        let onion = "mempoolhqx4isw62xs7abwphsq7ldayuidyx2v2oethdhhj6mlo2r6ad.onion";
+        assert_eq!(MempoolClient::normalize_url(onion), format!("http://{}/api", onion));
+        assert_eq!(MempoolClient::normalize_url(&format!("{}:8080/api", onion)), format!("http://{}:8080/api", onion));
+        // An explicit scheme is kept
+        assert_eq!(MempoolClient::normalize_url(&format!("https://{}", onion)), format!("https://{}/api", onion));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use url::Url;
+
+use crate::config::DEFAULT_MEMPOOL_API_URL;
+use crate::mempool_client::MempoolClient;
+use crate::network_provider::NetworkBackend;
+
+// How often every endpoint is checked, so a recovered endpoint is used again
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// A mempool API instance, e.g. a node at home or mempool.space
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MempoolEndpoint {
+    pub name: String,
+    pub url: String,
+}
+
+impl MempoolEndpoint {
+    /// Endpoint at `url`, named after its host when `name` is empty
+    pub fn new(name: &str, url: &str) -> Self {
+        let url = MempoolClient::normalize_url(url.trim());
+        let name = match name.trim() {
+            "" => Url::parse(&url).ok()
+                .and_then(|parsed| parsed.host_str().map(str::to_string))
+                .unwrap_or_else(|| url.clone()),
+            name => name.to_string(),
+        };
+        MempoolEndpoint { name, url }
+    }
+}
+
+/// The endpoint list of a fresh install: just mempool.space
+pub fn default_endpoints() -> Vec<MempoolEndpoint> {
+    vec![MempoolEndpoint::new("mempool.space", DEFAULT_MEMPOOL_API_URL)]
+}
+
+// Latest health of one endpoint
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStatus {
+    // Round trip of the last successful health check
+    pub latency: Option<Duration>,
+    // Why the endpoint last failed; cleared once it answers again
+    pub error: Option<String>,
+}
+
+/// Health of the mempool endpoints, from health checks and regular requests
+#[derive(Debug, Default)]
+pub struct EndpointPool {
+    // By endpoint URL
+    statuses: HashMap<String, EndpointStatus>,
+}
+
+impl EndpointPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Status of the endpoint at `url`, or None before it was first used or checked
+    pub fn status(&self, url: &str) -> Option<&EndpointStatus> {
+        self.statuses.get(url)
+    }
+
+    pub fn record_latency(&mut self, url: &str, latency: Duration) {
+        let status = self.statuses.entry(url.to_string()).or_default();
+        status.latency = Some(latency);
+        status.error = None;
+    }
+
+    pub fn record_success(&mut self, url: &str) {
+        self.statuses.entry(url.to_string()).or_default().error = None;
+    }
+
+    pub fn record_failure(&mut self, url: &str, error: impl ToString) {
+        self.statuses.entry(url.to_string()).or_default().error = Some(error.to_string());
+    }
+
+    fn is_failing(&self, url: &str) -> bool {
+        self.status(url).map(|status| status.error.is_some()).unwrap_or(false)
+    }
+
+    /// First endpoint that isn't failing, in the configured order. When all
+    /// of them are failing the first one is still preferred.
+    pub fn preferred<'a>(&self, endpoints: &'a [MempoolEndpoint]) -> Option<&'a MempoolEndpoint> {
+        endpoints.iter()
+            .find(|endpoint| !self.is_failing(&endpoint.url))
+            .or_else(|| endpoints.first())
+    }
+
+    /// Order to try the endpoints in: working or unchecked ones first, then
+    /// the failing ones, each in the configured order
+    pub fn failover_order(&self, endpoints: &[MempoolEndpoint]) -> Vec<MempoolEndpoint> {
+        let (failing, working): (Vec<_>, Vec<_>) = endpoints.iter()
+            .cloned()
+            .partition(|endpoint| self.is_failing(&endpoint.url));
+        working.into_iter().chain(failing).collect()
+    }
+}
+
+/// Round trip of a cheap request (the tip height) to the endpoint at `url`
+pub fn check(url: &str) -> Result<Duration> {
+    let client = MempoolClient::with_url(url);
+    let started = Instant::now();
+    client.fetch_tip_height()?;
+    Ok(started.elapsed())
+}
+
+/// Health-check every endpoint in turn, passing each result to `record`.
+/// Nothing is sent unless the mempool API is the selected backend, so users
+/// of their own node don't keep querying public instances.
+pub fn check_all<F>(backend: NetworkBackend, endpoints: &[MempoolEndpoint], mut record: F)
+where
+    F: FnMut(&MempoolEndpoint, Result<Duration>),
+{
+    if backend != NetworkBackend::Mempool {
+        return;
+    }
+    for endpoint in endpoints {
+        record(endpoint, check(&endpoint.url));
+    }
+}
+
+/// Latency for display, e.g. "42 ms"
+pub fn format_latency(latency: Duration) -> String {
+    format!("{} ms", latency.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::test_support;
+
+    fn endpoints() -> Vec<MempoolEndpoint> {
+        vec![
+            MempoolEndpoint::new("Home", "http://umbrel.local:3006"),
+            MempoolEndpoint::new("", "mempool.space"),
+            MempoolEndpoint::new("Emzy", "https://mempool.emzy.de/api/"),
+        ]
+    }
+
+    fn names(endpoints: &[MempoolEndpoint]) -> Vec<&str> {
+        endpoints.iter().map(|endpoint| endpoint.name.as_str()).collect()
+    }
+
+    #[test]
+    fn endpoints_are_normalized_and_named_after_their_host() {
+        let endpoints = endpoints();
+        assert_eq!(endpoints[0].url, "http://umbrel.local:3006/api");
+        assert_eq!(endpoints[1].name, "mempool.space");
+        assert_eq!(endpoints[1].url, "https://mempool.space/api");
+        assert_eq!(endpoints[2].url, "https://mempool.emzy.de/api");
+    }
+
+    #[test]
+    fn preferred_skips_failing_endpoints() {
+        let endpoints = endpoints();
+        let mut pool = EndpointPool::new();
+        assert_eq!(pool.preferred(&endpoints).unwrap().name, "Home");
+
+        pool.record_failure(&endpoints[0].url, "connection refused");
+        assert_eq!(pool.preferred(&endpoints).unwrap().name, "mempool.space");
+        assert_eq!(pool.status(&endpoints[0].url).unwrap().error.as_deref(), Some("connection refused"));
+
+        // Back to the first one once it answers again
+        pool.record_success(&endpoints[0].url);
+        assert_eq!(pool.preferred(&endpoints).unwrap().name, "Home");
+    }
+
+    #[test]
+    fn preferred_falls_back_to_the_first_when_all_fail() {
+        let endpoints = endpoints();
+        let mut pool = EndpointPool::new();
+        for endpoint in &endpoints {
+            pool.record_failure(&endpoint.url, "timeout");
+        }
+        assert_eq!(pool.preferred(&endpoints).unwrap().name, "Home");
+        assert!(pool.preferred(&[]).is_none());
+    }
+
+    #[test]
+    fn health_checks_clear_failures() {
+        let endpoints = endpoints();
+        let mut pool = EndpointPool::new();
+        pool.record_failure(&endpoints[0].url, "timeout");
+        pool.record_latency(&endpoints[0].url, Duration::from_millis(42));
+
+        let status = pool.status(&endpoints[0].url).unwrap();
+        assert_eq!(status.latency, Some(Duration::from_millis(42)));
+        assert!(status.error.is_none());
+        assert_eq!(format_latency(status.latency.unwrap()), "42 ms");
+    }
+
+    // Mock endpoint answering the tip height, counting the requests it gets
+    fn serve_counted() -> (MempoolEndpoint, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = test_support::serve(move |request| {
+            assert_eq!(request.path, "/api/blocks/tip/height");
+            counter.fetch_add(1, Ordering::SeqCst);
+            (200, "850000".to_string())
+        });
+        (MempoolEndpoint::new("Mock", &url), requests)
+    }
+
+    #[test]
+    fn health_checks_only_run_for_the_mempool_backend() {
+        let (endpoint, requests) = serve_counted();
+        let endpoints = [endpoint];
+
+        for backend in [NetworkBackend::BitcoinCore, NetworkBackend::Electrum] {
+            check_all(backend, &endpoints, |endpoint, _| panic!("{} was checked with {}", endpoint.url, backend.name()));
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        let mut results = Vec::new();
+        check_all(NetworkBackend::Mempool, &endpoints, |endpoint, result| results.push((endpoint.url.clone(), result.is_ok())));
+        assert_eq!(results, [(endpoints[0].url.clone(), true)]);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn failover_order_puts_failing_endpoints_last() {
+        let endpoints = endpoints();
+        let mut pool = EndpointPool::new();
+        assert_eq!(names(&pool.failover_order(&endpoints)), ["Home", "mempool.space", "Emzy"]);
+
+        pool.record_failure(&endpoints[0].url, "timeout");
+        pool.record_failure(&endpoints[1].url, "HTTP 503");
+        assert_eq!(names(&pool.failover_order(&endpoints)), ["Emzy", "Home", "mempool.space"]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::mempool_client::{
+    MempoolBlockInfo, MempoolClient, MempoolDifficultyAdjustment, MempoolFeeEstimate, MempoolProjectedBlock,
+};
+use crate::proxy::ProxySource;
+use crate::reconnect::Reconnect;
+use crate::transport::TlsOptions;
+use crate::ws_client::{self, Message, WebSocket};
+
+// Data the stream subscribes to
+const SUBSCRIPTIONS: [&str; 3] = ["blocks", "stats", "mempool-blocks"];
+
+// How long to wait for any message before checking whether to keep streaming
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Ask the server for a pong when it has been quiet for this long
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+// Reconnect if the socket has been silent for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+// Subset of the mempoolInfo object sent with the stats
+#[derive(Debug, Deserialize)]
+struct WsMempoolInfo {
+    size: u32,
+    bytes: u64,
+    // BTC
+    #[serde(default)]
+    total_fee: f64,
+}
+
+#[derive(Debug)]
+pub enum MempoolEvent {
+    Connected,
+    // Recent blocks on connect, then each new block as it is found
+    Blocks(Vec<MempoolBlockInfo>),
+    Fees(MempoolFeeEstimate),
+    // Transaction count, virtual size and total fees (satoshis) of the mempool
+    MempoolSize { count: u32, vsize: u64, total_fee: u64 },
+    ProjectedBlocks(Vec<MempoolProjectedBlock>),
+    DifficultyAdjustment(MempoolDifficultyAdjustment),
+    // No message arrived within the read timeout
+    Idle,
+    Disconnected(String),
+}
+
+/// WebSocket URL of a mempool instance, derived from its API URL
+/// (e.g. https://mempool.space/api becomes wss://mempool.space/api/v1/ws)
+pub fn websocket_url(api_url: &str) -> Result<String> {
+    let api_url = MempoolClient::normalize_url(api_url);
+    let mut parsed = Url::parse(&api_url)
+        .map_err(|e| anyhow!("Invalid mempool API URL '{}': {}", api_url, e))?;
+
+    let scheme = match parsed.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        scheme => return Err(anyhow!("Unsupported mempool API scheme: {}", scheme)),
+    };
+    parsed.set_scheme(scheme)
+        .map_err(|_| anyhow!("Failed to derive WebSocket URL from {}", api_url))?;
+
+    let path = format!("{}/v1/ws", parsed.path().trim_end_matches('/'));
+    parsed.set_path(&path);
+    Ok(parsed.to_string())
+}
+
+/// Streams blocks, fee estimates and projected blocks from a mempool instance's `/api/v1/ws`
+pub struct MempoolStream {
+    url: String,
+    // Sent with the handshake, e.g. Authorization
+    headers: Vec<(String, String)>,
+    tls: TlsOptions,
+}
+
+impl MempoolStream {
+    /// Create a stream against a ws:// or wss:// URL (see `websocket_url`)
+    pub fn with_url(url: &str) -> Self {
+        MempoolStream {
+            url: url.to_string(),
+            headers: Vec::new(),
+            tls: TlsOptions::default(),
+        }
+    }
+
+    /// Connect with these handshake headers and TLS options (see `mempool_auth::websocket_access`)
+    pub fn with_access(mut self, headers: Vec<(String, String)>, tls: TlsOptions) -> Self {
+        self.headers = headers;
+        self.tls = tls;
+        self
+    }
+
+    /// Stream updates, reconnecting with exponential backoff. Every event is
+    /// passed to `handler`; streaming stops once it returns false.
+    pub fn run<F: FnMut(MempoolEvent) -> bool>(&self, handler: F) {
+        Reconnect::new("Mempool stream").run(
+            |handler| self.stream_once(handler),
+            |event| matches!(event, MempoolEvent::Connected),
+            MempoolEvent::Disconnected,
+            handler,
+        );
+    }
+
+    // Connect, subscribe and forward updates until an error occurs or the handler stops us
+    fn stream_once(&self, handler: &mut dyn FnMut(MempoolEvent) -> bool) -> Result<()> {
+        println!("Connecting to mempool stream at: {}", self.url);
+
+        let mut socket = WebSocket::connect_with(ProxySource::Mempool, &self.url, READ_TIMEOUT, &self.tls, &self.headers)?;
+
+        // The server answers with the current data for each subscription, then pushes changes
+        let want = serde_json::json!({ "action": "want", "data": SUBSCRIPTIONS });
+        socket.send_text(&want.to_string())?;
+        println!("Subscribed to mempool updates: {}", SUBSCRIPTIONS.join(", "));
+
+        if !handler(MempoolEvent::Connected) {
+            socket.close();
+            return Ok(());
+        }
+
+        let mut last_message = Instant::now();
+        let mut ping_sent = false;
+        loop {
+            let text = match socket.read_message() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(_)) => continue,
+                Ok(Message::Close) => return Err(anyhow!("Server closed the connection")),
+                Err(e) if ws_client::is_timeout(&e) => {
+                    if last_message.elapsed() > IDLE_TIMEOUT {
+                        return Err(anyhow!("No messages received for {}s", IDLE_TIMEOUT.as_secs()));
+                    }
+                    if last_message.elapsed() > PING_INTERVAL && !ping_sent {
+                        socket.send_text(&serde_json::json!({ "action": "ping" }).to_string())?;
+                        ping_sent = true;
+                    }
+                    if !handler(MempoolEvent::Idle) {
+                        socket.close();
+                        return Ok(());
+                    }
+                    continue;
+                },
+                Err(e) => return Err(e),
+            };
+            last_message = Instant::now();
+            ping_sent = false;
+
+            let message: Value = match serde_json::from_str(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!("Ignoring unexpected stream message: {}", e);
+                    continue;
+                }
+            };
+
+            for event in parse_events(message) {
+                if !handler(event) {
+                    socket.close();
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// A single message can carry several kinds of update at once
+fn parse_events(mut message: Value) -> Vec<MempoolEvent> {
+    let mut events = Vec::new();
+
+    if let Some(blocks) = take_field::<Vec<MempoolBlockInfo>>(&mut message, "blocks") {
+        events.push(MempoolEvent::Blocks(blocks));
+    }
+    if let Some(block) = take_field::<MempoolBlockInfo>(&mut message, "block") {
+        events.push(MempoolEvent::Blocks(vec![block]));
+    }
+    if let Some(fees) = take_field(&mut message, "fees") {
+        events.push(MempoolEvent::Fees(fees));
+    }
+    if let Some(info) = take_field::<WsMempoolInfo>(&mut message, "mempoolInfo") {
+        events.push(MempoolEvent::MempoolSize {
+            count: info.size,
+            vsize: info.bytes,
+            total_fee: (info.total_fee * 100_000_000.0).round() as u64,
+        });
+    }
+    if let Some(blocks) = take_field(&mut message, "mempool-blocks") {
+        events.push(MempoolEvent::ProjectedBlocks(blocks));
+    }
+    if let Some(adjustment) = take_field(&mut message, "da") {
+        events.push(MempoolEvent::DifficultyAdjustment(adjustment));
+    }
+
+    events
+}
+
+// Remove and deserialize one field of a message, logging fields that don't parse
+fn take_field<T: for<'de> Deserialize<'de>>(message: &mut Value, key: &str) -> Option<T> {
+    let value = message.get_mut(key)?.take();
+    match serde_json::from_value(value) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            eprintln!("Ignoring unexpected '{}' in mempool stream: {}", key, e);
+            None
+        }
+    }
+}
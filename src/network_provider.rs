@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::bitcoin_rpc_client::{self, BitcoinRpcClient, RpcAuth, RpcAuthMethod};
+use crate::config::AppConfig;
+use crate::electrum_client::ElectrumClient;
+use crate::mempool_client::{MempoolBlockInfo, MempoolClient, MempoolFeeEstimate, MempoolStats};
+
+/// Common interface for the backends providing block, fee and mempool data
+pub trait NetworkProvider: Send {
+    fn fetch_latest_block(&self) -> Result<MempoolBlockInfo>;
+
+    /// Fetch up to 10 blocks ending at `start_height`, newest first
+    fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>>;
+
+    fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate>;
+
+    fn fetch_mempool_stats(&self) -> Result<MempoolStats>;
+}
+
+impl NetworkProvider for MempoolClient {
+    fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        MempoolClient::fetch_latest_block(self)
+    }
+
+    fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        MempoolClient::fetch_blocks(self, start_height)
+    }
+
+    fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
+        MempoolClient::fetch_fee_estimates(self)
+    }
+
+    fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        MempoolClient::fetch_mempool_stats(self)
+    }
+}
+
+impl NetworkProvider for BitcoinRpcClient {
+    fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        BitcoinRpcClient::fetch_latest_block(self)
+    }
+
+    fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        BitcoinRpcClient::fetch_blocks(self, start_height)
+    }
+
+    fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
+        BitcoinRpcClient::fetch_fee_estimates(self)
+    }
+
+    fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        BitcoinRpcClient::fetch_mempool_stats(self)
+    }
+}
+
+impl NetworkProvider for ElectrumClient {
+    fn fetch_latest_block(&self) -> Result<MempoolBlockInfo> {
+        ElectrumClient::fetch_latest_block(self)
+    }
+
+    fn fetch_blocks(&self, start_height: u32) -> Result<Vec<MempoolBlockInfo>> {
+        ElectrumClient::fetch_blocks(self, start_height)
+    }
+
+    fn fetch_fee_estimates(&self) -> Result<MempoolFeeEstimate> {
+        ElectrumClient::fetch_fee_estimates(self)
+    }
+
+    fn fetch_mempool_stats(&self) -> Result<MempoolStats> {
+        ElectrumClient::fetch_mempool_stats(self)
+    }
+}
+
+// Where block, fee and mempool data comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkBackend {
+    #[default]
+    Mempool,
+    BitcoinCore,
+    Electrum,
+}
+
+impl NetworkBackend {
+    pub const ALL: [NetworkBackend; 3] = [
+        NetworkBackend::Mempool,
+        NetworkBackend::BitcoinCore,
+        NetworkBackend::Electrum,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NetworkBackend::Mempool => "Mempool API",
+            NetworkBackend::BitcoinCore => "Bitcoin Core RPC",
+            NetworkBackend::Electrum => "Electrum server",
+        }
+    }
+
+    /// Whether address, wallet and transaction lookups work with this
+    /// backend. Bitcoin Core has no address index, and they must not quietly
+    /// go to a mempool instance instead.
+    pub fn supports_address_lookups(&self) -> bool {
+        *self != NetworkBackend::BitcoinCore
+    }
+
+    /// Whether the mining and fee histogram charts are available; only the
+    /// mempool API serves them
+    pub fn supports_mempool_charts(&self) -> bool {
+        *self == NetworkBackend::Mempool
+    }
+}
+
+/// Credentials for the configured Bitcoin Core RPC server
+pub fn rpc_auth(config: &AppConfig) -> RpcAuth {
+    match config.bitcoin_rpc_auth {
+        RpcAuthMethod::Cookie if config.bitcoin_rpc_cookie_file.trim().is_empty() => {
+            RpcAuth::Cookie(bitcoin_rpc_client::default_cookie_path())
+        },
+        RpcAuthMethod::Cookie => RpcAuth::Cookie(PathBuf::from(config.bitcoin_rpc_cookie_file.trim())),
+        RpcAuthMethod::UserPassword => RpcAuth::UserPassword {
+            user: config.bitcoin_rpc_user.clone(),
+            password: config.bitcoin_rpc_password.clone(),
+        },
+    }
+}
+
+/// Create a client for the configured backend; `mempool_url` is used for the mempool API
+pub fn create_network_provider(config: &AppConfig, mempool_url: &str) -> Box<dyn NetworkProvider> {
+    match config.network_backend {
+        NetworkBackend::Mempool => Box::new(MempoolClient::with_url(mempool_url)),
+        NetworkBackend::BitcoinCore => Box::new(BitcoinRpcClient::with_url(&config.bitcoin_rpc_url, rpc_auth(config))),
+        NetworkBackend::Electrum => Box::new(ElectrumClient::with_url(&config.electrum_server, config.electrum_accept_invalid_certs)),
+    }
+}
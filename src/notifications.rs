@@ -0,0 +1,43 @@
+use std::thread;
+use anyhow::Result;
+
+/// Show a desktop notification without blocking the caller
+pub fn notify(summary: &str, body: &str) {
+    let summary = summary.to_string();
+    let body = body.to_string();
+    thread::spawn(move || {
+        if let Err(e) = send_notification(&summary, &body) {
+            eprintln!("Failed to send desktop notification: {}", e);
+        }
+    });
+}
+
+// Send a notification through the freedesktop notification service on the session bus
+#[cfg(target_os = "linux")]
+fn send_notification(summary: &str, body: &str) -> Result<()> {
+    use std::collections::HashMap;
+    use anyhow::anyhow;
+    use zbus::zvariant::Value;
+
+    let connection = zbus::blocking::Connection::session()
+        .map_err(|e| anyhow!("Failed to connect to the session bus: {}", e))?;
+
+    let actions: Vec<&str> = Vec::new();
+    let hints: HashMap<&str, Value> = HashMap::new();
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        // app name, replaces id, icon, summary, body, actions, hints, expire timeout (-1 = default)
+        &("BTC Ticker", 0u32, "", summary, body, actions, hints, -1i32),
+    ).map_err(|e| anyhow!("Notification service call failed: {}", e))?;
+
+    Ok(())
+}
+
+// Desktop notifications are only implemented for Linux; the in-app banners still show
+#[cfg(not(target_os = "linux"))]
+fn send_notification(_summary: &str, _body: &str) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,480 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+
+use crate::config::AppConfig;
+use crate::price_provider::QuoteCurrency;
+
+// Amounts below this are treated as zero to absorb float rounding (1 satoshi)
+const DUST_BTC: f64 = 0.000_000_01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TradeSide::Buy => "Buy",
+            TradeSide::Sell => "Sell",
+        }
+    }
+}
+
+// A recorded buy or sell of bitcoin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub side: TradeSide,
+    // BTC bought or sold
+    pub amount: f64,
+    // Price per BTC in `currency`
+    pub price: f64,
+    // Fee paid in `currency`
+    #[serde(default)]
+    pub fee: f64,
+    // Unix timestamp of the trade
+    pub timestamp: i64,
+    pub currency: QuoteCurrency,
+}
+
+impl Trade {
+    /// Whether two trades record the same fill, used to skip re-imported rows
+    pub fn same_fill(&self, other: &Trade) -> bool {
+        self.side == other.side
+            && self.timestamp == other.timestamp
+            && self.currency == other.currency
+            && (self.amount - other.amount).abs() < DUST_BTC
+            && (self.price - other.price).abs() < 0.005
+    }
+}
+
+// How sold coins are matched against earlier buys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasisMethod {
+    #[default]
+    Fifo,
+    Lifo,
+    Average,
+}
+
+impl CostBasisMethod {
+    pub const ALL: [CostBasisMethod; 3] = [
+        CostBasisMethod::Fifo,
+        CostBasisMethod::Lifo,
+        CostBasisMethod::Average,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CostBasisMethod::Fifo => "FIFO",
+            CostBasisMethod::Lifo => "LIFO",
+            CostBasisMethod::Average => "Average cost",
+        }
+    }
+}
+
+// Holdings and profit of a set of trades at the current price
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PortfolioSummary {
+    // BTC still held
+    pub holdings: f64,
+    // Cost of the coins still held, fees included
+    pub cost_basis: f64,
+    // Cost basis per BTC held
+    pub average_cost: f64,
+    // Profit of the sales so far, fees included
+    pub realized_pnl: f64,
+    pub market_value: f64,
+    pub unrealized_pnl: f64,
+    pub unrealized_percent: f64,
+    // BTC sold beyond the recorded holdings, e.g. when buys are missing
+    pub unmatched_sells: f64,
+}
+
+/// Holdings, cost basis and P&L of `trades` valued at `price`, matching sells
+/// to buys with `method`. Trades are processed in chronological order.
+pub fn summarize(trades: &[Trade], method: CostBasisMethod, price: f64) -> PortfolioSummary {
+    let mut ordered: Vec<&Trade> = trades.iter().collect();
+    ordered.sort_by_key(|trade| trade.timestamp);
+
+    // Open lots as (BTC, cost); the average method keeps a single pooled lot
+    let mut lots: VecDeque<(f64, f64)> = VecDeque::new();
+    let mut realized_pnl = 0.0;
+    let mut unmatched_sells = 0.0;
+
+    for trade in ordered {
+        match trade.side {
+            TradeSide::Buy => {
+                let cost = trade.amount * trade.price + trade.fee;
+                match (method, lots.front_mut()) {
+                    (CostBasisMethod::Average, Some(pool)) => {
+                        pool.0 += trade.amount;
+                        pool.1 += cost;
+                    },
+                    _ => lots.push_back((trade.amount, cost)),
+                }
+            },
+            TradeSide::Sell => {
+                let mut remaining = trade.amount;
+                let mut matched_cost = 0.0;
+                while remaining > DUST_BTC {
+                    let lot = match method {
+                        CostBasisMethod::Lifo => lots.back_mut(),
+                        _ => lots.front_mut(),
+                    };
+                    let Some(lot) = lot else { break };
+
+                    let taken = remaining.min(lot.0);
+                    let cost = lot.1 * taken / lot.0;
+                    lot.0 -= taken;
+                    lot.1 -= cost;
+                    matched_cost += cost;
+                    remaining -= taken;
+
+                    if lot.0 <= DUST_BTC {
+                        match method {
+                            CostBasisMethod::Lifo => lots.pop_back(),
+                            _ => lots.pop_front(),
+                        };
+                    }
+                }
+
+                let matched = trade.amount - remaining.max(0.0);
+                if remaining > DUST_BTC {
+                    unmatched_sells += remaining;
+                }
+                // Only the matched part of the sale has a known cost
+                let proceeds = matched * trade.price - trade.fee;
+                realized_pnl += proceeds - matched_cost;
+            },
+        }
+    }
+
+    let holdings: f64 = lots.iter().map(|lot| lot.0).sum();
+    let cost_basis: f64 = lots.iter().map(|lot| lot.1).sum();
+    let market_value = holdings * price;
+    let unrealized_pnl = market_value - cost_basis;
+
+    PortfolioSummary {
+        holdings,
+        cost_basis,
+        average_cost: if holdings > DUST_BTC { cost_basis / holdings } else { 0.0 },
+        realized_pnl,
+        market_value,
+        unrealized_pnl,
+        unrealized_percent: if cost_basis > 0.0 { unrealized_pnl / cost_basis * 100.0 } else { 0.0 },
+        unmatched_sells,
+    }
+}
+
+/// Trades persisted as JSON in the config directory
+pub struct PortfolioStore {
+    path: PathBuf,
+}
+
+impl PortfolioStore {
+    /// Open the store at portfolio.json next to config.json
+    pub fn new() -> Self {
+        let mut path = AppConfig::get_config_dir();
+        path.push("portfolio.json");
+        PortfolioStore { path }
+    }
+
+    /// Load recorded trades, or an empty list if none are stored
+    pub fn load(&self) -> Vec<Trade> {
+        if !self.path.exists() {
+            return Vec::new();
+        }
+
+        match fs::read_to_string(&self.path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(trades) => trades,
+                Err(e) => {
+                    eprintln!("Error parsing portfolio: {}", e);
+                    Vec::new()
+                }
+            },
+            Err(e) => {
+                eprintln!("Error reading portfolio: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn save(&self, trades: &[Trade]) -> Result<()> {
+        let content = serde_json::to_string_pretty(trades)
+            .map_err(|e| anyhow!("Failed to serialize portfolio: {}", e))?;
+        fs::write(&self.path, content)
+            .map_err(|e| anyhow!("Failed to write portfolio: {}", e))?;
+        Ok(())
+    }
+}
+
+// Result of parsing an exchange export
+#[derive(Debug, Clone, Default)]
+pub struct CsvImport {
+    pub trades: Vec<Trade>,
+    // Rows that aren't BTC trades in a supported currency (deposits, withdrawals, ...)
+    pub skipped: usize,
+}
+
+/// Parse a Bitstamp transaction history export. Both the legacy layout
+/// (Type, Datetime, Account, Amount, Value, Rate, Fee, Sub Type) with amounts
+/// like "0.5 BTC" and the newer one with separate currency columns are accepted.
+pub fn parse_bitstamp_csv(content: &str) -> Result<CsvImport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers()
+        .map_err(|e| anyhow!("Failed to read CSV header: {}", e))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|header| header.eq_ignore_ascii_case(name));
+
+    let type_col = column("Type").ok_or_else(|| anyhow!("Not a Bitstamp export: missing Type column"))?;
+    let datetime_col = column("Datetime").ok_or_else(|| anyhow!("Not a Bitstamp export: missing Datetime column"))?;
+    let amount_col = column("Amount").ok_or_else(|| anyhow!("Not a Bitstamp export: missing Amount column"))?;
+    let rate_col = column("Rate").ok_or_else(|| anyhow!("Not a Bitstamp export: missing Rate column"))?;
+    let side_col = column("Sub Type").or_else(|| column("Subtype"))
+        .ok_or_else(|| anyhow!("Not a Bitstamp export: missing Sub Type column"))?;
+    let fee_col = column("Fee");
+    // Only present in the newer layout
+    let amount_currency_col = column("Amount currency");
+    let rate_currency_col = column("Rate currency");
+    let fee_currency_col = column("Fee currency");
+
+    let mut import = CsvImport::default();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| anyhow!("Failed to read CSV row {}: {}", index + 2, e))?;
+        let field = |col: usize| record.get(col).unwrap_or("");
+
+        // Only executed orders are trades
+        let side = match (field(type_col), field(side_col).to_ascii_lowercase().as_str()) {
+            ("Market" | "Limit" | "Instant", "buy") => TradeSide::Buy,
+            ("Market" | "Limit" | "Instant", "sell") => TradeSide::Sell,
+            _ => {
+                import.skipped += 1;
+                continue;
+            }
+        };
+
+        let (amount, amount_currency) = split_amount(field(amount_col), amount_currency_col.map(field));
+        let (price, price_currency) = split_amount(field(rate_col), rate_currency_col.map(field));
+        let currency = QuoteCurrency::ALL.iter().copied()
+            .find(|currency| currency.code().eq_ignore_ascii_case(price_currency));
+        let (Some(amount), Some(price), Some(currency)) = (amount, price, currency) else {
+            import.skipped += 1;
+            continue;
+        };
+        if !amount_currency.eq_ignore_ascii_case("BTC") {
+            import.skipped += 1;
+            continue;
+        }
+
+        let fee = match fee_col {
+            Some(col) => {
+                let (fee, fee_currency) = split_amount(field(col), fee_currency_col.map(field));
+                // Fees charged in BTC are converted at the trade price
+                if fee_currency.eq_ignore_ascii_case("BTC") {
+                    fee.unwrap_or(0.0) * price
+                } else {
+                    fee.unwrap_or(0.0)
+                }
+            },
+            None => 0.0,
+        };
+
+        let timestamp = parse_bitstamp_datetime(field(datetime_col))
+            .ok_or_else(|| anyhow!("Invalid date on CSV row {}: {}", index + 2, field(datetime_col)))?;
+
+        import.trades.push(Trade {
+            side,
+            amount: amount.abs(),
+            price,
+            fee: fee.abs(),
+            timestamp,
+            currency,
+        });
+    }
+
+    Ok(import)
+}
+
+// Split "0.50000000 BTC" into its number and currency; the newer layout
+// keeps the currency in a separate column
+fn split_amount<'a>(value: &'a str, currency_column: Option<&'a str>) -> (Option<f64>, &'a str) {
+    let mut parts = value.split_whitespace();
+    let number = parts.next().and_then(|number| number.replace(',', "").parse::<f64>().ok());
+    let currency = currency_column.unwrap_or_else(|| parts.next().unwrap_or(""));
+    (number, currency)
+}
+
+// Bitstamp dates look like "Jan. 15, 2021, 10:00 AM" (legacy, UTC) or RFC 3339
+fn parse_bitstamp_datetime(value: &str) -> Option<i64> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.timestamp());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        return Some(datetime.and_utc().timestamp());
+    }
+
+    // Month names are abbreviated inconsistently ("Jan.", "May", "Sept."), so
+    // reduce them to the three letters chrono expects
+    let (month, rest) = value.split_once(' ')?;
+    let month: String = month.trim_end_matches('.').chars().take(3).collect();
+    NaiveDateTime::parse_from_str(&format!("{} {}", month, rest), "%b %d, %Y, %I:%M %p")
+        .ok()
+        .map(|datetime| datetime.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(side: TradeSide, amount: f64, price: f64, fee: f64, timestamp: i64) -> Trade {
+        Trade { side, amount, price, fee, timestamp, currency: QuoteCurrency::Usd }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    // Two buys at different prices followed by a partial sell, given out of order
+    fn trades() -> Vec<Trade> {
+        vec![
+            trade(TradeSide::Sell, 1.0, 300.0, 2.0, 3),
+            trade(TradeSide::Buy, 1.0, 200.0, 1.0, 2),
+            trade(TradeSide::Buy, 1.0, 100.0, 1.0, 1),
+        ]
+    }
+
+    #[test]
+    fn fifo_sells_the_oldest_coins() {
+        let summary = summarize(&trades(), CostBasisMethod::Fifo, 400.0);
+        assert_close(summary.holdings, 1.0);
+        assert_close(summary.cost_basis, 201.0);
+        assert_close(summary.average_cost, 201.0);
+        // 300 - 2 fee proceeds against the 100 + 1 fee lot
+        assert_close(summary.realized_pnl, 197.0);
+        assert_close(summary.market_value, 400.0);
+        assert_close(summary.unrealized_pnl, 199.0);
+        assert_close(summary.unrealized_percent, 199.0 / 201.0 * 100.0);
+        assert_close(summary.unmatched_sells, 0.0);
+    }
+
+    #[test]
+    fn lifo_sells_the_newest_coins() {
+        let summary = summarize(&trades(), CostBasisMethod::Lifo, 400.0);
+        assert_close(summary.cost_basis, 101.0);
+        assert_close(summary.realized_pnl, 97.0);
+        assert_close(summary.unrealized_pnl, 299.0);
+    }
+
+    #[test]
+    fn average_pools_all_buys() {
+        let summary = summarize(&trades(), CostBasisMethod::Average, 400.0);
+        assert_close(summary.holdings, 1.0);
+        assert_close(summary.cost_basis, 151.0);
+        assert_close(summary.realized_pnl, 147.0);
+        assert_close(summary.unrealized_pnl, 249.0);
+    }
+
+    #[test]
+    fn sell_spanning_several_lots() {
+        let trades = vec![
+            trade(TradeSide::Buy, 0.5, 100.0, 0.0, 1),
+            trade(TradeSide::Buy, 0.5, 200.0, 0.0, 2),
+            trade(TradeSide::Buy, 1.0, 300.0, 0.0, 3),
+            trade(TradeSide::Sell, 1.25, 400.0, 0.0, 4),
+        ];
+
+        let summary = summarize(&trades, CostBasisMethod::Fifo, 400.0);
+        assert_close(summary.holdings, 0.75);
+        assert_close(summary.cost_basis, 225.0);
+        assert_close(summary.realized_pnl, 500.0 - (50.0 + 100.0 + 75.0));
+    }
+
+    #[test]
+    fn reports_unmatched_sells() {
+        let trades = vec![
+            trade(TradeSide::Buy, 1.0, 100.0, 0.0, 1),
+            trade(TradeSide::Sell, 1.5, 200.0, 3.0, 2),
+        ];
+
+        let summary = summarize(&trades, CostBasisMethod::Fifo, 250.0);
+        assert_close(summary.unmatched_sells, 0.5);
+        assert_close(summary.holdings, 0.0);
+        assert_close(summary.average_cost, 0.0);
+        assert_close(summary.unrealized_percent, 0.0);
+        // Only the matched coin counts, with the whole fee
+        assert_close(summary.realized_pnl, 200.0 - 3.0 - 100.0);
+    }
+
+    #[test]
+    fn empty_portfolio() {
+        assert_eq!(summarize(&[], CostBasisMethod::Lifo, 400.0), PortfolioSummary::default());
+    }
+
+    #[test]
+    fn parses_legacy_export() {
+        let csv = "\
+Type,Datetime,Account,Amount,Value,Rate,Fee,Sub Type
+Deposit,\"Jan. 14, 2021, 09:00 AM\",Main Account,\"1,000.00 USD\",,,,
+Market,\"Jan. 15, 2021, 10:00 AM\",Main Account,0.50000000 BTC,\"15,000.00 USD\",\"30,000.00 USD\",75.00 USD,Buy
+Limit,\"Sept. 3, 2021, 04:30 PM\",Main Account,0.25000000 BTC,12500.00 EUR,50000.00 EUR,0.00100000 BTC,Sell
+";
+
+        let import = parse_bitstamp_csv(csv).unwrap();
+        assert_eq!(import.skipped, 1);
+        assert_eq!(import.trades, vec![
+            Trade { side: TradeSide::Buy, amount: 0.5, price: 30_000.0, fee: 75.0, timestamp: 1_610_704_800, currency: QuoteCurrency::Usd },
+            // The BTC fee is converted at the trade price
+            Trade { side: TradeSide::Sell, amount: 0.25, price: 50_000.0, fee: 50.0, timestamp: 1_630_686_600, currency: QuoteCurrency::Eur },
+        ]);
+    }
+
+    #[test]
+    fn parses_current_export() {
+        let csv = "\
+ID,Account,Type,Subtype,Datetime,Amount,Amount currency,Value,Value currency,Rate,Rate currency,Fee,Fee currency,Order ID
+1,Main Account,Market,Buy,2023-05-01T12:00:00Z,0.10000000,BTC,2900.00,USD,29000.00,USD,5.80,USD,11
+2,Main Account,Market,Sell,2023-05-01T12:00:00Z,1.00000000,ETH,1900.00,USD,1900.00,USD,1.00,USD,12
+3,Main Account,Instant,Sell,2022-12-31 23:59:59,-0.05000000,BTC,1000.00,CHF,20000.00,CHF,0,CHF,13
+4,Main Account,Withdrawal,,2023-05-02T00:00:00Z,0.10000000,BTC,,,,,,,
+";
+
+        let import = parse_bitstamp_csv(csv).unwrap();
+        // ETH trade, CHF market and withdrawal
+        assert_eq!(import.skipped, 3);
+        assert_eq!(import.trades, vec![
+            Trade { side: TradeSide::Buy, amount: 0.1, price: 29_000.0, fee: 5.8, timestamp: 1_682_942_400, currency: QuoteCurrency::Usd },
+        ]);
+    }
+
+    #[test]
+    fn parses_every_month_abbreviation() {
+        let months = ["Jan.", "Feb.", "March", "April", "May", "June", "July", "Aug.", "Sept.", "Oct.", "Nov.", "Dec."];
+        let mut previous = 0;
+        for month in months {
+            let timestamp = parse_bitstamp_datetime(&format!("{} 1, 2021, 12:00 PM", month))
+                .unwrap_or_else(|| panic!("failed to parse {}", month));
+            assert!(timestamp > previous);
+            previous = timestamp;
+        }
+        assert_eq!(parse_bitstamp_datetime("Sept. 3, 2021, 04:30 PM"), Some(1_630_686_600));
+        assert_eq!(parse_bitstamp_datetime("yesterday"), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse_bitstamp_csv("Date,Description,Amount\n2021-01-01,Coffee,3.50\n").is_err());
+    }
+}
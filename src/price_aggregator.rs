@@ -0,0 +1,190 @@
+use std::thread;
+
+use crate::price_provider::{PriceSource, QuoteCurrency};
+
+// Price reported by a single exchange during an aggregated refresh
+#[derive(Debug, Clone)]
+pub struct SourceQuote {
+    pub source: PriceSource,
+    pub price: Option<f64>,
+    pub error: Option<String>,
+    // Set when the price deviated too far from the median of all sources
+    pub rejected: bool,
+}
+
+// Result of combining the quotes of several exchanges
+#[derive(Debug, Clone)]
+pub struct AggregatedPrice {
+    pub median: Option<f64>,
+    // Difference between the highest and lowest accepted price
+    pub spread: f64,
+    pub quotes: Vec<SourceQuote>,
+}
+
+impl AggregatedPrice {
+    pub fn accepted_count(&self) -> usize {
+        self.quotes.iter().filter(|quote| quote.price.is_some() && !quote.rejected).count()
+    }
+}
+
+/// Query the current price in `currency` from all given sources in parallel,
+/// skipping exchanges that have no market in that currency
+pub fn fetch_quotes(sources: &[PriceSource], currency: QuoteCurrency) -> Vec<SourceQuote> {
+    let handles: Vec<_> = sources.iter()
+        .filter(|source| source.supports(currency))
+        .map(|&source| {
+            let handle = thread::spawn(move || source.create_provider().fetch_current_price(currency));
+            (source, handle)
+        })
+        .collect();
+
+    handles.into_iter()
+        .map(|(source, handle)| {
+            let result = handle.join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Price fetch thread panicked")));
+            match result {
+                Ok(price) => SourceQuote { source, price: Some(price), error: None, rejected: false },
+                Err(e) => SourceQuote { source, price: None, error: Some(e.to_string()), rejected: false },
+            }
+        })
+        .collect()
+}
+
+/// Combine quotes into a median price, rejecting prices that deviate from the
+/// median of all sources by more than `max_deviation_percent`
+pub fn aggregate(mut quotes: Vec<SourceQuote>, max_deviation_percent: f64) -> AggregatedPrice {
+    let prices: Vec<f64> = quotes.iter().filter_map(|quote| quote.price).collect();
+
+    let Some(initial_median) = median(&prices) else {
+        return AggregatedPrice { median: None, spread: 0.0, quotes };
+    };
+
+    for quote in quotes.iter_mut() {
+        if let Some(price) = quote.price {
+            let deviation = (price - initial_median).abs() / initial_median * 100.0;
+            quote.rejected = deviation > max_deviation_percent;
+        }
+    }
+
+    let accepted: Vec<f64> = quotes.iter()
+        .filter(|quote| !quote.rejected)
+        .filter_map(|quote| quote.price)
+        .collect();
+
+    let spread = match (
+        accepted.iter().cloned().reduce(f64::max),
+        accepted.iter().cloned().reduce(f64::min),
+    ) {
+        (Some(max), Some(min)) => max - min,
+        _ => 0.0,
+    };
+
+    AggregatedPrice {
+        median: median(&accepted),
+        spread,
+        quotes,
+    }
+}
+
+// Median of a list of prices, averaging the middle two for even counts
+fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let mid = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => Some((sorted[mid - 1] + sorted[mid]) / 2.0),
+        _ => Some(sorted[mid]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: PriceSource, price: Option<f64>) -> SourceQuote {
+        SourceQuote {
+            source,
+            price,
+            error: price.is_none().then(|| "timed out".to_string()),
+            rejected: false,
+        }
+    }
+
+    #[test]
+    fn skips_unsupported_currencies() {
+        // Neither exchange lists BTC/JPY, so no request is made and nothing is quoted
+        let quotes = fetch_quotes(&[PriceSource::Coinbase, PriceSource::Binance], QuoteCurrency::Jpy);
+        assert!(quotes.is_empty());
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        assert_eq!(median(&[7.0]), Some(7.0));
+        assert_eq!(median(&[]), None);
+    }
+
+    #[test]
+    fn rejects_outlier() {
+        let quotes = vec![
+            quote(PriceSource::Bitstamp, Some(100.0)),
+            quote(PriceSource::Kraken, Some(101.0)),
+            quote(PriceSource::Coinbase, Some(99.0)),
+            quote(PriceSource::Binance, Some(150.0)),
+        ];
+
+        let aggregated = aggregate(quotes, 2.0);
+        assert!(aggregated.quotes[3].rejected);
+        assert!(aggregated.quotes[..3].iter().all(|quote| !quote.rejected));
+        assert_eq!(aggregated.accepted_count(), 3);
+        assert_eq!(aggregated.median, Some(100.0));
+        assert_eq!(aggregated.spread, 2.0);
+    }
+
+    #[test]
+    fn keeps_prices_within_threshold() {
+        let quotes = vec![
+            quote(PriceSource::Bitstamp, Some(100.0)),
+            quote(PriceSource::Kraken, Some(101.0)),
+        ];
+
+        let aggregated = aggregate(quotes, 2.0);
+        assert_eq!(aggregated.accepted_count(), 2);
+        assert_eq!(aggregated.median, Some(100.5));
+        assert_eq!(aggregated.spread, 1.0);
+    }
+
+    #[test]
+    fn every_source_failing() {
+        let quotes = vec![
+            quote(PriceSource::Bitstamp, None),
+            quote(PriceSource::Kraken, None),
+        ];
+
+        let aggregated = aggregate(quotes, 2.0);
+        assert_eq!(aggregated.median, None);
+        assert_eq!(aggregated.accepted_count(), 0);
+        assert_eq!(aggregated.spread, 0.0);
+        assert!(aggregated.quotes.iter().all(|quote| quote.error.is_some()));
+    }
+
+    #[test]
+    fn single_source_responding() {
+        let quotes = vec![
+            quote(PriceSource::Bitstamp, None),
+            quote(PriceSource::Kraken, Some(100.0)),
+            quote(PriceSource::Coinbase, None),
+        ];
+
+        let aggregated = aggregate(quotes, 2.0);
+        assert_eq!(aggregated.median, Some(100.0));
+        assert_eq!(aggregated.accepted_count(), 1);
+        assert_eq!(aggregated.spread, 0.0);
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::bitstamp_client::BitstampClient;
+use crate::kraken_client::KrakenClient;
+use crate::coinbase_client::CoinbaseClient;
+use crate::binance_client::BinanceClient;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartTimeframe {
+    Hours24,
+    Week,
+    Month,
+    Year,
+}
+
+impl ChartTimeframe {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ChartTimeframe::Hours24 => "24 Hours (hourly)",
+            ChartTimeframe::Week => "1 Week (4-hour)",
+            ChartTimeframe::Month => "1 Month (daily)",
+            ChartTimeframe::Year => "1 Year (daily)",
+        }
+    }
+
+    pub fn api_params(&self) -> (u32, u32) {
+        match self {
+            ChartTimeframe::Hours24 => (3600, 24),      // 1 hour steps, 24 candles
+            ChartTimeframe::Week => (14400, 42),        // 4 hour steps, 42 candles (1 week)
+            ChartTimeframe::Month => (86400, 30),       // 24 hour steps, 30 candles (1 month)
+            ChartTimeframe::Year => (86400, 365),       // 1 day steps, 365 candles (1 year)
+        }
+    }
+}
+
+// Fiat currency BTC is quoted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteCurrency {
+    #[default]
+    Usd,
+    Eur,
+    Gbp,
+    Jpy,
+}
+
+impl QuoteCurrency {
+    pub const ALL: [QuoteCurrency; 4] = [
+        QuoteCurrency::Usd,
+        QuoteCurrency::Eur,
+        QuoteCurrency::Gbp,
+        QuoteCurrency::Jpy,
+    ];
+
+    /// ISO 4217 currency code (e.g. "USD")
+    pub fn code(&self) -> &'static str {
+        match self {
+            QuoteCurrency::Usd => "USD",
+            QuoteCurrency::Eur => "EUR",
+            QuoteCurrency::Gbp => "GBP",
+            QuoteCurrency::Jpy => "JPY",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            QuoteCurrency::Usd => "$",
+            QuoteCurrency::Eur => "€",
+            QuoteCurrency::Gbp => "£",
+            QuoteCurrency::Jpy => "¥",
+        }
+    }
+
+    /// Format an amount with the currency symbol, e.g. "$65000.00" or "¥9800000"
+    pub fn format_price(&self, value: f64) -> String {
+        match self {
+            // Yen has no minor unit
+            QuoteCurrency::Jpy => format!("{}{:.0}", self.symbol(), value),
+            _ => format!("{}{:.2}", self.symbol(), value),
+        }
+    }
+}
+
+// A single OHLC candle, normalized across all exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceCandle {
+    pub timestamp: i64, // Unix timestamp of the candle open (seconds)
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// Common interface for all exchange price backends
+pub trait PriceProvider: Send {
+    /// Human readable name of the exchange
+    fn name(&self) -> &'static str;
+
+    /// Fetch the last traded BTC price in the given currency
+    fn fetch_current_price(&self, currency: QuoteCurrency) -> Result<f64>;
+
+    /// Fetch OHLC candles for the given timeframe and currency, oldest first
+    fn fetch_historical_prices(&self, timeframe: ChartTimeframe, currency: QuoteCurrency) -> Result<Vec<PriceCandle>>;
+
+    /// Fetch candles of the timeframe's step opening at or after `start`, oldest first.
+    /// Backends without a start parameter fetch the whole timeframe and filter it.
+    fn fetch_candles_since(&self, timeframe: ChartTimeframe, currency: QuoteCurrency, start: i64) -> Result<Vec<PriceCandle>> {
+        let candles = self.fetch_historical_prices(timeframe, currency)?;
+        Ok(candles.into_iter().filter(|candle| candle.timestamp >= start).collect())
+    }
+}
+
+// Exchanges the ticker can get its price data from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    #[default]
+    Bitstamp,
+    Kraken,
+    Coinbase,
+    Binance,
+}
+
+impl PriceSource {
+    pub const ALL: [PriceSource; 4] = [
+        PriceSource::Bitstamp,
+        PriceSource::Kraken,
+        PriceSource::Coinbase,
+        PriceSource::Binance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceSource::Bitstamp => "Bitstamp",
+            PriceSource::Kraken => "Kraken",
+            PriceSource::Coinbase => "Coinbase",
+            PriceSource::Binance => "Binance",
+        }
+    }
+
+    /// Whether the exchange lists a BTC spot market in the given currency
+    pub fn supports(&self, currency: QuoteCurrency) -> bool {
+        match self {
+            PriceSource::Kraken => true,
+            PriceSource::Bitstamp | PriceSource::Coinbase => currency != QuoteCurrency::Jpy,
+            // Binance only quotes BTC against USDT and EUR among our currencies
+            PriceSource::Binance => matches!(currency, QuoteCurrency::Usd | QuoteCurrency::Eur),
+        }
+    }
+
+    /// Fail with an "unsupported currency" error before making a request the exchange can't serve
+    pub fn ensure_supports(&self, currency: QuoteCurrency) -> Result<()> {
+        if self.supports(currency) {
+            Ok(())
+        } else {
+            Err(anyhow!("Unsupported currency: {} has no BTC/{} market", self.name(), currency.code()))
+        }
+    }
+
+    /// Create a client for this source using the exchange's public API
+    pub fn create_provider(&self) -> Box<dyn PriceProvider> {
+        match self {
+            PriceSource::Bitstamp => Box::new(BitstampClient::new()),
+            PriceSource::Kraken => Box::new(KrakenClient::new()),
+            PriceSource::Coinbase => Box::new(CoinbaseClient::new()),
+            PriceSource::Binance => Box::new(BinanceClient::new()),
+        }
+    }
+}
+
+// Helper to parse a numeric string field from an exchange response
+pub fn parse_price_field(value: &str, field: &str) -> Result<f64> {
+    value.parse::<f64>()
+        .map_err(|e| anyhow!("Failed to parse {} value '{}': {}", field, value, e))
+}
@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize};
+
+use crate::price_provider::QuoteCurrency;
+use crate::proxy::ProxySource;
+use crate::reconnect::Reconnect;
+use crate::ws_client::{self, Message, WebSocket};
+
+// Default Bitstamp WebSocket API URL
+pub const DEFAULT_BITSTAMP_WS_URL: &str = "wss://ws.bitstamp.net";
+
+// How long to wait for any message before checking whether to keep streaming
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Reconnect if the socket has been silent for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+// Envelope of every message on the Bitstamp WebSocket
+#[derive(Debug, Deserialize)]
+pub struct BitstampWsMessage {
+    pub event: String,
+    #[serde(default)]
+    pub channel: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BitstampTrade {
+    pub price: f64,
+    // Unix timestamp of the trade as a string
+    pub timestamp: String,
+}
+
+// A single trade received from the stream
+#[derive(Debug, Clone, Copy)]
+pub struct Trade {
+    pub timestamp: i64,
+    pub price: f64,
+}
+
+#[derive(Debug)]
+pub enum StreamEvent {
+    Connected,
+    Trade(Trade),
+    // No message arrived within the read timeout
+    Idle,
+    Disconnected(String),
+}
+
+/// Streams live trades from Bitstamp's `live_trades_<pair>` channel
+pub struct BitstampPriceStream {
+    url: String,
+}
+
+impl BitstampPriceStream {
+    /// Create a stream using the default Bitstamp WebSocket URL
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_BITSTAMP_WS_URL)
+    }
+
+    /// Create a stream against a custom URL (e.g. a local WebSocket stand-in)
+    pub fn with_url(url: &str) -> Self {
+        BitstampPriceStream { url: url.to_string() }
+    }
+
+    /// Stream trades for `currency`, reconnecting with exponential backoff.
+    /// Every event is passed to `handler`; streaming stops once it returns false.
+    pub fn run<F: FnMut(StreamEvent) -> bool>(&self, currency: QuoteCurrency, handler: F) {
+        Reconnect::new("Bitstamp price stream").run(
+            |handler| self.stream_once(currency, handler),
+            |event| matches!(event, StreamEvent::Connected),
+            StreamEvent::Disconnected,
+            handler,
+        );
+    }
+
+    // Connect, subscribe and forward trades until an error occurs or the handler stops us
+    fn stream_once(&self, currency: QuoteCurrency, handler: &mut dyn FnMut(StreamEvent) -> bool) -> Result<()> {
+        println!("Connecting to Bitstamp price stream at: {}", self.url);
+
+        let mut socket = WebSocket::connect(ProxySource::Exchanges, &self.url, READ_TIMEOUT)?;
+        let channel = format!("live_trades_btc{}", currency.code().to_lowercase());
+
+        let subscribe = serde_json::json!({
+            "event": "bts:subscribe",
+            "data": { "channel": channel },
+        });
+        socket.send_text(&subscribe.to_string())?;
+
+        let mut last_message = Instant::now();
+        loop {
+            let text = match socket.read_message() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(_)) => continue,
+                Ok(Message::Close) => return Err(anyhow!("Server closed the connection")),
+                Err(e) if ws_client::is_timeout(&e) => {
+                    if last_message.elapsed() > IDLE_TIMEOUT {
+                        return Err(anyhow!("No messages received for {}s", IDLE_TIMEOUT.as_secs()));
+                    }
+                    if !handler(StreamEvent::Idle) {
+                        socket.close();
+                        return Ok(());
+                    }
+                    continue;
+                },
+                Err(e) => return Err(e),
+            };
+            last_message = Instant::now();
+
+            let message: BitstampWsMessage = match serde_json::from_str(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!("Ignoring unexpected stream message: {}", e);
+                    continue;
+                }
+            };
+
+            let event = match message.event.as_str() {
+                "bts:subscription_succeeded" => {
+                    println!("Subscribed to Bitstamp channel: {}", message.channel);
+                    StreamEvent::Connected
+                },
+                "trade" if message.channel == channel => {
+                    let trade: BitstampTrade = serde_json::from_value(message.data)
+                        .map_err(|e| anyhow!("Failed to parse trade: {}", e))?;
+                    let timestamp = trade.timestamp.parse::<i64>()
+                        .map_err(|e| anyhow!("Failed to parse trade timestamp: {}", e))?;
+                    StreamEvent::Trade(Trade { timestamp, price: trade.price })
+                },
+                // Bitstamp asks clients to reconnect before maintenance
+                "bts:request_reconnect" => return Err(anyhow!("Server requested a reconnect")),
+                _ => continue,
+            };
+
+            if !handler(event) {
+                socket.close();
+                return Ok(());
+            }
+        }
+    }
+}
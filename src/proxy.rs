@@ -0,0 +1,474 @@
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use url::Url;
+
+use crate::config::AppConfig;
+
+// Tor's default SOCKS port on the local machine
+pub const DEFAULT_PROXY_HOST: &str = "127.0.0.1";
+pub const DEFAULT_PROXY_PORT: u16 = 9050;
+
+// Connection and handshake timeout for the proxy itself
+const PROXY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Groups of outgoing connections that can be routed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxySource {
+    // Price, chart and trade stream requests to the exchanges
+    Exchanges,
+    // Mempool API and WebSocket
+    Mempool,
+    BitcoinCore,
+    Electrum,
+}
+
+impl ProxySource {
+    pub const ALL: [ProxySource; 4] = [
+        ProxySource::Exchanges,
+        ProxySource::Mempool,
+        ProxySource::BitcoinCore,
+        ProxySource::Electrum,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProxySource::Exchanges => "Exchanges",
+            ProxySource::Mempool => "Mempool API",
+            ProxySource::BitcoinCore => "Bitcoin Core RPC",
+            ProxySource::Electrum => "Electrum server",
+        }
+    }
+}
+
+// Route for one source in place of the global proxy setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "route", rename_all = "snake_case")]
+pub enum ProxyRoute {
+    // Connect without a proxy
+    Direct,
+    // Use a different SOCKS5 proxy
+    Socks5 { host: String, port: u16 },
+}
+
+// A SOCKS5 proxy that resolves host names itself (socks5h)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocksProxy {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, PartialEq)]
+struct ProxySettings {
+    global: Option<SocksProxy>,
+    tor_only: bool,
+    overrides: BTreeMap<ProxySource, ProxyRoute>,
+}
+
+// Settings every new connection is routed by
+static SETTINGS: RwLock<ProxySettings> = RwLock::new(ProxySettings {
+    global: None,
+    tor_only: false,
+    overrides: BTreeMap::new(),
+});
+
+// Bumped whenever the settings change
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Route connections opened from now on according to the proxy options in `config`
+pub fn configure(config: &AppConfig) {
+    let settings = ProxySettings {
+        global: config.proxy_enabled.then(|| SocksProxy {
+            host: config.proxy_host.trim().to_string(),
+            port: config.proxy_port,
+        }),
+        tor_only: config.proxy_tor_only,
+        overrides: config.proxy_overrides.clone(),
+    };
+
+    let mut current = SETTINGS.write().unwrap();
+    if *current != settings {
+        *current = settings;
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Changes whenever the proxy settings do, so long-lived connections know to reconnect
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Proxy to reach `host` through for `source`, or None to connect directly.
+/// Fails when the settings don't allow reaching `host` at all.
+pub fn route(source: ProxySource, host: &str) -> Result<Option<SocksProxy>> {
+    SETTINGS.read().unwrap().route(source, host)
+}
+
+impl ProxySettings {
+    fn route(&self, source: ProxySource, host: &str) -> Result<Option<SocksProxy>> {
+        let proxy = match self.overrides.get(&source) {
+            Some(ProxyRoute::Direct) => None,
+            Some(ProxyRoute::Socks5 { host, port }) => Some(SocksProxy { host: host.trim().to_string(), port: *port }),
+            None => self.global.clone(),
+        };
+
+        if proxy.is_none() {
+            if is_onion(host) {
+                return Err(anyhow!("{} is an onion address and can only be reached through a Tor proxy", host));
+            }
+            // A node on this machine never touches the clearnet
+            if self.tor_only && !is_local(host) {
+                return Err(anyhow!("Tor only mode refuses a direct connection to {}", host));
+            }
+        }
+        Ok(proxy)
+    }
+}
+
+/// Whether `host` is a Tor onion service
+pub fn is_onion(host: &str) -> bool {
+    host.trim_end_matches('.').to_ascii_lowercase().ends_with(".onion")
+}
+
+fn is_local(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().map(|ip| ip.is_loopback()).unwrap_or(false)
+}
+
+/// Open a TCP connection to `host:port` through a SOCKS5 proxy. Host names are
+/// passed to the proxy unresolved, so DNS lookups don't leak and onion addresses work.
+pub fn connect_socks5(proxy: &SocksProxy, host: &str, port: u16) -> Result<TcpStream> {
+    let address = (proxy.host.as_str(), proxy.port).to_socket_addrs()
+        .map_err(|e| anyhow!("Failed to resolve proxy {}: {}", proxy.host, e))?
+        .next()
+        .ok_or_else(|| anyhow!("No addresses found for proxy {}", proxy.host))?;
+
+    let mut stream = TcpStream::connect_timeout(&address, PROXY_TIMEOUT)
+        .map_err(|e| anyhow!("Failed to connect to proxy {}: {}", address, e))?;
+    stream.set_read_timeout(Some(PROXY_TIMEOUT))?;
+    stream.set_write_timeout(Some(PROXY_TIMEOUT))?;
+
+    // Greeting offering only "no authentication"
+    stream.write_all(&[0x05, 0x01, 0x00])
+        .map_err(|e| anyhow!("Failed to send SOCKS greeting: {}", e))?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)
+        .map_err(|e| anyhow!("Failed to read SOCKS greeting: {}", e))?;
+    if reply[0] != 0x05 {
+        return Err(anyhow!("{}:{} is not a SOCKS5 proxy", proxy.host, proxy.port));
+    }
+    if reply[1] != 0x00 {
+        return Err(anyhow!("SOCKS proxy requires authentication"));
+    }
+
+    // CONNECT request, with the target as an IP address or unresolved host name
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        },
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        },
+        Err(_) => {
+            let name = host.as_bytes();
+            if name.len() > 255 {
+                return Err(anyhow!("Host name too long for SOCKS: {}", host));
+            }
+            request.push(0x03);
+            request.push(name.len() as u8);
+            request.extend_from_slice(name);
+        },
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)
+        .map_err(|e| anyhow!("Failed to send SOCKS request: {}", e))?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)
+        .map_err(|e| anyhow!("Failed to read SOCKS reply: {}", e))?;
+    if header[1] != 0x00 {
+        return Err(anyhow!("SOCKS proxy could not reach {}:{}: {}", host, port, reply_error(header[1])));
+    }
+
+    // Skip the address the proxy bound for us
+    let bound_length = match header[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut length = [0u8; 1];
+            stream.read_exact(&mut length)?;
+            length[0] as usize
+        },
+        kind => return Err(anyhow!("Unknown SOCKS address type: {}", kind)),
+    };
+    let mut bound = vec![0u8; bound_length + 2];
+    stream.read_exact(&mut bound)
+        .map_err(|e| anyhow!("Failed to read SOCKS reply: {}", e))?;
+
+    Ok(stream)
+}
+
+// Reply codes from RFC 1928
+fn reply_error(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// HTTP client routed according to the proxy settings. When the settings
+/// refuse the route every request fails instead of connecting directly.
+pub struct HttpClient {
+    client: Result<reqwest::blocking::Client, String>,
+}
+
+impl HttpClient {
+    /// Build `builder` into a client for the server at `url`, routed for `source`
+    pub fn build(source: ProxySource, url: &str, builder: reqwest::blocking::ClientBuilder) -> Self {
+        HttpClient {
+            client: build_client(source, url, builder).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// A client whose requests all fail with `error`
+    pub fn unavailable(error: anyhow::Error) -> Self {
+        HttpClient { client: Err(error.to_string()) }
+    }
+
+    pub fn get(&self, url: &str) -> Result<reqwest::blocking::RequestBuilder> {
+        Ok(self.client()?.get(url))
+    }
+
+    pub fn post(&self, url: &str) -> Result<reqwest::blocking::RequestBuilder> {
+        Ok(self.client()?.post(url))
+    }
+
+    fn client(&self) -> Result<&reqwest::blocking::Client> {
+        self.client.as_ref().map_err(|e| anyhow!("{}", e))
+    }
+}
+
+fn build_client(source: ProxySource, url: &str, builder: reqwest::blocking::ClientBuilder) -> Result<reqwest::blocking::Client> {
+    let parsed = Url::parse(url)
+        .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+    let host = parsed.host_str()
+        .ok_or_else(|| anyhow!("URL has no host: {}", url))?;
+
+    let builder = match route(source, host)? {
+        Some(proxy) => {
+            let proxy_url = format!("socks5h://{}:{}", proxy.host, proxy.port);
+            let proxy = reqwest::Proxy::all(&proxy_url)
+                .map_err(|e| anyhow!("Invalid proxy {}: {}", proxy_url, e))?;
+            builder.proxy(proxy)
+        },
+        // Only a local server may be reached directly in Tor only mode. Don't
+        // let it redirect us elsewhere or hand us to a system proxy.
+        None if SETTINGS.read().unwrap().tor_only => {
+            builder.no_proxy().redirect(reqwest::redirect::Policy::none())
+        },
+        None => builder,
+    };
+
+    builder.build()
+        .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn tor() -> SocksProxy {
+        SocksProxy { host: DEFAULT_PROXY_HOST.to_string(), port: DEFAULT_PROXY_PORT }
+    }
+
+    fn settings(global: Option<SocksProxy>, tor_only: bool) -> ProxySettings {
+        ProxySettings { global, tor_only, overrides: BTreeMap::new() }
+    }
+
+    #[test]
+    fn direct_by_default() {
+        let settings = settings(None, false);
+        assert_eq!(settings.route(ProxySource::Exchanges, "www.bitstamp.net").unwrap(), None);
+    }
+
+    #[test]
+    fn onion_hosts_require_a_proxy() {
+        let direct = settings(None, false);
+        let error = direct.route(ProxySource::Mempool, "mempoolhqx4isw62xs7abwphsq7ldayuidyx2v2oethdhhj6mlo2r6ad.onion").unwrap_err();
+        assert!(error.to_string().contains("onion address"), "{}", error);
+
+        let proxied = settings(Some(tor()), false);
+        assert_eq!(proxied.route(ProxySource::Mempool, "example.ONION.").unwrap(), Some(tor()));
+    }
+
+    #[test]
+    fn tor_only_refuses_direct_clearnet_connections() {
+        let mut settings = settings(None, true);
+        let error = settings.route(ProxySource::Exchanges, "api.kraken.com").unwrap_err();
+        assert!(error.to_string().contains("Tor only mode"), "{}", error);
+
+        // A local node is still reachable directly
+        assert_eq!(settings.route(ProxySource::BitcoinCore, "127.0.0.1").unwrap(), None);
+        assert_eq!(settings.route(ProxySource::BitcoinCore, "localhost").unwrap(), None);
+
+        // Fine as long as a proxy is used
+        settings.global = Some(tor());
+        assert_eq!(settings.route(ProxySource::Exchanges, "api.kraken.com").unwrap(), Some(tor()));
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_the_global_proxy() {
+        let mut settings = settings(Some(tor()), false);
+        settings.overrides.insert(ProxySource::BitcoinCore, ProxyRoute::Direct);
+        settings.overrides.insert(ProxySource::Electrum, ProxyRoute::Socks5 { host: " 10.0.0.2 ".to_string(), port: 1080 });
+
+        assert_eq!(settings.route(ProxySource::BitcoinCore, "192.168.1.10").unwrap(), None);
+        assert_eq!(
+            settings.route(ProxySource::Electrum, "electrum.example.com").unwrap(),
+            Some(SocksProxy { host: "10.0.0.2".to_string(), port: 1080 })
+        );
+        assert_eq!(settings.route(ProxySource::Mempool, "mempool.space").unwrap(), Some(tor()));
+
+        // A direct override is still subject to Tor only mode
+        settings.tor_only = true;
+        assert!(settings.route(ProxySource::BitcoinCore, "192.168.1.10").is_err());
+    }
+
+    #[test]
+    fn local_hosts() {
+        assert!(is_local("localhost"));
+        assert!(is_local("LocalHost"));
+        assert!(is_local("127.0.0.1"));
+        assert!(is_local("127.1.2.3"));
+        assert!(is_local("::1"));
+        assert!(is_local("[::1]"));
+        assert!(!is_local("192.168.1.10"));
+        assert!(!is_local("[2001:db8::1]"));
+        assert!(!is_local("localhost.example.com"));
+    }
+
+    // Fake SOCKS5 proxy accepting one connection. The CONNECT request it
+    // receives is sent back over the channel; it answers with `reply`
+    // followed by "hello" from the target.
+    fn serve_socks(reply: Vec<u8>) -> (SocksProxy, mpsc::Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            assert_eq!(greeting, [0x05, 0x01, 0x00]);
+            stream.write_all(&[0x05, 0x00]).unwrap();
+
+            let mut request = vec![0u8; 4];
+            stream.read_exact(&mut request).unwrap();
+            let address_length = match request[3] {
+                0x01 => 4,
+                0x04 => 16,
+                _ => {
+                    let mut length = [0u8; 1];
+                    stream.read_exact(&mut length).unwrap();
+                    request.push(length[0]);
+                    length[0] as usize
+                },
+            };
+            let mut rest = vec![0u8; address_length + 2];
+            stream.read_exact(&mut rest).unwrap();
+            request.extend(rest);
+            sender.send(request).unwrap();
+
+            stream.write_all(&reply).unwrap();
+            stream.write_all(b"hello").ok();
+        });
+
+        (SocksProxy { host: "127.0.0.1".to_string(), port }, receiver)
+    }
+
+    fn read_hello(mut stream: TcpStream) -> String {
+        let mut hello = [0u8; 5];
+        stream.read_exact(&mut hello).unwrap();
+        String::from_utf8_lossy(&hello).into_owned()
+    }
+
+    #[test]
+    fn connect_sends_host_names_unresolved() {
+        // Bound address given as a 9 byte domain name
+        let mut reply = vec![0x05, 0x00, 0x00, 0x03, 9];
+        reply.extend_from_slice(b"relay.tor");
+        reply.extend_from_slice(&443u16.to_be_bytes());
+        let (proxy, requests) = serve_socks(reply);
+
+        let stream = connect_socks5(&proxy, "example.onion", 80).unwrap();
+
+        let mut expected = vec![0x05, 0x01, 0x00, 0x03, 13];
+        expected.extend_from_slice(b"example.onion");
+        expected.extend_from_slice(&[0x00, 80]);
+        assert_eq!(requests.recv().unwrap(), expected);
+        // The whole bound address was consumed
+        assert_eq!(read_hello(stream), "hello");
+    }
+
+    #[test]
+    fn connect_sends_ip_addresses() {
+        let (proxy, requests) = serve_socks(vec![0x05, 0x00, 0x00, 0x01, 10, 0, 0, 1, 0x1f, 0x90]);
+        let stream = connect_socks5(&proxy, "192.168.1.10", 8332).unwrap();
+        assert_eq!(requests.recv().unwrap(), [0x05, 0x01, 0x00, 0x01, 192, 168, 1, 10, 0x20, 0x8c]);
+        assert_eq!(read_hello(stream), "hello");
+
+        let mut reply = vec![0x05, 0x00, 0x00, 0x04];
+        reply.extend_from_slice(&[0u8; 18]);
+        let (proxy, requests) = serve_socks(reply);
+        let stream = connect_socks5(&proxy, "[::1]", 50001).unwrap();
+        let mut expected = vec![0x05, 0x01, 0x00, 0x04];
+        expected.extend_from_slice(&"::1".parse::<std::net::Ipv6Addr>().unwrap().octets());
+        expected.extend_from_slice(&50001u16.to_be_bytes());
+        assert_eq!(requests.recv().unwrap(), expected);
+        assert_eq!(read_hello(stream), "hello");
+    }
+
+    #[test]
+    fn connect_reports_proxy_failures() {
+        let (proxy, _requests) = serve_socks(vec![0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+        let error = connect_socks5(&proxy, "mempool.space", 443).unwrap_err().to_string();
+        assert_eq!(error, "SOCKS proxy could not reach mempool.space:443: connection refused");
+    }
+
+    #[test]
+    fn connect_refuses_proxies_requiring_authentication() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = SocksProxy { host: "127.0.0.1".to_string(), port: listener.local_addr().unwrap().port() };
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            // No acceptable methods
+            stream.write_all(&[0x05, 0xff]).unwrap();
+        });
+
+        let error = connect_socks5(&proxy, "mempool.space", 443).unwrap_err().to_string();
+        assert!(error.contains("requires authentication"), "{}", error);
+    }
+}
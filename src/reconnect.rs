@@ -0,0 +1,181 @@
+use std::thread;
+use std::time::Duration;
+use anyhow::Result;
+
+// Reconnect backoff bounds
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Exponential backoff that starts over once a connection has been made
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Backoff { initial, max, next: initial }
+    }
+
+    // How long to wait before the next attempt
+    fn delay(&mut self, connected: bool) -> Duration {
+        // Only back off further if we never managed to connect
+        if connected {
+            self.next = self.initial;
+        }
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+}
+
+/// Keeps a streaming connection (WebSocket, Electrum subscription, ...) alive,
+/// reconnecting with exponential backoff whenever it drops
+pub struct Reconnect {
+    name: &'static str,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Reconnect {
+    /// `name` identifies the connection in log output
+    pub fn new(name: &'static str) -> Self {
+        Reconnect { name, initial_backoff: INITIAL_BACKOFF, max_backoff: MAX_BACKOFF }
+    }
+
+    #[cfg(test)]
+    fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Call `connect` until it returns Ok, which means `handler` asked to stop.
+    /// Events are forwarded to `handler`; `is_connected` spots the event that marks
+    /// a successful connection and `disconnected` builds the event sent when one drops.
+    /// Streaming stops once `handler` returns false.
+    pub fn run<E>(
+        &self,
+        mut connect: impl FnMut(&mut dyn FnMut(E) -> bool) -> Result<()>,
+        is_connected: impl Fn(&E) -> bool,
+        disconnected: impl Fn(String) -> E,
+        mut handler: impl FnMut(E) -> bool,
+    ) {
+        let mut backoff = Backoff::new(self.initial_backoff, self.max_backoff);
+
+        loop {
+            let mut connected = false;
+            let result = connect(&mut |event| {
+                if is_connected(&event) {
+                    connected = true;
+                }
+                handler(event)
+            });
+
+            let reason = match result {
+                // The handler asked us to stop
+                Ok(()) => return,
+                Err(e) => e.to_string(),
+            };
+            eprintln!("{} disconnected: {}", self.name, reason);
+            if !handler(disconnected(reason)) {
+                return;
+            }
+
+            let delay = backoff.delay(connected);
+            println!("Reconnecting {} in {}s", self.name, delay.as_secs());
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Connected,
+        Data(u32),
+        Disconnected(String),
+    }
+
+    fn reconnect() -> Reconnect {
+        Reconnect::new("test stream").with_backoff(Duration::from_millis(1), Duration::from_millis(4))
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit_and_resets_after_connecting() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        let delays: Vec<u64> = [false, false, false, false].iter()
+            .map(|&connected| backoff.delay(connected).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 5]);
+
+        assert_eq!(backoff.delay(true), Duration::from_secs(1));
+        assert_eq!(backoff.delay(false), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn reconnects_until_the_handler_stops() {
+        let mut attempts = 0;
+        let mut events = Vec::new();
+
+        reconnect().run(
+            |handler| {
+                attempts += 1;
+                match attempts {
+                    1 => Err(anyhow!("connection refused")),
+                    2 => {
+                        handler(Event::Connected);
+                        handler(Event::Data(7));
+                        Err(anyhow!("reset by peer"))
+                    },
+                    _ => {
+                        handler(Event::Connected);
+                        // Stop streaming once the handler returns false
+                        while handler(Event::Data(8)) {}
+                        Ok(())
+                    },
+                }
+            },
+            |event| *event == Event::Connected,
+            Event::Disconnected,
+            |event| {
+                let keep_going = event != Event::Data(8);
+                events.push(event);
+                keep_going
+            },
+        );
+
+        assert_eq!(attempts, 3);
+        assert_eq!(events, vec![
+            Event::Disconnected("connection refused".to_string()),
+            Event::Connected,
+            Event::Data(7),
+            Event::Disconnected("reset by peer".to_string()),
+            Event::Connected,
+            Event::Data(8),
+        ]);
+    }
+
+    #[test]
+    fn stops_when_the_handler_rejects_a_disconnect() {
+        let mut attempts = 0;
+
+        reconnect().run(
+            |_| {
+                attempts += 1;
+                Err(anyhow!("connection refused"))
+            },
+            |event| *event == Event::Connected,
+            Event::Disconnected,
+            |event| !matches!(event, Event::Disconnected(_)),
+        );
+
+        assert_eq!(attempts, 1);
+    }
+}
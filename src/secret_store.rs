@@ -0,0 +1,196 @@
+use anyhow::Result;
+
+// Attribute every secret stored by this app is tagged with
+const APPLICATION: &str = "btc-ticker";
+
+/// Save `secret` in the OS keyring under `attributes`, replacing any secret
+/// stored with the same attributes
+pub fn store(label: &str, attributes: &[(&str, &str)], secret: &str) -> Result<()> {
+    platform::store(label, &with_application(attributes), secret)
+}
+
+/// Secret stored under `attributes`, or None when there is none
+pub fn load(attributes: &[(&str, &str)]) -> Result<Option<String>> {
+    platform::load(&with_application(attributes))
+}
+
+/// Remove the secrets stored under `attributes`, if any
+pub fn delete(attributes: &[(&str, &str)]) -> Result<()> {
+    platform::delete(&with_application(attributes))
+}
+
+fn with_application<'a>(attributes: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+    let mut attributes = attributes.to_vec();
+    attributes.push(("application", APPLICATION));
+    attributes
+}
+
+// The freedesktop Secret Service (GNOME Keyring, KeePassXC, KWallet) on the session bus
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use anyhow::{Result, anyhow};
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::{DynamicType, OwnedObjectPath, OwnedValue, Type, Value};
+
+    const SERVICE: &str = "org.freedesktop.secrets";
+    const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+    const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+    const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+    const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+    const SESSION_INTERFACE: &str = "org.freedesktop.Secret.Session";
+    const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+
+    // Session, algorithm parameters, value and content type
+    type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+    // An open Secret Service session. Secrets travel unencrypted ("plain"),
+    // which is fine on the private session bus.
+    struct Session {
+        connection: Connection,
+        path: OwnedObjectPath,
+    }
+
+    impl Session {
+        fn open() -> Result<Self> {
+            let connection = Connection::session()
+                .map_err(|e| anyhow!("Failed to connect to the session bus: {}", e))?;
+            let (_, path): (OwnedValue, OwnedObjectPath) = call(
+                &connection, SERVICE_PATH, SERVICE_INTERFACE, "OpenSession", &("plain", Value::from("")),
+            ).map_err(|e| anyhow!("Failed to open a keyring session: {}", e))?;
+            Ok(Session { connection, path })
+        }
+
+        fn call<B, R>(&self, path: &str, interface: &str, method: &str, body: &B) -> Result<R>
+        where
+            B: Serialize + DynamicType,
+            R: DeserializeOwned + Type,
+        {
+            call(&self.connection, path, interface, method, body)
+        }
+
+        // Items matching `attributes`, unlocked ones first
+        fn search(&self, attributes: &HashMap<&str, &str>) -> Result<Vec<OwnedObjectPath>> {
+            let (mut unlocked, locked): (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) =
+                self.call(SERVICE_PATH, SERVICE_INTERFACE, "SearchItems", &(attributes,))?;
+            unlocked.extend(locked);
+            Ok(unlocked)
+        }
+
+        fn unlock(&self, path: &OwnedObjectPath) -> Result<()> {
+            let (_, prompt): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+                self.call(SERVICE_PATH, SERVICE_INTERFACE, "Unlock", &(vec![path],))?;
+            self.prompt(&prompt)
+        }
+
+        // Show a prompt (e.g. for the keyring password) and wait for the user to answer it
+        fn prompt(&self, path: &OwnedObjectPath) -> Result<()> {
+            // "/" means no prompt is needed
+            if path.as_str() == "/" {
+                return Ok(());
+            }
+
+            let prompt = Proxy::new(&self.connection, SERVICE, path.as_str(), PROMPT_INTERFACE)
+                .map_err(|e| anyhow!("Failed to open keyring prompt: {}", e))?;
+            let mut completed = prompt.receive_signal("Completed")
+                .map_err(|e| anyhow!("Failed to watch keyring prompt: {}", e))?;
+            // No parent window to attach the prompt to
+            prompt.call_method("Prompt", &("",))
+                .map_err(|e| anyhow!("Failed to show keyring prompt: {}", e))?;
+
+            let message = completed.next()
+                .ok_or_else(|| anyhow!("Keyring prompt went away without an answer"))?;
+            let (dismissed, _): (bool, OwnedValue) = message.body().deserialize()
+                .map_err(|e| anyhow!("Unexpected keyring prompt result: {}", e))?;
+            if dismissed {
+                return Err(anyhow!("Keyring prompt was dismissed"));
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            let _: Result<()> = self.call(self.path.as_str(), SESSION_INTERFACE, "Close", &());
+        }
+    }
+
+    fn call<B, R>(connection: &Connection, path: &str, interface: &str, method: &str, body: &B) -> Result<R>
+    where
+        B: Serialize + DynamicType,
+        R: DeserializeOwned + Type,
+    {
+        let reply = connection.call_method(Some(SERVICE), path, Some(interface), method, body)
+            .map_err(|e| anyhow!("Keyring call {} failed: {}", method, e))?;
+        reply.body().deserialize()
+            .map_err(|e| anyhow!("Unexpected keyring reply to {}: {}", method, e))
+    }
+
+    pub fn store(label: &str, attributes: &[(&str, &str)], secret: &str) -> Result<()> {
+        let session = Session::open()?;
+
+        let collection: OwnedObjectPath = session.call(SERVICE_PATH, SERVICE_INTERFACE, "ReadAlias", &("default",))?;
+        if collection.as_str() == "/" {
+            return Err(anyhow!("The keyring has no default collection"));
+        }
+        session.unlock(&collection)?;
+
+        let attributes: HashMap<&str, &str> = attributes.iter().copied().collect();
+        let mut properties: HashMap<&str, Value> = HashMap::new();
+        properties.insert("org.freedesktop.Secret.Item.Label", Value::from(label));
+        properties.insert("org.freedesktop.Secret.Item.Attributes", Value::from(attributes));
+        let secret: Secret = (session.path.clone(), Vec::new(), secret.as_bytes().to_vec(), "text/plain".to_string());
+
+        // Replace an item with the same attributes instead of adding another
+        let (_, prompt): (OwnedObjectPath, OwnedObjectPath) = session.call(
+            collection.as_str(), COLLECTION_INTERFACE, "CreateItem", &(properties, secret, true),
+        )?;
+        session.prompt(&prompt)
+    }
+
+    pub fn load(attributes: &[(&str, &str)]) -> Result<Option<String>> {
+        let session = Session::open()?;
+        let attributes: HashMap<&str, &str> = attributes.iter().copied().collect();
+
+        let Some(item) = session.search(&attributes)?.into_iter().next() else {
+            return Ok(None);
+        };
+        session.unlock(&item)?;
+
+        let secret: Secret = session.call(item.as_str(), ITEM_INTERFACE, "GetSecret", &(&session.path,))?;
+        let value = String::from_utf8(secret.2)
+            .map_err(|_| anyhow!("Keyring secret is not valid UTF-8"))?;
+        Ok(Some(value))
+    }
+
+    pub fn delete(attributes: &[(&str, &str)]) -> Result<()> {
+        let session = Session::open()?;
+        let attributes: HashMap<&str, &str> = attributes.iter().copied().collect();
+
+        for item in session.search(&attributes)? {
+            let prompt: OwnedObjectPath = session.call(item.as_str(), ITEM_INTERFACE, "Delete", &())?;
+            session.prompt(&prompt)?;
+        }
+        Ok(())
+    }
+}
+
+// No keyring support elsewhere yet; nothing can be stored, so nothing is ever found
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use anyhow::{Result, anyhow};
+
+    pub fn store(_label: &str, _attributes: &[(&str, &str)], _secret: &str) -> Result<()> {
+        Err(anyhow!("Storing secrets in the OS keyring is only supported on Linux"))
+    }
+
+    pub fn load(_attributes: &[(&str, &str)]) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    pub fn delete(_attributes: &[(&str, &str)]) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use bitcoin::hashes::{sha256, Hash};
+
+use crate::proxy::{self, ProxySource};
+
+// Any byte stream a socket protocol can run over (plain TCP or TLS)
+pub trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+// Certificate checks for a TLS connection
+#[derive(Clone, Default)]
+pub struct TlsOptions {
+    // Self-hosted servers commonly use self-signed certificates
+    pub accept_invalid_certs: bool,
+    // CA certificates trusted in addition to the system ones
+    pub root_certificates: Vec<native_tls::Certificate>,
+    // Only accept a server certificate with this SHA-256 fingerprint
+    pub pinned_sha256: Option<[u8; 32]>,
+}
+
+/// Open a TCP connection to `host:port`, routed through the proxy configured
+/// for `source` and wrapped in TLS checked by `tls` when `secure` is set.
+/// `read_timeout` bounds how long reads block without data.
+pub fn connect(source: ProxySource, host: &str, port: u16, secure: bool, tls: &TlsOptions, read_timeout: Duration) -> Result<Box<dyn Stream>> {
+    let tcp = match proxy::route(source, host)? {
+        Some(proxy) => proxy::connect_socks5(&proxy, host, port)?,
+        None => {
+            let address = (host, port).to_socket_addrs()
+                .map_err(|e| anyhow!("Failed to resolve {}: {}", host, e))?
+                .next()
+                .ok_or_else(|| anyhow!("No addresses found for {}", host))?;
+
+            TcpStream::connect_timeout(&address, Duration::from_secs(10))
+                .map_err(|e| anyhow!("Failed to connect to {}: {}", address, e))?
+        },
+    };
+    tcp.set_read_timeout(Some(read_timeout))?;
+    tcp.set_write_timeout(Some(Duration::from_secs(10)))?;
+
+    if !secure {
+        return Ok(Box::new(tcp));
+    }
+
+    let mut builder = native_tls::TlsConnector::builder();
+    builder.danger_accept_invalid_certs(tls.accept_invalid_certs);
+    for certificate in &tls.root_certificates {
+        builder.add_root_certificate(certificate.clone());
+    }
+    // A pinned certificate is trusted on its own, so self-signed ones work too
+    if tls.pinned_sha256.is_some() {
+        builder.danger_accept_invalid_certs(true);
+        builder.danger_accept_invalid_hostnames(true);
+    }
+    let connector = builder.build()
+        .map_err(|e| anyhow!("Failed to create TLS connector: {}", e))?;
+    let stream = connector.connect(host, tcp)
+        .map_err(|e| anyhow!("TLS handshake with {} failed: {}", host, e))?;
+
+    // Check the pin before anything is sent over the connection
+    if let Some(pinned) = tls.pinned_sha256 {
+        let certificate = stream.peer_certificate()
+            .map_err(|e| anyhow!("Failed to read the certificate of {}: {}", host, e))?
+            .ok_or_else(|| anyhow!("{} sent no certificate to check the pin against", host))?;
+        let der = certificate.to_der()
+            .map_err(|e| anyhow!("Failed to encode the certificate of {}: {}", host, e))?;
+        check_pin(host, &der, &pinned)?;
+    }
+    Ok(Box::new(stream))
+}
+
+/// SHA-256 fingerprint of a DER encoded certificate
+pub fn certificate_fingerprint(der: &[u8]) -> [u8; 32] {
+    sha256::Hash::hash(der).to_byte_array()
+}
+
+/// Fingerprint as colon separated hex, the way browsers and openssl show it
+pub fn format_fingerprint(fingerprint: &[u8; 32]) -> String {
+    fingerprint.iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Fail unless the DER encoded certificate `der` of `host` matches `pinned`
+pub fn check_pin(host: &str, der: &[u8], pinned: &[u8; 32]) -> Result<()> {
+    let fingerprint = certificate_fingerprint(der);
+    if fingerprint != *pinned {
+        return Err(anyhow!(
+            "Certificate of {} does not match the pinned fingerprint (server sent {})",
+            host, format_fingerprint(&fingerprint)
+        ));
+    }
+    Ok(())
+}
+
+/// Whether an I/O error is just a read timeout expiring
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<std::io::Error>()
+        .map(|e| matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, LOCALHOST_CERT};
+
+    fn localhost_fingerprint() -> [u8; 32] {
+        let der = native_tls::Certificate::from_pem(LOCALHOST_CERT.as_bytes()).unwrap().to_der().unwrap();
+        certificate_fingerprint(&der)
+    }
+
+    // Connect over TLS to a local server using the self-signed LOCALHOST_CERT
+    fn connect_tls(tls: &TlsOptions) -> Result<Box<dyn Stream>> {
+        let url = url::Url::parse(&test_support::serve_tls(|_| (200, "{}".to_string()))).unwrap();
+        connect(ProxySource::Electrum, "127.0.0.1", url.port().unwrap(), true, tls, Duration::from_secs(5))
+    }
+
+    #[test]
+    fn matching_pin_trusts_a_self_signed_certificate() {
+        let tls = TlsOptions { pinned_sha256: Some(localhost_fingerprint()), ..Default::default() };
+        let mut stream = connect_tls(&tls).unwrap();
+
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    }
+
+    #[test]
+    fn mismatched_pin_is_rejected() {
+        let mut pinned = localhost_fingerprint();
+        pinned[31] ^= 0x01;
+        let tls = TlsOptions { pinned_sha256: Some(pinned), ..Default::default() };
+
+        let error = connect_tls(&tls).err().unwrap().to_string();
+        assert!(error.contains("does not match the pinned fingerprint"), "{}", error);
+        assert!(error.contains(&format_fingerprint(&localhost_fingerprint())), "{}", error);
+    }
+
+    #[test]
+    fn self_signed_certificate_without_pin_is_rejected() {
+        let error = connect_tls(&TlsOptions::default()).err().unwrap().to_string();
+        assert!(error.contains("TLS handshake with 127.0.0.1 failed"), "{}", error);
+    }
+
+    #[test]
+    fn fingerprint_format() {
+        let mut fingerprint = [0u8; 32];
+        fingerprint[0] = 0xab;
+        fingerprint[31] = 0x0f;
+        let formatted = format_fingerprint(&fingerprint);
+        assert!(formatted.starts_with("AB:00:"));
+        assert!(formatted.ends_with(":00:0F"));
+        assert_eq!(formatted.len(), 32 * 3 - 1);
+    }
+}
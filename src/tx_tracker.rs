@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::config::AppConfig;
+use crate::mempool_client::MempoolProjectedBlock;
+
+// Full projected blocks come within a few kvB of the 1 MvB limit; smaller
+// ones have room to spare
+const FULL_BLOCK_VSIZE: f64 = 990_000.0;
+
+// A transaction whose confirmations are being tracked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedTx {
+    pub txid: String,
+    // Notifications already sent, so restarts don't repeat them
+    #[serde(default)]
+    pub notified_confirmed: bool,
+    #[serde(default)]
+    pub notified_target: bool,
+}
+
+impl TrackedTx {
+    pub fn new(txid: &str) -> Self {
+        TrackedTx {
+            txid: txid.to_string(),
+            notified_confirmed: false,
+            notified_target: false,
+        }
+    }
+}
+
+// Latest known state of a tracked transaction
+#[derive(Debug, Clone, Default)]
+pub struct TxProgress {
+    // Sat/vB, known once the transaction has been fetched
+    pub fee_rate: Option<f64>,
+    pub vsize: u32,
+    pub confirmed_height: Option<u32>,
+    pub last_error: Option<String>,
+}
+
+/// Tracked transactions persisted as JSON in the config directory
+pub struct TrackedTxStore {
+    path: PathBuf,
+}
+
+impl TrackedTxStore {
+    /// Open the store at tracked_txs.json next to config.json
+    pub fn new() -> Self {
+        let mut path = AppConfig::get_config_dir();
+        path.push("tracked_txs.json");
+        TrackedTxStore { path }
+    }
+
+    /// Load tracked transactions, or an empty list if none are stored
+    pub fn load(&self) -> Vec<TrackedTx> {
+        if !self.path.exists() {
+            return Vec::new();
+        }
+
+        match fs::read_to_string(&self.path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(txs) => txs,
+                Err(e) => {
+                    eprintln!("Error parsing tracked transactions: {}", e);
+                    Vec::new()
+                }
+            },
+            Err(e) => {
+                eprintln!("Error reading tracked transactions: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn save(&self, txs: &[TrackedTx]) -> Result<()> {
+        let content = serde_json::to_string_pretty(txs)
+            .map_err(|e| anyhow!("Failed to serialize tracked transactions: {}", e))?;
+        fs::write(&self.path, content)
+            .map_err(|e| anyhow!("Failed to write tracked transactions: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Whether `input` looks like a txid (64 hex characters)
+pub fn is_valid_txid(input: &str) -> bool {
+    input.len() == 64 && input.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Confirmations of a transaction mined at `tx_height` when the tip is at `tip_height`
+pub fn confirmations(tip_height: u32, tx_height: Option<u32>) -> u32 {
+    match tx_height {
+        Some(height) if tip_height >= height => tip_height - height + 1,
+        // The tip hasn't caught up with the block yet
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// Index of the first projected block a transaction paying `fee_rate` would make
+/// it into, or None if it falls below all projected blocks
+pub fn projected_block_position(fee_rate: f64, blocks: &[MempoolProjectedBlock]) -> Option<usize> {
+    blocks.iter().position(|block| {
+        // A block that isn't full takes everything left in the mempool
+        block.block_v_size < FULL_BLOCK_VSIZE
+            || block.fee_range.first().is_some_and(|&lowest_fee| fee_rate >= lowest_fee)
+    })
+}
+
+/// Estimated seconds until confirmation at `position` in the projected blocks
+pub fn estimated_wait_secs(position: usize) -> i64 {
+    // Blocks are found roughly every ten minutes
+    (position as i64 + 1) * 600
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projected(block_v_size: f64, fee_range: &[f64]) -> MempoolProjectedBlock {
+        MempoolProjectedBlock {
+            block_size: (block_v_size * 1.6) as u64,
+            block_v_size,
+            n_tx: 3000,
+            total_fees: 10_000_000,
+            median_fee: fee_range[fee_range.len() / 2],
+            fee_range: fee_range.to_vec(),
+        }
+    }
+
+    #[test]
+    fn counts_confirmations() {
+        assert_eq!(confirmations(800_000, None), 0);
+        assert_eq!(confirmations(800_000, Some(800_000)), 1);
+        assert_eq!(confirmations(800_005, Some(800_000)), 6);
+    }
+
+    #[test]
+    fn counts_one_confirmation_while_the_tip_lags() {
+        // The transaction was seen in a block the tip source doesn't know about yet
+        assert_eq!(confirmations(799_999, Some(800_000)), 1);
+    }
+
+    #[test]
+    fn places_transactions_in_full_blocks_by_fee() {
+        let blocks = [
+            projected(997_800.0, &[20.0, 25.0, 60.0]),
+            projected(999_950.0, &[8.0, 12.0, 20.0]),
+            projected(4_500_000.0, &[2.0, 4.0, 8.0]),
+        ];
+
+        assert_eq!(projected_block_position(30.0, &blocks), Some(0));
+        assert_eq!(projected_block_position(20.0, &blocks), Some(0));
+        assert_eq!(projected_block_position(10.0, &blocks), Some(1));
+        assert_eq!(projected_block_position(2.0, &blocks), Some(2));
+        assert_eq!(projected_block_position(1.0, &blocks), None);
+        assert_eq!(estimated_wait_secs(1), 1200);
+    }
+
+    #[test]
+    fn partial_blocks_take_any_fee() {
+        let blocks = [
+            projected(998_400.0, &[15.0, 20.0, 40.0]),
+            projected(350_000.0, &[3.0, 5.0, 9.0]),
+        ];
+
+        assert_eq!(projected_block_position(16.0, &blocks), Some(0));
+        assert_eq!(projected_block_position(1.0, &blocks), Some(1));
+        assert_eq!(projected_block_position(1.0, &blocks[..1]), None);
+    }
+
+    #[test]
+    fn validates_txids() {
+        assert!(is_valid_txid(&"ab".repeat(32)));
+        assert!(!is_valid_txid(&"ab".repeat(31)));
+        assert!(!is_valid_txid(&"zz".repeat(32)));
+    }
+}
@@ -0,0 +1,335 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use anyhow::Result;
+use bitcoin::{ScriptBuf, Transaction};
+use serde::{Serialize, Deserialize};
+
+use crate::electrum_client::{self, ElectrumClient};
+use crate::hd_wallet::{AccountKey, Chain, ScriptKind};
+use crate::mempool_client::{MempoolClient, MempoolTx};
+
+// Addresses derived per chain at most, in case a wallet never runs out of used addresses
+const MAX_ADDRESSES_PER_CHAIN: u32 = 1000;
+
+// Number of history entries kept per wallet
+const MAX_HISTORY: usize = 100;
+
+fn default_gap_limit() -> u32 {
+    20
+}
+
+// A watch-only wallet given by its account extended public key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedWallet {
+    pub xpub: String,
+    #[serde(default)]
+    pub label: String,
+    // Stop scanning a chain after this many consecutive unused addresses
+    #[serde(default = "default_gap_limit")]
+    pub gap_limit: u32,
+}
+
+impl WatchedWallet {
+    pub fn display_name(&self) -> String {
+        if !self.label.is_empty() {
+            self.label.clone()
+        } else {
+            crate::address_watch::shorten_address(&self.xpub)
+        }
+    }
+}
+
+// A transaction touching the wallet, with its net effect on the wallet balance
+#[derive(Debug, Clone)]
+pub struct WalletTx {
+    pub txid: String,
+    // Satoshis, negative when spending
+    pub net_value: i64,
+    pub confirmed: bool,
+    pub block_time: Option<u32>,
+}
+
+// Result of scanning a wallet's addresses
+#[derive(Debug, Clone)]
+pub struct WalletSummary {
+    pub kind: ScriptKind,
+    // Satoshis
+    pub confirmed: i64,
+    pub unconfirmed: i64,
+    pub used_addresses: usize,
+    // First unused receive address
+    pub next_receive_address: String,
+    // Newest first
+    pub history: Vec<WalletTx>,
+}
+
+/// Address lookups a wallet scan needs, served by the mempool API or an Electrum server
+pub trait AddressIndex {
+    /// Confirmed and unconfirmed balance of an address in satoshis, or None
+    /// if it has never been used
+    fn address_balance(&self, address: &str) -> Result<Option<(i64, i64)>>;
+
+    /// Transactions touching any of `addresses` with their net effect on them
+    fn wallet_history(&self, addresses: &[String]) -> Result<Vec<WalletTx>>;
+}
+
+impl AddressIndex for MempoolClient {
+    fn address_balance(&self, address: &str) -> Result<Option<(i64, i64)>> {
+        let info = self.fetch_address(address)?;
+        let used = info.chain_stats.tx_count + info.mempool_stats.tx_count > 0;
+        Ok(used.then(|| (info.confirmed_balance(), info.unconfirmed_balance())))
+    }
+
+    fn wallet_history(&self, addresses: &[String]) -> Result<Vec<WalletTx>> {
+        let mut txs: HashMap<String, MempoolTx> = HashMap::new();
+        for address in addresses {
+            for tx in self.fetch_address_txs(address)? {
+                txs.entry(tx.txid.clone()).or_insert(tx);
+            }
+        }
+
+        let wallet_addresses: HashSet<&str> = addresses.iter().map(|address| address.as_str()).collect();
+        Ok(txs.into_values()
+            .map(|tx| WalletTx {
+                net_value: net_value_for_wallet(&tx, &wallet_addresses),
+                confirmed: tx.status.confirmed,
+                block_time: tx.status.block_time,
+                txid: tx.txid,
+            })
+            .collect())
+    }
+}
+
+impl AddressIndex for ElectrumClient {
+    fn address_balance(&self, address: &str) -> Result<Option<(i64, i64)>> {
+        if self.fetch_address_history(address)?.is_empty() {
+            return Ok(None);
+        }
+        let balance = self.fetch_address_balance(address)?;
+        Ok(Some((balance.confirmed, balance.unconfirmed)))
+    }
+
+    // Electrum histories only have txids and heights, so the newest
+    // transactions are fetched whole and their inputs looked up to work out
+    // what they moved
+    fn wallet_history(&self, addresses: &[String]) -> Result<Vec<WalletTx>> {
+        let mut heights: HashMap<String, i64> = HashMap::new();
+        for address in addresses {
+            for entry in self.fetch_address_history(address)? {
+                heights.insert(entry.tx_hash, entry.height);
+            }
+        }
+        // Unconfirmed transactions (height 0 or -1) first, then newest first
+        let mut entries: Vec<(String, i64)> = heights.into_iter().collect();
+        entries.sort_by_key(|&(_, height)| Reverse(if height > 0 { height } else { i64::MAX }));
+        entries.truncate(MAX_HISTORY);
+
+        let scripts = addresses.iter()
+            .map(|address| electrum_client::address_script(address))
+            .collect::<Result<HashSet<ScriptBuf>>>()?;
+        // Wallet transactions often spend each other's outputs
+        let mut fetched: HashMap<String, Transaction> = HashMap::new();
+        let mut fetch = |txid: &str| -> Result<Transaction> {
+            if let Some(tx) = fetched.get(txid) {
+                return Ok(tx.clone());
+            }
+            let tx = self.fetch_transaction(txid)?;
+            fetched.insert(txid.to_string(), tx.clone());
+            Ok(tx)
+        };
+
+        let mut history = Vec::with_capacity(entries.len());
+        for (txid, height) in entries {
+            let tx = fetch(&txid)?;
+            let received: u64 = tx.output.iter()
+                .filter(|output| scripts.contains(&output.script_pubkey))
+                .map(|output| output.value.to_sat())
+                .sum();
+            let mut spent = 0;
+            // Coinbase inputs have no previous output
+            for input in tx.input.iter().filter(|input| !input.previous_output.is_null()) {
+                let previous = fetch(&input.previous_output.txid.to_string())?;
+                if let Some(output) = previous.output.get(input.previous_output.vout as usize) {
+                    if scripts.contains(&output.script_pubkey) {
+                        spent += output.value.to_sat();
+                    }
+                }
+            }
+
+            let block_time = match u32::try_from(height) {
+                Ok(height) if height > 0 => Some(self.fetch_block_time(height)?),
+                _ => None,
+            };
+            history.push(WalletTx {
+                txid,
+                net_value: received as i64 - spent as i64,
+                confirmed: block_time.is_some(),
+                block_time,
+            });
+        }
+        Ok(history)
+    }
+}
+
+/// Scan the receive and change chains of a wallet up to its gap limit and
+/// aggregate the balances and transaction history of all used addresses
+pub fn scan_wallet(index: &dyn AddressIndex, wallet: &WatchedWallet) -> Result<WalletSummary> {
+    let key = AccountKey::parse(&wallet.xpub)?;
+    let gap_limit = wallet.gap_limit.max(1);
+
+    let mut confirmed = 0;
+    let mut unconfirmed = 0;
+    let mut used_addresses = Vec::new();
+    let mut next_receive_address = String::new();
+
+    for chain in [Chain::Receive, Chain::Change] {
+        let mut unused_in_a_row = 0;
+        let mut position = 0;
+        while unused_in_a_row < gap_limit && position < MAX_ADDRESSES_PER_CHAIN {
+            let address = key.address(chain, position)?;
+
+            if let Some((address_confirmed, address_unconfirmed)) = index.address_balance(&address)? {
+                confirmed += address_confirmed;
+                unconfirmed += address_unconfirmed;
+                used_addresses.push(address);
+                unused_in_a_row = 0;
+            } else {
+                if chain == Chain::Receive && next_receive_address.is_empty() {
+                    next_receive_address = address;
+                }
+                unused_in_a_row += 1;
+            }
+            position += 1;
+        }
+    }
+
+    let mut history = index.wallet_history(&used_addresses)?;
+    // Unconfirmed transactions first, then newest first
+    history.sort_by_key(|tx| Reverse(tx.block_time.unwrap_or(u32::MAX)));
+    history.truncate(MAX_HISTORY);
+
+    Ok(WalletSummary {
+        kind: key.kind(),
+        confirmed,
+        unconfirmed,
+        used_addresses: used_addresses.len(),
+        next_receive_address,
+        history,
+    })
+}
+
+// Net satoshis a transaction moves into (positive) or out of (negative) the wallet
+fn net_value_for_wallet(tx: &MempoolTx, addresses: &HashSet<&str>) -> i64 {
+    let received: u64 = tx.vout.iter()
+        .filter(|output| output.scriptpubkey_address.as_deref().is_some_and(|address| addresses.contains(address)))
+        .map(|output| output.value)
+        .sum();
+    let spent: u64 = tx.vin.iter()
+        .filter_map(|input| input.prevout.as_ref())
+        .filter(|prevout| prevout.scriptpubkey_address.as_deref().is_some_and(|address| addresses.contains(address)))
+        .map(|prevout| prevout.value)
+        .sum();
+    received as i64 - spent as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::block::{Header, Version};
+    use bitcoin::consensus::encode::serialize_hex;
+    use bitcoin::hashes::{sha256, Hash};
+    use bitcoin::hex::DisplayHex;
+    use bitcoin::{BlockHash, CompactTarget, OutPoint, TxMerkleNode};
+    use serde_json::{json, Value};
+    use crate::test_support;
+
+    // First receive and change addresses of the BIP84 test vector wallet
+    const RECEIVE: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+    const CHANGE: &str = "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el";
+    const BLOCK_TIME: u32 = 1_700_000_000;
+
+    fn scripthash(address: &str) -> String {
+        let script = electrum_client::address_script(address).unwrap();
+        let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+        hash.reverse();
+        hash.to_lower_hex_string()
+    }
+
+    #[test]
+    fn electrum_history_nets_outputs_against_spent_prevouts() {
+        let external = ScriptBuf::new_op_return([1u8; 4]);
+        let receive = electrum_client::address_script(RECEIVE).unwrap();
+        let change = electrum_client::address_script(CHANGE).unwrap();
+
+        // Coinbase paying someone else, who then funds the wallet
+        let coinbase = test_support::transaction(&[OutPoint::null()], &[(external.clone(), 200_000)]);
+        let funding = test_support::transaction(
+            &[OutPoint { txid: coinbase.compute_txid(), vout: 0 }],
+            &[(receive, 100_000)],
+        );
+        // Pays 30,000 out, 69,000 back to change and 1,000 in fees
+        let spend = test_support::transaction(
+            &[OutPoint { txid: funding.compute_txid(), vout: 0 }],
+            &[(external, 30_000), (change, 69_000)],
+        );
+        let header = Header {
+            version: Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time: BLOCK_TIME,
+            bits: CompactTarget::from_consensus(0x1d00ffff),
+            nonce: 0,
+        };
+
+        let txs: HashMap<String, String> = [&coinbase, &funding, &spend].into_iter()
+            .map(|tx| (tx.compute_txid().to_string(), serialize_hex(tx)))
+            .collect();
+        let (funding_id, spend_id) = (funding.compute_txid().to_string(), spend.compute_txid().to_string());
+        let histories: HashMap<String, Value> = HashMap::from([
+            (scripthash(RECEIVE), json!([
+                { "tx_hash": funding_id, "height": 800_000 },
+                { "tx_hash": spend_id, "height": 0, "fee": 1000 },
+            ])),
+            (scripthash(CHANGE), json!([{ "tx_hash": spend_id, "height": 0, "fee": 1000 }])),
+        ]);
+        let header_hex = serialize_hex(&header);
+        let server = test_support::serve_electrum(move |method, params| match method {
+            "blockchain.transaction.get" => json!(txs[params[0].as_str().unwrap()]),
+            "blockchain.scripthash.get_history" => histories.get(params[0].as_str().unwrap()).cloned().unwrap_or(json!([])),
+            "blockchain.block.header" => {
+                assert_eq!(params[0], 800_000);
+                json!(header_hex)
+            },
+            other => panic!("unexpected call {}", other),
+        });
+
+        let client = ElectrumClient::with_url(&server, false);
+        let history = client.wallet_history(&[RECEIVE.to_string(), CHANGE.to_string()]).unwrap();
+
+        assert_eq!(history.len(), 2);
+        // Unconfirmed first
+        assert_eq!(history[0].txid, spend_id);
+        assert_eq!(history[0].net_value, -31_000);
+        assert!(!history[0].confirmed);
+        assert_eq!(history[0].block_time, None);
+        assert_eq!(history[1].txid, funding_id);
+        assert_eq!(history[1].net_value, 100_000);
+        assert!(history[1].confirmed);
+        assert_eq!(history[1].block_time, Some(BLOCK_TIME));
+    }
+
+    #[test]
+    fn electrum_balance_is_none_for_unused_addresses() {
+        let used = scripthash(RECEIVE);
+        let server = test_support::serve_electrum(move |method, params| match method {
+            "blockchain.scripthash.get_history" if params[0] == used.as_str() => json!([{ "tx_hash": "00", "height": 1 }]),
+            "blockchain.scripthash.get_history" => json!([]),
+            "blockchain.scripthash.get_balance" => json!({ "confirmed": 5000, "unconfirmed": -200 }),
+            other => panic!("unexpected call {}", other),
+        });
+
+        let client = ElectrumClient::with_url(&server, false);
+        assert_eq!(client.address_balance(RECEIVE).unwrap(), Some((5000, -200)));
+        assert_eq!(client.address_balance(CHANGE).unwrap(), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::{HeaderName, HeaderValue};
+use tungstenite::HandshakeError;
+use url::Url;
+
+use crate::proxy::ProxySource;
+use crate::transport::{self, Stream, TlsOptions};
+
+// How long the opening handshake may keep hitting read timeouts before giving up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+/// Blocking WebSocket client supporting ws:// and wss:// URLs. Framing is done by
+/// tungstenite; the connection itself goes through `transport` so proxy routing
+/// and certificate pinning apply.
+pub struct WebSocket {
+    socket: tungstenite::WebSocket<Box<dyn Stream>>,
+}
+
+impl WebSocket {
+    /// Connect to a ws:// or wss:// URL, routed for `source`, and perform the
+    /// opening handshake. `read_timeout` bounds how long `read_message` blocks without data.
+    pub fn connect(source: ProxySource, url: &str, read_timeout: Duration) -> Result<Self> {
+        Self::connect_with(source, url, read_timeout, &TlsOptions::default(), &[])
+    }
+
+    /// Like `connect`, checking a wss:// server's certificate with `tls` and
+    /// sending `headers` (e.g. Authorization) with the handshake
+    pub fn connect_with(source: ProxySource, url: &str, read_timeout: Duration, tls: &TlsOptions, headers: &[(String, String)]) -> Result<Self> {
+        let parsed = Url::parse(url)
+            .map_err(|e| anyhow!("Invalid WebSocket URL '{}': {}", url, e))?;
+
+        let secure = match parsed.scheme() {
+            "ws" => false,
+            "wss" => true,
+            scheme => return Err(anyhow!("Unsupported WebSocket scheme: {}", scheme)),
+        };
+        let host = parsed.host_str()
+            .ok_or_else(|| anyhow!("WebSocket URL has no host: {}", url))?
+            .to_string();
+        let port = parsed.port_or_known_default()
+            .ok_or_else(|| anyhow!("WebSocket URL has no port: {}", url))?;
+
+        let mut request = url.into_client_request()
+            .map_err(|e| anyhow!("Invalid WebSocket URL '{}': {}", url, e))?;
+        let user_agent = format!("btc-ticker/{}", env!("CARGO_PKG_VERSION"));
+        let extra_headers = std::iter::once(("User-Agent", user_agent.as_str()))
+            .chain(headers.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        for (name, value) in extra_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| anyhow!("Invalid WebSocket header name '{}': {}", name, e))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| anyhow!("Invalid value for WebSocket header '{}': {}", name, e))?;
+            request.headers_mut().insert(name, value);
+        }
+
+        let stream = transport::connect(source, &host, port, secure, tls, read_timeout)?;
+
+        // The stream has a read timeout, so the handshake can be interrupted and resumed
+        let started = Instant::now();
+        let mut handshake = tungstenite::client(request, stream);
+        loop {
+            match handshake {
+                Ok((socket, _)) => return Ok(WebSocket { socket }),
+                Err(HandshakeError::Interrupted(partial)) if started.elapsed() < HANDSHAKE_TIMEOUT => {
+                    handshake = partial.handshake();
+                },
+                Err(HandshakeError::Interrupted(_)) => return Err(anyhow!("WebSocket handshake timed out")),
+                Err(HandshakeError::Failure(e)) => return Err(anyhow!("WebSocket handshake failed: {}", e)),
+            }
+        }
+    }
+
+    /// Send a text message
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.socket.send(tungstenite::Message::text(text))
+            .map_err(|e| anyhow!("Failed to send WebSocket message: {}", e))
+    }
+
+    /// Send a close frame; errors are ignored since the connection is going away
+    pub fn close(&mut self) {
+        self.socket.close(None).ok();
+        self.socket.flush().ok();
+    }
+
+    /// Read the next complete message, answering pings along the way.
+    /// Returns an error of kind WouldBlock/TimedOut when the read timeout expires;
+    /// anything received so far is kept and the next call picks up where this one stopped.
+    pub fn read_message(&mut self) -> Result<Message> {
+        loop {
+            match self.socket.read() {
+                Ok(tungstenite::Message::Text(text)) => return Ok(Message::Text(text.as_str().to_string())),
+                Ok(tungstenite::Message::Binary(data)) => return Ok(Message::Binary(data.to_vec())),
+                Ok(tungstenite::Message::Close(_)) => {
+                    // tungstenite has queued the close reply; push it out
+                    self.socket.flush().ok();
+                    return Ok(Message::Close);
+                },
+                // Pongs to pings are queued by tungstenite and sent by the next read
+                Ok(_) => {},
+                // Keep IO errors as io::Error so `is_timeout` can recognise them
+                Err(tungstenite::Error::Io(e)) => return Err(e.into()),
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Err(anyhow!("Server closed the WebSocket connection"));
+                },
+                Err(e) => return Err(anyhow!("WebSocket error: {}", e)),
+            }
+        }
+    }
+}
+
+/// Whether an error returned by `read_message` is just the read timeout expiring
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    transport::is_timeout(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+    use tungstenite::handshake::server::{Request, Response};
+
+    type ServerSocket = tungstenite::WebSocket<TcpStream>;
+
+    const READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+    // Accept one connection, check the upgrade request with `check_request` and
+    // hand the socket to `server`
+    #[allow(clippy::result_large_err)] // the callback's error type is tungstenite's
+    fn serve_checked(
+        check_request: impl FnOnce(&Request) + Send + 'static,
+        server: impl FnOnce(ServerSocket) + Send + 'static,
+    ) -> (String, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/socket?feed=blocks", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+                assert_eq!(request.uri(), "/socket?feed=blocks");
+                check_request(request);
+                Ok(response)
+            }).unwrap();
+            server(socket);
+        });
+
+        (url, handle)
+    }
+
+    fn serve(server: impl FnOnce(ServerSocket) + Send + 'static) -> (String, JoinHandle<()>) {
+        serve_checked(|_| {}, server)
+    }
+
+    // Unmasked server frame, written raw so tests control how it is split up
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 | opcode } else { opcode }];
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn connect(url: &str) -> WebSocket {
+        WebSocket::connect(ProxySource::Exchanges, url, READ_TIMEOUT).unwrap()
+    }
+
+    #[test]
+    fn exchanges_text_messages() {
+        let (url, server) = serve(|mut socket| {
+            socket.send(tungstenite::Message::text("hello")).unwrap();
+            assert_eq!(socket.read().unwrap(), tungstenite::Message::text("subscribe"));
+        });
+
+        let mut socket = connect(&url);
+        assert!(matches!(socket.read_message().unwrap(), Message::Text(text) if text == "hello"));
+        socket.send_text("subscribe").unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn sends_extra_headers() {
+        let (url, server) = serve_checked(
+            |request| {
+                assert_eq!(request.headers()["authorization"], "Basic dXNlcjpwYXNz");
+                assert!(request.headers()["user-agent"].to_str().unwrap().starts_with("btc-ticker/"));
+            },
+            |mut socket| socket.close(None).unwrap(),
+        );
+
+        let headers = [("Authorization".to_string(), "Basic dXNlcjpwYXNz".to_string())];
+        WebSocket::connect_with(ProxySource::Exchanges, &url, READ_TIMEOUT, &TlsOptions::default(), &headers).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn answers_pings() {
+        let (url, server) = serve(|mut socket| {
+            socket.send(tungstenite::Message::Ping("are you there".into())).unwrap();
+            socket.send(tungstenite::Message::text("after ping")).unwrap();
+            assert_eq!(socket.read().unwrap(), tungstenite::Message::Pong("are you there".into()));
+        });
+
+        let mut socket = connect(&url);
+        assert!(matches!(socket.read_message().unwrap(), Message::Text(text) if text == "after ping"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn echoes_close() {
+        let (url, server) = serve(|mut socket| {
+            socket.close(None).unwrap();
+            assert!(matches!(socket.read(), Ok(tungstenite::Message::Close(_))));
+        });
+
+        let mut socket = connect(&url);
+        assert!(matches!(socket.read_message().unwrap(), Message::Close));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn keeps_partial_frames_across_timeouts() {
+        let long_message = "x".repeat(300);
+        let long_frame = frame(true, 0x1, long_message.as_bytes());
+
+        let (url, server) = serve(move |mut socket| {
+            let stream = socket.get_mut();
+            // Stop in the middle of the extended length, then in the middle of the payload
+            for chunk in [&long_frame[..3], &long_frame[3..100], &long_frame[100..]] {
+                stream.write_all(chunk).unwrap();
+                thread::sleep(READ_TIMEOUT * 2);
+            }
+            // A fragmented message whose continuation arrives after a timeout
+            stream.write_all(&frame(false, 0x1, b"frag")).unwrap();
+            thread::sleep(READ_TIMEOUT * 2);
+            stream.write_all(&frame(true, 0x0, b"mented")).unwrap();
+        });
+
+        let mut socket = connect(&url);
+        let mut timeouts = 0;
+        let mut messages = Vec::new();
+        while messages.len() < 2 {
+            match socket.read_message() {
+                Ok(Message::Text(text)) => messages.push(text),
+                Ok(message) => panic!("unexpected message: {:?}", message),
+                Err(e) if is_timeout(&e) => timeouts += 1,
+                Err(e) => panic!("read failed: {}", e),
+            }
+        }
+
+        assert!(timeouts >= 3, "only {} timeouts", timeouts);
+        assert_eq!(messages, vec![long_message.clone(), "fragmented".to_string()]);
+        server.join().unwrap();
+    }
+}